@@ -200,9 +200,11 @@ document.addEventListener("DOMContentLoaded", async () => {
     const photographerName = photographerNameInput.value.trim();
 
     const result = await invoke("start_processing", {
-      inputDir: inputDir,
-      outputDir: outputDir,
-      photographerName: photographerName,
+      options: {
+        inputDir: inputDir,
+        outputDir: outputDir,
+        photographerName: photographerName,
+      },
     });
 
     if (!result.success) {
@@ -244,6 +246,8 @@ document.addEventListener("DOMContentLoaded", async () => {
       const assetUrl = convertFileSrc(data.path);
       previewImage.src = assetUrl;
       currentFile.textContent = data.file;
+    } else if (data.skipped) {
+      console.info(`Skipped ${data.file}: ${data.skipped}`);
     } else {
       console.error(`Error processing ${data.file}: ${data.error}`);
     }
@@ -0,0 +1,166 @@
+//! Framing a batch of photos the same way in every front end: the filters,
+//! then the design rules or every photo in several designs, then signature
+//! sidecars. Each photo's outcome is reported as a [`BatchEvent`]; the app
+//! and the CLI only present the events.
+
+use std::path::{Path, PathBuf};
+
+use crate::contact_sheet::SheetImage;
+use crate::engine::{self, DesignOutput, FramedPhoto, Watermarker};
+use crate::filters::BatchFilter;
+use crate::rules::RuleWatermarkers;
+use crate::sidecar::SidecarSigner;
+
+/// Settings of a batch run.
+pub struct Batch<'a> {
+    /// Photos left out on their metadata, before they are decoded
+    pub filter: &'a BatchFilter,
+    /// Picks the design of each photo when there are no `variants`
+    pub rules: &'a RuleWatermarkers<'a>,
+    /// Every photo in each of these designs instead, decoded once
+    pub variants: &'a [Watermarker],
+    /// How the outputs of the `variants` are told apart
+    pub design_output: DesignOutput,
+    /// Signs every output; the caller finishes it after the run
+    pub signer: Option<&'a mut SidecarSigner>,
+}
+
+/// What happened to a photo of the batch, in order.
+#[derive(Debug)]
+pub enum BatchEvent<'a> {
+    /// The `index`th photo is about to be checked and framed
+    Started { index: usize, input: &'a Path },
+    /// The filters left the photo out
+    Skipped { input: &'a Path, reason: String },
+    /// One output of the photo was written, and signed if asked to
+    Framed { input: &'a Path, output: &'a Path, overrides: &'a [&'static str] },
+    /// The photo couldn't be checked, or one of its outputs failed
+    Failed { input: &'a Path, error: String },
+}
+
+/// Totals of a batch run.
+#[derive(Debug, Default)]
+pub struct BatchSummary {
+    /// The first output of each framed photo, with the input its caption
+    /// is read from, ready for a contact sheet
+    pub framed: Vec<SheetImage>,
+    /// Photos the filters left out
+    pub skipped: usize,
+    /// Failed outputs, plus photos the filters couldn't check
+    pub failed: usize,
+}
+
+/// Frames each `(input, output)` pair of `photos`, calling `on_event` as it
+/// goes. With `variants`, `output` is the path their outputs are derived
+/// from.
+pub fn frame_batch(photos: &[(PathBuf, PathBuf)], batch: Batch, mut on_event: impl FnMut(BatchEvent)) -> BatchSummary {
+    let Batch { filter, rules, variants, design_output, mut signer } = batch;
+    let mut summary = BatchSummary::default();
+    for (index, (input, output)) in photos.iter().enumerate() {
+        on_event(BatchEvent::Started { index, input });
+        match filter.skip_reason(input) {
+            Ok(None) => {}
+            Ok(Some(reason)) => {
+                summary.skipped += 1;
+                on_event(BatchEvent::Skipped { input, reason });
+                continue;
+            }
+            Err(error) => {
+                summary.failed += 1;
+                on_event(BatchEvent::Failed { input, error });
+                continue;
+            }
+        }
+
+        let outputs: Vec<(&Watermarker, PathBuf)> = if variants.is_empty() {
+            vec![(rules.select_path(input), output.clone())]
+        } else {
+            variants
+                .iter()
+                .map(|wm| (wm, design_output.path_for(output, wm.design_id())))
+                .collect()
+        };
+        let framed = engine::process_path_designs(input, &outputs)
+            .unwrap_or_else(|e| FramedPhoto { results: vec![Err(e); outputs.len()], ..Default::default() });
+        for ((_, output), result) in outputs.iter().zip(framed.results) {
+            let result = result.and_then(|_| match signer.as_deref_mut() {
+                Some(signer) => signer.sign(output),
+                None => Ok(()),
+            });
+            match result {
+                Ok(()) => {
+                    if summary.framed.last().is_none_or(|image| image.source.as_ref() != Some(input)) {
+                        summary.framed.push(SheetImage { path: output.clone(), source: Some(input.clone()) });
+                    }
+                    on_event(BatchEvent::Framed { input, output, overrides: &framed.overrides });
+                }
+                Err(error) => {
+                    summary.failed += 1;
+                    on_event(BatchEvent::Failed { input, error });
+                }
+            }
+        }
+    }
+    summary
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::DesignRules;
+    use crate::test_support::TempDir;
+    use image::{ImageFormat, Rgb, RgbImage};
+    use std::fs;
+
+    #[test]
+    fn reports_every_photo_and_pairs_outputs_with_inputs() {
+        let dir = TempDir::new("batch");
+        let photo = |name: &str, width: u32| {
+            let path = dir.join(name);
+            RgbImage::from_pixel(width, 200, Rgb([90, 120, 150])).save_with_format(&path, ImageFormat::Png).unwrap();
+            (path, dir.join("out").join(name))
+        };
+        let mut photos = vec![photo("a.png", 300), photo("b.png", 300)];
+        photos.push((dir.join("missing.png"), dir.join("out/missing.png")));
+        // A fifth as large as the others, so a minimum size leaves it out
+        let small = dir.join("small.png");
+        fs::write(&small, &fs::read(&photos[0].0).unwrap()[..100]).unwrap();
+        photos.push((small, dir.join("out/small.png")));
+        let min_size = fs::metadata(&photos[0].0).unwrap().len() / 2;
+
+        let base = Watermarker::builder().system_fonts(false).build();
+        let variants = ["classic", "dark"].map(|id| base.with_design(id, None));
+        let rules = DesignRules::default();
+        let rules = rules.watermarkers(&base, |_| None);
+        let filter = BatchFilter { min_size: Some(min_size), ..Default::default() };
+        let batch = Batch { filter: &filter, rules: &rules, variants: &variants, design_output: DesignOutput::Suffix, signer: None };
+
+        let mut events = Vec::new();
+        let summary = frame_batch(&photos, batch, |event| {
+            events.push(match event {
+                BatchEvent::Started { index, .. } => format!("start {}", index),
+                BatchEvent::Skipped { input, .. } => format!("skip {}", input.file_name().unwrap().to_string_lossy()),
+                BatchEvent::Framed { output, .. } => format!("framed {}", output.file_name().unwrap().to_string_lossy()),
+                BatchEvent::Failed { input, .. } => format!("fail {}", input.file_name().unwrap().to_string_lossy()),
+            })
+        });
+        assert_eq!(
+            events,
+            [
+                "start 0", "framed a-classic.png", "framed a-dark.png",
+                "start 1", "framed b-classic.png", "framed b-dark.png",
+                "start 2", "fail missing.png",
+                "start 3", "skip small.png",
+            ]
+        );
+        assert_eq!((summary.skipped, summary.failed), (1, 1));
+        let sheet: Vec<_> = summary.framed.iter().map(|i| (i.path.clone(), i.source.clone().unwrap())).collect();
+        assert_eq!(
+            sheet,
+            [
+                (dir.join("out/a-classic.png"), photos[0].0.clone()),
+                (dir.join("out/b-classic.png"), photos[1].0.clone()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,526 @@
+//! Command-line front end for the watermark engine.
+
+use app_lib::batch::{self, Batch, BatchEvent};
+use app_lib::brands::BrandRegistry;
+use app_lib::c2pa::{self, C2paSigner};
+use app_lib::camera_names::{CameraNames, ModelNameStyle};
+use app_lib::color::ColorSpace;
+use app_lib::contact_sheet::{ContactSheetOptions, SheetImage};
+use app_lib::designs;
+use app_lib::filters::BatchFilter;
+use app_lib::histogram::HistogramStyle;
+use app_lib::palette::FrameColor;
+use app_lib::rights::Rights;
+use app_lib::rules::{DesignRules, Orientation};
+use app_lib::sidecar::{self, SidecarSigner, SignatureMode};
+use app_lib::{
+    verify_watermark, DesignOutput, FrostOptions, LogoSource, Watermarker, WatermarkerBuilder,
+};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+const USAGE: &str = "\
+Usage:
+  digicamwm-cli frame [OPTIONS] <INPUT>...   Add a frame to images or folders of images
+  digicamwm-cli svg [OPTIONS] <INPUT>        Print the frame SVG for one image
+  digicamwm-cli sheet [OPTIONS] <INPUT>...   Make a contact sheet (-o sheet.pdf or sheet.jpg)
+  digicamwm-cli verify <INPUT>...            Read the invisible watermark from images
+  digicamwm-cli credentials <INPUT>...       Check the C2PA content credentials of JPEGs
+  digicamwm-cli verify-signatures --public-key <FILE> <INPUT>...
+                                             Check delivered files against their signatures
+  digicamwm-cli designs                      List available designs
+
+Options:
+  -o, --output <PATH>       Output file (single input) or folder [default: <input folder>/framed]
+  -d, --design <ID>         Design ID [default: classic]
+      --designs <IDS>       Comma-separated design IDs; frames every image in each of them
+      --design-output <MODE> suffix (photo-dark.jpg) or subfolder (dark/photo.jpg) for --designs [default: suffix]
+      --rules <FILE>        Pick the design per image from its metadata (design_rules.json)
+  -p, --photographer <NAME> Photographer name shown in the frame and written as the creator
+      --copyright <NOTICE>  Copyright notice written into the output's EXIF and XMP
+      --usage-terms <TEXT>  Usage terms written into the output's XMP
+      --contact-email <ADDR> Creator email written into the output's XMP
+      --contact-url <URL>   Creator website written into the output's XMP
+      --contact-phone <NUM> Creator phone number written into the output's XMP
+      --credit <TEXT>       Credit line written into the output's XMP
+      --logos <DIR>         Folder with brand logo PNGs (e.g. assets/models)
+      --brands <FILE>       Extra brand aliases (brands.json)
+      --camera-names <FILE> Extra model names (camera_names.json)
+      --raw-model-names     Print the EXIF model as recorded (e.g. ILCE-7M4)
+      --color-space <SPACE> srgb, display-p3, adobe-rgb or keep-source [default: srgb]
+      --frame-color <MODE>  design, or adaptive to tint the frame with the photo's colour [default: design]
+      --frost-blur <FRACTION> Frosted design blur radius, as a fraction of the frame height [default: 0.3]
+      --frost-dim <AMOUNT>  Frosted design darkening, 0 to 1 [default: 0.45]
+      --swatches <N>        Show the photo's N dominant colours with their hex codes, up to 8
+      --histogram <STYLE>   Histogram next to the exposure info: filled, line or channels
+      --qr <URL>            QR code linking to URL; {name} is replaced by the file name
+      --show-title          Print the XMP title (or caption) from Lightroom etc. before the date
+      --min-rating <STARS>  Frame only photos rated at least STARS in XMP; others are skipped
+      --label <LABEL>       Frame only photos with this XMP colour label (e.g. Red)
+      --from <DATE>         Frame only photos taken on or after DATE (2024-05-01, or 2024-05-01 18:30)
+      --to <DATE>           Frame only photos taken on or before DATE
+      --make <TEXT>         Frame only photos whose camera make contains TEXT
+      --model <TEXT>        Frame only photos whose camera model contains TEXT
+      --lens <TEXT>         Frame only photos whose lens contains TEXT
+      --orientation <O>     Frame only portrait or landscape photos
+      --min-size <SIZE>     Skip files smaller than SIZE, in bytes or with K, M or G
+      --max-size <SIZE>     Skip files larger than SIZE
+      --mark <ID>           Hide photographer ID (0 to 4294967295) and an image ID in the photo
+      --c2pa-cert <FILE>    Sign JPEG output with a C2PA manifest, using this PEM certificate chain
+      --c2pa-key <FILE>     P-256 private key (PEM) for --c2pa-cert
+      --sign-key <FILE>     Sign every output with this Ed25519 key (PEM), created if missing
+      --signatures <MODE>   sidecar for a .sig per file, or manifest for signatures.json [default: sidecar]
+      --public-key <FILE>   Key to check signatures with (public or private PEM)
+      --transparent-frame   Keep the frame background's transparency in PNG/WebP output
+      --memory-budget <MIB> Peak memory per image; larger images are streamed [default: 2048]
+      --columns <N>         Contact sheet thumbnails per row [default: 4]
+      --rows <N>            Contact sheet rows per page [default: 5]
+      --source <DIR>        Source photos to read contact sheet captions from, for framed images without EXIF
+      --font <FAMILY>       Font family to use before the design's bundled font
+      --font-dir <DIR>      Extra font folder (repeatable)
+      --no-system-fonts     Use bundled and --font-dir fonts only
+";
+
+const IMAGE_EXTENSIONS: [&str; 6] = ["jpg", "jpeg", "png", "tif", "tiff", "webp"];
+
+struct Args {
+    command: String,
+    inputs: Vec<PathBuf>,
+    output: Option<PathBuf>,
+    builder: WatermarkerBuilder,
+    signer: Option<SidecarSigner>,
+    public_key: Option<PathBuf>,
+    sheet: ContactSheetOptions,
+    designs: Vec<String>,
+    design_output: DesignOutput,
+    rules: DesignRules,
+    filter: BatchFilter,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut args = std::env::args().skip(1);
+    let command = args.next().ok_or_else(|| USAGE.to_string())?;
+
+    let mut inputs = Vec::new();
+    let mut output = None;
+    let mut builder = Watermarker::builder();
+    let mut font_dirs = Vec::new();
+    let mut frost = FrostOptions::default();
+    let mut c2pa_cert = None;
+    let mut c2pa_key = None;
+    let mut sign_key = None;
+    let mut signature_mode = SignatureMode::default();
+    let mut public_key = None;
+    let mut sheet = ContactSheetOptions::default();
+    let mut design_ids = Vec::new();
+    let mut design_output = DesignOutput::default();
+    let mut rules = DesignRules::default();
+    let mut filter = BatchFilter::default();
+    let mut rights = Rights::default();
+
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| args.next().ok_or_else(|| format!("{} needs a value", name));
+        match arg.as_str() {
+            "-o" | "--output" => output = Some(PathBuf::from(value(&arg)?)),
+            "-d" | "--design" => builder = builder.design(&value(&arg)?),
+            "--designs" => {
+                let known = designs::get_design_list();
+                for id in value(&arg)?.split(',').map(str::trim).filter(|id| !id.is_empty()) {
+                    if !known.iter().any(|d| d.id == id) {
+                        return Err(format!("Unknown design {}", id));
+                    }
+                    design_ids.push(id.to_string());
+                }
+            }
+            "--design-output" => {
+                let name = value(&arg)?;
+                design_output = DesignOutput::from_name(&name)
+                    .ok_or_else(|| format!("Unknown design output {}", name))?;
+            }
+            "--rules" => rules = DesignRules::from_file(Path::new(&value(&arg)?))?,
+            "-p" | "--photographer" => builder = builder.photographer_name(&value(&arg)?),
+            "--copyright" => rights.copyright = Some(value(&arg)?),
+            "--usage-terms" => rights.usage_terms = Some(value(&arg)?),
+            "--contact-email" => rights.contact_email = Some(value(&arg)?),
+            "--contact-url" => rights.contact_url = Some(value(&arg)?),
+            "--contact-phone" => rights.contact_phone = Some(value(&arg)?),
+            "--credit" => rights.credit = Some(value(&arg)?),
+            "--logos" => builder = builder.logos(LogoSource::Directory(value(&arg)?.into())),
+            "--brands" => {
+                let path = PathBuf::from(value(&arg)?);
+                let mut brands = BrandRegistry::builtin();
+                brands.extend_from_file(&path)?;
+                builder = builder.brands(brands);
+            }
+            "--camera-names" => {
+                let path = PathBuf::from(value(&arg)?);
+                let mut names = CameraNames::builtin();
+                names.extend_from_file(&path)?;
+                builder = builder.camera_names(names);
+            }
+            "--raw-model-names" => builder = builder.model_names(ModelNameStyle::Raw),
+            "--color-space" => {
+                let name = value(&arg)?;
+                let space = ColorSpace::from_name(&name)
+                    .ok_or_else(|| format!("Unknown colour space {}", name))?;
+                builder = builder.color_space(space);
+            }
+            "--frame-color" => {
+                let name = value(&arg)?;
+                let mode = FrameColor::from_name(&name)
+                    .ok_or_else(|| format!("Unknown frame colour mode {}", name))?;
+                builder = builder.frame_color(mode);
+            }
+            "--frost-blur" => frost.blur = parse_number(&arg, &value(&arg)?)?,
+            "--frost-dim" => frost.dim = parse_number(&arg, &value(&arg)?)?,
+            "--swatches" => {
+                let count: usize = value(&arg)?
+                    .parse()
+                    .map_err(|_| "--swatches takes a number of colours".to_string())?;
+                builder = builder.swatches(count);
+            }
+            "--histogram" => {
+                let name = value(&arg)?;
+                let style = HistogramStyle::from_name(&name)
+                    .ok_or_else(|| format!("Unknown histogram style {}", name))?;
+                builder = builder.histogram(Some(style));
+            }
+            "--qr" => builder = builder.qr_link(Some(value(&arg)?)),
+            "--show-title" => builder = builder.show_title(true),
+            "--min-rating" => {
+                let stars = value(&arg)?
+                    .parse()
+                    .map_err(|_| "--min-rating takes a number of stars".to_string())?;
+                filter.min_rating = Some(stars);
+            }
+            "--label" => filter.label = Some(value(&arg)?),
+            "--from" => filter.date_from = Some(value(&arg)?),
+            "--to" => filter.date_to = Some(value(&arg)?),
+            "--make" => filter.make = Some(value(&arg)?),
+            "--model" => filter.model = Some(value(&arg)?),
+            "--lens" => filter.lens = Some(value(&arg)?),
+            "--orientation" => {
+                let name = value(&arg)?;
+                filter.orientation =
+                    Some(Orientation::from_name(&name).ok_or_else(|| format!("Unknown orientation {}", name))?);
+            }
+            "--min-size" => filter.min_size = Some(parse_size(&arg, &value(&arg)?)?),
+            "--max-size" => filter.max_size = Some(parse_size(&arg, &value(&arg)?)?),
+            "--mark" => {
+                let id: u32 = value(&arg)?
+                    .parse()
+                    .map_err(|_| "--mark takes a numeric photographer ID".to_string())?;
+                builder = builder.invisible_mark(Some(id));
+            }
+            "--c2pa-cert" => c2pa_cert = Some(PathBuf::from(value(&arg)?)),
+            "--c2pa-key" => c2pa_key = Some(PathBuf::from(value(&arg)?)),
+            "--sign-key" => sign_key = Some(PathBuf::from(value(&arg)?)),
+            "--signatures" => {
+                let name = value(&arg)?;
+                signature_mode = SignatureMode::from_name(&name)
+                    .ok_or_else(|| format!("Unknown signature mode {}", name))?;
+            }
+            "--public-key" => public_key = Some(PathBuf::from(value(&arg)?)),
+            "--transparent-frame" => builder = builder.transparent_frame(true),
+            "--memory-budget" => {
+                let bytes = value(&arg)?
+                    .parse::<u64>()
+                    .ok()
+                    .and_then(|mb| mb.checked_mul(1024 * 1024))
+                    .ok_or_else(|| "--memory-budget takes a size in MiB".to_string())?;
+                builder = builder.memory_budget(bytes);
+            }
+            "--columns" => {
+                sheet.columns = value(&arg)?.parse().map_err(|_| "--columns takes a number".to_string())?;
+            }
+            "--rows" => sheet.rows = value(&arg)?.parse().map_err(|_| "--rows takes a number".to_string())?,
+            "--source" => sheet.source_dir = Some(PathBuf::from(value(&arg)?)),
+            "--font" => builder = builder.font_family(Some(value(&arg)?)),
+            "--font-dir" => font_dirs.push(PathBuf::from(value(&arg)?)),
+            "--no-system-fonts" => builder = builder.system_fonts(false),
+            "-h" | "--help" => return Err(USAGE.to_string()),
+            flag if flag.starts_with('-') => return Err(format!("Unknown option {}\n\n{}", flag, USAGE)),
+            _ => inputs.push(PathBuf::from(arg)),
+        }
+    }
+    match (c2pa_cert, c2pa_key) {
+        (Some(cert), Some(key)) => builder = builder.content_credentials(Some(C2paSigner::load(&cert, &key)?)),
+        (None, None) => {}
+        _ => return Err("--c2pa-cert and --c2pa-key go together".into()),
+    }
+    if !design_ids.is_empty() && !rules.is_empty() {
+        return Err("--designs and --rules can't be combined".into());
+    }
+    filter.validate()?;
+    let signer = sign_key
+        .map(|path| SidecarSigner::load_or_create(&path, signature_mode))
+        .transpose()?;
+
+    Ok(Args {
+        command,
+        inputs,
+        output,
+        builder: builder.font_dirs(font_dirs).frost(frost).rights(rights),
+        signer,
+        public_key,
+        sheet,
+        designs: design_ids,
+        design_output,
+        rules,
+        filter,
+    })
+}
+
+fn parse_number(name: &str, value: &str) -> Result<f32, String> {
+    value.parse().map_err(|_| format!("{} takes a number", name))
+}
+
+/// Bytes, or kilo-, mega- or gigabytes with a K, M or G suffix.
+fn parse_size(name: &str, value: &str) -> Result<u64, String> {
+    let upper = value.trim().to_uppercase();
+    let (number, unit) = match upper.trim_end_matches('B').char_indices().last() {
+        Some((i, 'K')) => (&upper[..i], 1_000),
+        Some((i, 'M')) => (&upper[..i], 1_000_000),
+        Some((i, 'G')) => (&upper[..i], 1_000_000_000),
+        _ => (upper.as_str(), 1),
+    };
+    let number: f64 = number.trim().parse().map_err(|_| format!("{} takes a size such as 500K or 20M", name))?;
+    Ok((number * unit as f64) as u64)
+}
+
+fn is_image(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|ext| IMAGE_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+}
+
+/// Expands folders into the images they contain, sorted by name.
+fn collect_images(inputs: &[PathBuf]) -> Result<Vec<PathBuf>, String> {
+    let mut images = Vec::new();
+    for input in inputs {
+        if input.is_dir() {
+            let entries = fs::read_dir(input)
+                .map_err(|e| format!("Failed to read {}: {}", input.display(), e))?;
+            let mut found: Vec<PathBuf> = entries
+                .flatten()
+                .map(|e| e.path())
+                .filter(|p| p.is_file() && is_image(p))
+                .collect();
+            found.sort();
+            images.extend(found);
+        } else {
+            images.push(input.clone());
+        }
+    }
+    Ok(images)
+}
+
+fn output_path_for(input: &Path, output: Option<&Path>, single: bool) -> PathBuf {
+    let file_name = input.file_name().unwrap_or_default();
+    match output {
+        Some(out) if single && !out.is_dir() => out.to_path_buf(),
+        Some(out) => out.join(file_name),
+        None => input
+            .parent()
+            .unwrap_or(Path::new("."))
+            .join("framed")
+            .join(file_name),
+    }
+}
+
+fn frame(args: Args) -> Result<(), String> {
+    let images = collect_images(&args.inputs)?;
+    if images.is_empty() {
+        return Err(format!("No input images\n\n{}", USAGE));
+    }
+
+    let watermarker = args.builder.build();
+    let font_family = watermarker.options().font_family.clone();
+    let variants: Vec<Watermarker> =
+        args.designs.iter().map(|id| watermarker.with_design(id, font_family.clone())).collect();
+    let by_rule = args.rules.watermarkers(&watermarker, |_| font_family.clone());
+
+    let mut signer = args.signer;
+    let single = images.len() == 1 && !args.inputs[0].is_dir();
+    let photos: Vec<(PathBuf, PathBuf)> = images
+        .iter()
+        .map(|input| (input.clone(), output_path_for(input, args.output.as_deref(), single)))
+        .collect();
+    let batch = Batch {
+        filter: &args.filter,
+        rules: &by_rule,
+        variants: &variants,
+        design_output: args.design_output,
+        signer: signer.as_mut(),
+    };
+    let summary = batch::frame_batch(&photos, batch, |event| match event {
+        BatchEvent::Started { .. } => {}
+        BatchEvent::Skipped { input, reason } => println!("{}: skipped ({})", input.display(), reason),
+        BatchEvent::Framed { input, output, overrides } => match overrides {
+            [] => println!("{} -> {}", input.display(), output.display()),
+            applied => println!("{} -> {} (overrides: {})", input.display(), output.display(), applied.join(", ")),
+        },
+        BatchEvent::Failed { input, error } => eprintln!("{}: {}", input.display(), error),
+    });
+    let (skipped, failed) = (summary.skipped, summary.failed);
+
+    if let Some(signer) = signer {
+        for manifest in signer.finish()? {
+            println!("Signatures written to {}", manifest.display());
+        }
+    }
+
+    if skipped > 0 {
+        println!("{} of {} images skipped by the filters", skipped, images.len());
+    }
+    if failed > 0 {
+        let total = (images.len() - skipped) * variants.len().max(1);
+        return Err(format!("{} of {} images failed", failed, total));
+    }
+    Ok(())
+}
+
+fn svg(args: Args) -> Result<(), String> {
+    let [input] = args.inputs.as_slice() else {
+        return Err(format!("svg takes exactly one input\n\n{}", USAGE));
+    };
+    let data = fs::read(input).map_err(|e| format!("Failed to read {}: {}", input.display(), e))?;
+    println!("{}", args.builder.build().render_frame_svg(&data)?);
+    Ok(())
+}
+
+fn sheet(args: Args) -> Result<(), String> {
+    let images = collect_images(&args.inputs)?;
+    if images.is_empty() {
+        return Err(format!("No input images\n\n{}", USAGE));
+    }
+    let output = args.output.unwrap_or_else(|| PathBuf::from("contact-sheet.pdf"));
+    let images: Vec<SheetImage> = images.into_iter().map(SheetImage::from).collect();
+    for page in args.builder.build().contact_sheet(&images, &output, &args.sheet)? {
+        println!("{}", page.display());
+    }
+    Ok(())
+}
+
+fn verify(args: Args) -> Result<(), String> {
+    let images = collect_images(&args.inputs)?;
+    if images.is_empty() {
+        return Err(format!("No input images\n\n{}", USAGE));
+    }
+
+    let mut unmarked = 0;
+    for input in &images {
+        let data = fs::read(input).map_err(|e| format!("Failed to read {}: {}", input.display(), e))?;
+        match verify_watermark(&data)? {
+            Some(mark) => println!(
+                "{}: photographer {}, image {:08x}",
+                input.display(),
+                mark.photographer_id,
+                mark.image_id
+            ),
+            None => {
+                println!("{}: no watermark found", input.display());
+                unmarked += 1;
+            }
+        }
+    }
+
+    if unmarked > 0 {
+        return Err(format!("{} of {} images carry no watermark", unmarked, images.len()));
+    }
+    Ok(())
+}
+
+fn credentials(args: Args) -> Result<(), String> {
+    let images = collect_images(&args.inputs)?;
+    if images.is_empty() {
+        return Err(format!("No input images\n\n{}", USAGE));
+    }
+
+    let mut failed = 0;
+    for input in &images {
+        let data = fs::read(input).map_err(|e| format!("Failed to read {}: {}", input.display(), e))?;
+        match c2pa::verify_jpeg(&data) {
+            Ok(Some(credentials)) => {
+                let camera = format!(
+                    "{} {}",
+                    credentials.camera_make.unwrap_or_default(),
+                    credentials.camera_model.unwrap_or_default()
+                );
+                println!(
+                    "{}: signed by {}; creator {}, camera {}, actions {}",
+                    input.display(),
+                    credentials.signer,
+                    credentials.creator.as_deref().unwrap_or("unknown"),
+                    if camera.trim().is_empty() { "unknown" } else { camera.trim() },
+                    credentials.actions.join(", ")
+                );
+            }
+            Ok(None) => {
+                println!("{}: no content credentials", input.display());
+                failed += 1;
+            }
+            Err(e) => {
+                println!("{}: invalid content credentials: {}", input.display(), e);
+                failed += 1;
+            }
+        }
+    }
+
+    if failed > 0 {
+        return Err(format!("{} of {} images carry no valid content credentials", failed, images.len()));
+    }
+    Ok(())
+}
+
+fn verify_signatures(args: Args) -> Result<(), String> {
+    let key_path = args.public_key.ok_or_else(|| format!("verify-signatures needs --public-key\n\n{}", USAGE))?;
+    let key = sidecar::load_verifying_key(&key_path)?;
+    let images = collect_images(&args.inputs)?;
+    if images.is_empty() {
+        return Err(format!("No input images\n\n{}", USAGE));
+    }
+
+    let mut failed = 0;
+    for input in &images {
+        match sidecar::verify_file(input, &key) {
+            Ok(()) => println!("{}: signature valid", input.display()),
+            Err(e) => {
+                println!("{}: {}", input.display(), e);
+                failed += 1;
+            }
+        }
+    }
+
+    if failed > 0 {
+        return Err(format!("{} of {} files failed verification", failed, images.len()));
+    }
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let result = parse_args().and_then(|args| match args.command.as_str() {
+        "frame" => frame(args),
+        "svg" => svg(args),
+        "sheet" => sheet(args),
+        "verify" => verify(args),
+        "credentials" => credentials(args),
+        "verify-signatures" => verify_signatures(args),
+        "designs" => {
+            for design in designs::get_design_list() {
+                println!("{:<10} {}", design.id, design.description);
+            }
+            Ok(())
+        }
+        other => Err(format!("Unknown command {}\n\n{}", other, USAGE)),
+    });
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
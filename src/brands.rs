@@ -0,0 +1,369 @@
+use regex::Regex;
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+
+// ─── Brand registry ───────────────────────────────────
+
+/// A camera manufacturer known to the logo lookup.
+///
+/// `id` is the file stem of the logo in `assets/models` (e.g. `olympus` →
+/// `olympus.png`), `name` is the canonical display name.
+#[derive(Clone)]
+pub struct Brand {
+    pub id: String,
+    pub name: String,
+    aliases: Vec<String>,
+    make_pattern: Option<Regex>,
+    model_pattern: Option<Regex>,
+    model_overrides: Vec<ModelOverride>,
+}
+
+/// Model-specific logo from the user's brand file, e.g. a series badge.
+#[derive(Clone)]
+struct ModelOverride {
+    pattern: Regex,
+    logo: String,
+}
+
+/// Result of resolving a Make/Model pair against the registry.
+pub struct BrandMatch<'a> {
+    pub brand: &'a Brand,
+    /// Logo stems to try, most specific first.
+    pub logos: Vec<String>,
+}
+
+#[derive(Clone)]
+pub struct BrandRegistry {
+    brands: Vec<Brand>,
+}
+
+// ─── User file format ─────────────────────────────────
+
+#[derive(Deserialize)]
+struct BrandFile {
+    #[serde(default)]
+    brands: Vec<BrandEntry>,
+}
+
+#[derive(Deserialize)]
+struct BrandEntry {
+    id: String,
+    name: Option<String>,
+    #[serde(default)]
+    aliases: Vec<String>,
+    make: Option<String>,
+    model: Option<String>,
+    #[serde(default)]
+    overrides: Vec<OverrideEntry>,
+}
+
+#[derive(Deserialize)]
+struct OverrideEntry {
+    model: String,
+    logo: String,
+}
+
+// ─── Built-in brands ──────────────────────────────────
+
+// (id, name, aliases, make regex, model regex)
+type BuiltinBrand = (&'static str, &'static str, &'static [&'static str], Option<&'static str>, Option<&'static str>);
+
+const BUILTIN_BRANDS: &[BuiltinBrand] = &[
+    ("apple", "Apple", &["apple"], None, Some(r"(?i)^(iphone|ipad)\b")),
+    ("canon", "Canon", &["canon"], None, None),
+    ("dji", "DJI", &["dji"], None, Some(r"(?i)^(fc\d{4}|mavic|osmo|mini \d)")),
+    ("fujifilm", "Fujifilm", &["fujifilm", "fuji photo film", "fuji"], None, None),
+    ("gopro", "GoPro", &["gopro"], None, Some(r"(?i)^hero\d")),
+    ("hasselblad", "Hasselblad", &["hasselblad"], None, None),
+    ("kodak", "Kodak", &["kodak", "eastman kodak"], None, None),
+    ("konicaminolta", "Konica Minolta", &["konica minolta", "konicaminolta", "minolta", "konica"], None, None),
+    ("leica", "Leica", &["leica"], None, None),
+    ("nikon", "Nikon", &["nikon"], None, None),
+    ("olympus", "Olympus", &["olympus", "om digital solutions", "om system"], None, None),
+    ("panasonic", "Panasonic", &["panasonic"], None, Some(r"(?i)^(dmc|dc)-")),
+    ("pentax", "Pentax", &["pentax", "ricoh", "asahi"], None, None),
+    ("samsung", "Samsung", &["samsung"], None, None),
+    ("sigma", "Sigma", &["sigma"], None, None),
+    ("sony", "Sony", &["sony"], None, Some(r"(?i)^(ilce|ilca|dsc|nex|slt)-")),
+];
+
+/// Lowercases, replaces punctuation with spaces and collapses whitespace, so
+/// "NIKON CORPORATION" and "Konica-Minolta" compare cleanly against aliases.
+pub(crate) fn normalize(s: &str) -> String {
+    s.to_lowercase()
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { ' ' })
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn compile(pattern: &str) -> Result<Regex, String> {
+    Regex::new(pattern).map_err(|e| format!("Invalid brand pattern '{}': {}", pattern, e))
+}
+
+impl Brand {
+    fn matches_make(&self, normalized_make: &str, raw_make: &str) -> bool {
+        let alias_match = self.aliases.iter().any(|alias| {
+            normalized_make == alias
+                || normalized_make
+                    .strip_prefix(alias.as_str())
+                    .is_some_and(|rest| rest.starts_with(' '))
+        });
+        alias_match || self.make_pattern.as_ref().is_some_and(|re| re.is_match(raw_make))
+    }
+
+    fn matches_model(&self, model: &str) -> bool {
+        self.model_pattern.as_ref().is_some_and(|re| re.is_match(model))
+    }
+
+    /// Byte length of the brand name a Model string starts with, e.g. 5 for
+    /// "NIKON Z 8" or "Canon EOS R5". The longest matching alias wins.
+    pub fn model_prefix_len(&self, model: &str) -> Option<usize> {
+        std::iter::once(&self.name)
+            .chain(&self.aliases)
+            .filter_map(|alias| {
+                let head = model.get(..alias.len())?;
+                let rest = &model[alias.len()..];
+                let at_word_end = rest.chars().next().is_none_or(|c| !c.is_alphanumeric());
+                (head.eq_ignore_ascii_case(alias) && at_word_end).then_some(alias.len())
+            })
+            .max()
+    }
+}
+
+impl BrandRegistry {
+    pub fn builtin() -> Self {
+        let brands = BUILTIN_BRANDS
+            .iter()
+            .map(|(id, name, aliases, make, model)| Brand {
+                id: id.to_string(),
+                name: name.to_string(),
+                aliases: aliases.iter().map(|a| normalize(a)).collect(),
+                make_pattern: make.map(|p| compile(p).expect("built-in make pattern")),
+                model_pattern: model.map(|p| compile(p).expect("built-in model pattern")),
+                model_overrides: Vec::new(),
+            })
+            .collect();
+        Self { brands }
+    }
+
+    /// Built-in registry extended with the user's brand file, if present.
+    /// A broken user file is logged and ignored rather than failing the run.
+    pub fn load(user_file: Option<&Path>) -> Self {
+        let mut registry = Self::builtin();
+        if let Some(path) = user_file.filter(|p| p.exists()) {
+            if let Err(e) = registry.extend_from_file(path) {
+                log::warn!("Ignoring brand file {}: {}", path.display(), e);
+            }
+        }
+        registry
+    }
+
+    pub fn extend_from_file(&mut self, path: &Path) -> Result<(), String> {
+        let data = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        self.extend_from_json(&data)
+    }
+
+    /// Merges user entries into the registry. Entries with a known `id` extend
+    /// that brand; new brands are checked before the built-in ones. Every
+    /// pattern is compiled first, so a bad entry leaves the registry untouched.
+    pub fn extend_from_json(&mut self, json: &str) -> Result<(), String> {
+        let file: BrandFile =
+            serde_json::from_str(json).map_err(|e| format!("Invalid brand file: {}", e))?;
+
+        let compiled = file
+            .brands
+            .into_iter()
+            .map(|entry| {
+                let make_pattern = entry.make.as_deref().map(compile).transpose()?;
+                let model_pattern = entry.model.as_deref().map(compile).transpose()?;
+                let overrides = entry
+                    .overrides
+                    .iter()
+                    .map(|o| {
+                        Ok(ModelOverride {
+                            pattern: compile(&o.model)?,
+                            logo: o.logo.clone(),
+                        })
+                    })
+                    .collect::<Result<Vec<_>, String>>()?;
+                Ok((entry, make_pattern, model_pattern, overrides))
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        for (entry, make_pattern, model_pattern, overrides) in compiled.into_iter().rev() {
+            let aliases: Vec<String> = entry.aliases.iter().map(|a| normalize(a)).collect();
+
+            if let Some(brand) = self.brands.iter_mut().find(|b| b.id == entry.id) {
+                if let Some(name) = entry.name {
+                    brand.name = name;
+                }
+                brand.aliases.splice(0..0, aliases);
+                if make_pattern.is_some() {
+                    brand.make_pattern = make_pattern;
+                }
+                if model_pattern.is_some() {
+                    brand.model_pattern = model_pattern;
+                }
+                brand.model_overrides.splice(0..0, overrides);
+            } else {
+                self.brands.insert(
+                    0,
+                    Brand {
+                        name: entry.name.unwrap_or_else(|| entry.id.clone()),
+                        aliases: if aliases.is_empty() { vec![normalize(&entry.id)] } else { aliases },
+                        id: entry.id,
+                        make_pattern,
+                        model_pattern,
+                        model_overrides: overrides,
+                    },
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Resolves the brand from EXIF Make, falling back to the Model for files
+    /// (phones, action cams) that leave Make empty or use an unknown vendor.
+    pub fn resolve(&self, camera_make: &str, camera_model: &str) -> Option<BrandMatch<'_>> {
+        let normalized_make = normalize(camera_make);
+        let brand = self
+            .brands
+            .iter()
+            .find(|b| !normalized_make.is_empty() && b.matches_make(&normalized_make, camera_make))
+            .or_else(|| self.brands.iter().find(|b| b.matches_model(camera_model)))?;
+
+        let mut logos: Vec<String> = brand
+            .model_overrides
+            .iter()
+            .filter(|o| o.pattern.is_match(camera_model))
+            .map(|o| o.logo.clone())
+            .collect();
+        logos.push(brand.id.clone());
+
+        Some(BrandMatch { brand, logos })
+    }
+}
+
+impl Default for BrandRegistry {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn brand_id(make: &str, model: &str) -> Option<String> {
+        BrandRegistry::builtin()
+            .resolve(make, model)
+            .map(|m| m.brand.id.clone())
+    }
+
+    #[test]
+    fn resolves_real_make_strings() {
+        let corpus = [
+            ("Canon", "canon"),
+            ("NIKON CORPORATION", "nikon"),
+            ("NIKON", "nikon"),
+            ("SONY", "sony"),
+            ("FUJIFILM", "fujifilm"),
+            ("FUJI PHOTO FILM CO., LTD.", "fujifilm"),
+            ("OLYMPUS IMAGING CORP.", "olympus"),
+            ("OLYMPUS CORPORATION", "olympus"),
+            ("OM Digital Solutions", "olympus"),
+            ("Panasonic", "panasonic"),
+            ("LEICA CAMERA AG", "leica"),
+            ("Leica Camera AG", "leica"),
+            ("Apple", "apple"),
+            ("samsung", "samsung"),
+            ("SAMSUNG TECHWIN", "samsung"),
+            ("PENTAX Corporation", "pentax"),
+            ("PENTAX", "pentax"),
+            ("RICOH IMAGING COMPANY, LTD.", "pentax"),
+            ("Konica Minolta Camera, Inc.", "konicaminolta"),
+            ("KONICA MINOLTA", "konicaminolta"),
+            ("Minolta Co., Ltd.", "konicaminolta"),
+            ("Hasselblad", "hasselblad"),
+            ("DJI", "dji"),
+            ("GoPro", "gopro"),
+            ("SIGMA", "sigma"),
+            ("EASTMAN KODAK COMPANY", "kodak"),
+        ];
+        for (make, expected) in corpus {
+            assert_eq!(brand_id(make, "").as_deref(), Some(expected), "make {:?}", make);
+        }
+    }
+
+    #[test]
+    fn does_not_match_on_substrings() {
+        assert_eq!(brand_id("Google", "Pixel 8"), None);
+        assert_eq!(brand_id("HUAWEI", "P30"), None);
+        assert_eq!(brand_id("Sonyericsson", "K800i"), None);
+        assert_eq!(brand_id("", ""), None);
+    }
+
+    #[test]
+    fn falls_back_to_model_pattern() {
+        assert_eq!(brand_id("", "iPhone 15 Pro").as_deref(), Some("apple"));
+        assert_eq!(brand_id("", "HERO11 Black").as_deref(), Some("gopro"));
+        assert_eq!(brand_id("", "ILCE-7M4").as_deref(), Some("sony"));
+    }
+
+    #[test]
+    fn model_override_is_tried_first() {
+        let mut registry = BrandRegistry::builtin();
+        registry
+            .extend_from_json(
+                r#"{ "brands": [{ "id": "fujifilm", "overrides": [{ "model": "(?i)^x100", "logo": "fujifilm-x100" }] }] }"#,
+            )
+            .unwrap();
+        let m = registry.resolve("FUJIFILM", "X100VI").unwrap();
+        assert_eq!(m.logos, vec!["fujifilm-x100", "fujifilm"]);
+        let m = registry.resolve("FUJIFILM", "X-T5").unwrap();
+        assert_eq!(m.logos, vec!["fujifilm"]);
+    }
+
+    #[test]
+    fn user_file_extends_registry() {
+        let mut registry = BrandRegistry::builtin();
+        registry
+            .extend_from_json(
+                r#"{ "brands": [
+                    { "id": "ricoh", "name": "Ricoh", "make": "(?i)^ricoh", "overrides": [] },
+                    { "id": "sony", "overrides": [{ "model": "^ZV-", "logo": "sony-zv" }] }
+                ] }"#,
+            )
+            .unwrap();
+
+        let m = registry.resolve("RICOH IMAGING COMPANY, LTD.", "GR III").unwrap();
+        assert_eq!(m.brand.id, "ricoh");
+        let m = registry.resolve("SONY", "ZV-E10").unwrap();
+        assert_eq!(m.logos, vec!["sony-zv", "sony"]);
+    }
+
+    #[test]
+    fn rejects_invalid_user_patterns() {
+        let mut registry = BrandRegistry::builtin();
+        assert!(registry
+            .extend_from_json(r#"{ "brands": [{ "id": "x", "make": "(" }] }"#)
+            .is_err());
+
+        // A bad entry after a good one must not leave the good one applied.
+        assert!(registry
+            .extend_from_json(
+                r#"{ "brands": [
+                    { "id": "ricoh", "make": "(?i)^ricoh" },
+                    { "id": "sony", "overrides": [{ "model": "(", "logo": "sony-zv" }] }
+                ] }"#,
+            )
+            .is_err());
+        let m = registry.resolve("RICOH IMAGING COMPANY, LTD.", "GR III").unwrap();
+        assert_eq!(m.brand.id, "pentax");
+    }
+}
@@ -0,0 +1,641 @@
+//! C2PA content credentials for framed JPEGs: a signed manifest stating who
+//! took the photo, with which camera, and that a frame was added.
+//!
+//! The manifest store is a JUMBF box carried in APP11 segments, as in the
+//! C2PA spec. It holds a version 1 claim over four assertions: the creator
+//! (`stds.schema-org.CreativeWork`), the camera (`stds.exif`), the edit
+//! (`c2pa.actions`) and a hash of the file outside the manifest
+//! (`c2pa.hash.data`). The claim is signed as a COSE_Sign1 with ES256, with
+//! the certificate chain in the protected header. No timestamp authority is
+//! contacted, so everything works offline.
+
+use ciborium::Value;
+use p256::ecdsa::signature::{Signer, Verifier};
+use p256::ecdsa::{Signature, SigningKey, VerifyingKey};
+use p256::pkcs8::{DecodePrivateKey, DecodePublicKey};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use x509_cert::der::{Decode, Encode};
+use x509_cert::Certificate;
+
+/// APP11 marker and the JPEG XT "JP" common identifier.
+const APP11: u8 = 0xEB;
+const JPEG_XT_ID: [u8; 2] = *b"JP";
+
+/// Largest APP11 payload: the segment length field counts itself.
+const MAX_SEGMENT_PAYLOAD: usize = 65533;
+
+/// COSE header labels and the ES256 algorithm ID.
+const COSE_ALG: i64 = 1;
+const COSE_X5CHAIN: i64 = 33;
+const COSE_ES256: i64 = -7;
+const COSE_SIGN1_TAG: u64 = 18;
+
+const CLAIM_GENERATOR: &str = concat!("DigiCamWM/", env!("CARGO_PKG_VERSION"));
+
+/// Certificate chain and P-256 key the manifests are signed with.
+#[derive(Clone)]
+pub struct C2paSigner {
+    /// DER certificates, signer first
+    chain: Vec<Vec<u8>>,
+    key: SigningKey,
+}
+
+impl std::fmt::Debug for C2paSigner {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("C2paSigner").field("chain", &self.chain.len()).finish_non_exhaustive()
+    }
+}
+
+impl C2paSigner {
+    /// Reads a PEM certificate chain (signer first) and its PKCS#8 or SEC1
+    /// PEM private key. Only P-256 (ES256) keys are supported.
+    pub fn from_pem(certificates: &str, private_key: &str) -> Result<Self, String> {
+        let chain = Certificate::load_pem_chain(certificates.as_bytes())
+            .map_err(|e| format!("Invalid certificate: {}", e))?;
+        let chain: Vec<Vec<u8>> = chain
+            .iter()
+            .map(|c| c.to_der().map_err(|e| format!("Invalid certificate: {}", e)))
+            .collect::<Result<_, _>>()?;
+        if chain.is_empty() {
+            return Err("No certificate in the certificate file".into());
+        }
+        let key = SigningKey::from_pkcs8_pem(private_key)
+            .or_else(|_| p256::SecretKey::from_sec1_pem(private_key).map(SigningKey::from))
+            .map_err(|_| "Private key must be a P-256 (ES256) key in PEM format".to_string())?;
+
+        // Catch a key that doesn't belong to the certificate now, not in every photo
+        if certificate_key(&chain[0])? != *key.verifying_key() {
+            return Err("Private key doesn't match the certificate".into());
+        }
+        Ok(Self { chain, key })
+    }
+
+    pub fn load(certificate_path: &Path, key_path: &Path) -> Result<Self, String> {
+        let read = |path: &Path| {
+            fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))
+        };
+        Self::from_pem(&read(certificate_path)?, &read(key_path)?)
+    }
+}
+
+/// What the manifest states about the photo.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Provenance {
+    /// Photographer, empty for none
+    pub creator: String,
+    /// EXIF Make and Model as recorded, empty when missing
+    pub camera_make: String,
+    pub camera_model: String,
+}
+
+/// Content credentials read back from a file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Credentials {
+    pub creator: Option<String>,
+    pub camera_make: Option<String>,
+    pub camera_model: Option<String>,
+    /// Action labels, e.g. `c2pa.edited`
+    pub actions: Vec<String>,
+    /// Subject of the signing certificate
+    pub signer: String,
+}
+
+// ─── Signing ──────────────────────────────────────────
+
+/// Writes an encoded JPEG to `writer` with a signed manifest right after its
+/// APP0/APP1 segments, without copying it.
+pub(crate) fn sign_jpeg<W: Write>(
+    jpeg: &[u8],
+    provenance: &Provenance,
+    signer: &C2paSigner,
+    writer: &mut W,
+) -> Result<(), String> {
+    let insert_at = manifest_offset(jpeg)?;
+    // The hash covers everything but the inserted segments, i.e. the JPEG as encoded
+    let data_hash = Sha256::digest(jpeg).to_vec();
+    let uuid = |salt: &str| uuid_from(&Sha256::new().chain_update(jpeg).chain_update(salt).finalize());
+
+    // The exclusion length is part of the manifest, so build until it's stable
+    let mut length = 0;
+    let segments = loop {
+        let store = manifest_store(provenance, signer, &data_hash, (insert_at, length), &uuid)?;
+        let segments = app11_segments(&store);
+        if segments.len() == length {
+            break segments;
+        }
+        length = segments.len();
+    };
+
+    for part in [&jpeg[..insert_at], &segments, &jpeg[insert_at..]] {
+        writer.write_all(part).map_err(|e| format!("Failed to write output: {}", e))?;
+    }
+    Ok(())
+}
+
+fn manifest_store(
+    provenance: &Provenance,
+    signer: &C2paSigner,
+    data_hash: &[u8],
+    (start, length): (usize, usize),
+    uuid: &dyn Fn(&str) -> String,
+) -> Result<Vec<u8>, String> {
+    let mut assertions = Vec::new();
+    if !provenance.creator.is_empty() {
+        let creative_work = serde_json::json!({
+            "@context": "https://schema.org",
+            "@type": "CreativeWork",
+            "author": [{ "@type": "Person", "name": provenance.creator }],
+        });
+        assertions.push(json_assertion("stds.schema-org.CreativeWork", &creative_work));
+    }
+    let mut exif = serde_json::json!({ "@context": { "exif": "http://ns.adobe.com/exif/1.0/" } });
+    for (key, value) in [("exif:Make", &provenance.camera_make), ("exif:Model", &provenance.camera_model)] {
+        if !value.is_empty() {
+            exif[key] = value.as_str().into();
+        }
+    }
+    assertions.push(json_assertion("stds.exif", &exif));
+    let actions = map(vec![(
+        "actions",
+        Value::Array(vec![map(vec![
+            ("action", "c2pa.edited".into()),
+            ("softwareAgent", CLAIM_GENERATOR.into()),
+            ("parameters", map(vec![("description", "Watermark frame added".into())])),
+        ])]),
+    )]);
+    assertions.push(cbor_assertion("c2pa.actions", &actions)?);
+    let hash_data = map(vec![
+        (
+            "exclusions",
+            Value::Array(vec![map(vec![("start", (start as u64).into()), ("length", (length as u64).into())])]),
+        ),
+        ("name", "jumbf manifest".into()),
+        ("alg", "sha256".into()),
+        ("hash", Value::Bytes(data_hash.to_vec())),
+        ("pad", Value::Bytes(Vec::new())),
+    ]);
+    assertions.push(cbor_assertion("c2pa.hash.data", &hash_data)?);
+
+    let claim = map(vec![
+        ("claim_generator", CLAIM_GENERATOR.into()),
+        ("signature", "self#jumbf=c2pa.signature".into()),
+        (
+            "assertions",
+            Value::Array(
+                assertions
+                    .iter()
+                    .map(|(label, bytes)| {
+                        map(vec![
+                            ("url", format!("self#jumbf=c2pa.assertions/{}", label).into()),
+                            ("hash", Value::Bytes(assertion_hash(bytes))),
+                        ])
+                    })
+                    .collect(),
+            ),
+        ),
+        ("dc:format", "image/jpeg".into()),
+        ("instanceID", format!("xmp:iid:{}", uuid("instance")).into()),
+        ("alg", "sha256".into()),
+    ]);
+    let claim = to_cbor(&claim)?;
+    let signature = cose_sign1(&claim, signer)?;
+
+    let assertion_store = superbox(
+        *b"c2as",
+        "c2pa.assertions",
+        &assertions.into_iter().map(|(_, bytes)| bytes).collect::<Vec<_>>(),
+    );
+    let manifest = superbox(
+        *b"c2ma",
+        &format!("urn:uuid:{}", uuid("manifest")),
+        &[
+            assertion_store,
+            superbox(*b"c2cl", "c2pa.claim", &[content_box(*b"cbor", &claim)]),
+            superbox(*b"c2cs", "c2pa.signature", &[content_box(*b"cbor", &signature)]),
+        ],
+    );
+    Ok(superbox(*b"c2pa", "c2pa", &[manifest]))
+}
+
+/// COSE_Sign1 over the claim, detached: the claim is the payload but isn't
+/// repeated in the signature box.
+fn cose_sign1(claim: &[u8], signer: &C2paSigner) -> Result<Vec<u8>, String> {
+    let chain = match signer.chain.as_slice() {
+        [single] => Value::Bytes(single.clone()),
+        chain => Value::Array(chain.iter().cloned().map(Value::Bytes).collect()),
+    };
+    let protected = to_cbor(&Value::Map(vec![
+        (COSE_ALG.into(), COSE_ES256.into()),
+        (COSE_X5CHAIN.into(), chain),
+    ]))?;
+    let signature: Signature = signer.key.sign(&sig_structure(&protected, claim)?);
+    to_cbor(&Value::Tag(
+        COSE_SIGN1_TAG,
+        Box::new(Value::Array(vec![
+            Value::Bytes(protected),
+            Value::Map(Vec::new()),
+            Value::Null,
+            Value::Bytes(signature.to_bytes().to_vec()),
+        ])),
+    ))
+}
+
+/// The bytes COSE actually signs.
+fn sig_structure(protected: &[u8], payload: &[u8]) -> Result<Vec<u8>, String> {
+    to_cbor(&Value::Array(vec![
+        "Signature1".into(),
+        Value::Bytes(protected.to_vec()),
+        Value::Bytes(Vec::new()),
+        Value::Bytes(payload.to_vec()),
+    ]))
+}
+
+// ─── JUMBF ────────────────────────────────────────────
+
+/// C2PA's JUMBF type UUIDs: the four-character code followed by a fixed tail.
+fn jumbf_type(code: [u8; 4]) -> [u8; 16] {
+    let mut uuid = [0x00, 0x11, 0x00, 0x10, 0x80, 0x00, 0x00, 0xAA, 0x00, 0x38, 0x9B, 0x71, 0, 0, 0, 0];
+    uuid.rotate_right(4);
+    uuid[..4].copy_from_slice(&code);
+    uuid
+}
+
+fn plain_box(kind: [u8; 4], payload: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(8 + payload.len());
+    out.extend_from_slice(&(8 + payload.len() as u32).to_be_bytes());
+    out.extend_from_slice(&kind);
+    out.extend_from_slice(payload);
+    out
+}
+
+/// Labelled superbox: a description box followed by `children`.
+fn superbox(kind: [u8; 4], label: &str, children: &[Vec<u8>]) -> Vec<u8> {
+    let mut description = jumbf_type(kind).to_vec();
+    // Requestable, with a label
+    description.push(0x03);
+    description.extend_from_slice(label.as_bytes());
+    description.push(0);
+
+    let mut payload = plain_box(*b"jumd", &description);
+    for child in children {
+        payload.extend_from_slice(child);
+    }
+    plain_box(*b"jumb", &payload)
+}
+
+/// Unlabelled content box (`cbor` or `json`).
+fn content_box(kind: [u8; 4], data: &[u8]) -> Vec<u8> {
+    plain_box(kind, data)
+}
+
+fn cbor_assertion(label: &'static str, value: &Value) -> Result<(&'static str, Vec<u8>), String> {
+    Ok((label, superbox(*b"cbor", label, &[content_box(*b"cbor", &to_cbor(value)?)])))
+}
+
+fn json_assertion(label: &'static str, value: &serde_json::Value) -> (&'static str, Vec<u8>) {
+    (label, superbox(*b"json", label, &[content_box(*b"json", value.to_string().as_bytes())]))
+}
+
+/// Hash of an assertion's superbox without its box header, as referenced
+/// from the claim.
+fn assertion_hash(superbox: &[u8]) -> Vec<u8> {
+    Sha256::digest(&superbox[8..]).to_vec()
+}
+
+/// A parsed box: its type, label (for superboxes) and content.
+struct JumbfBox<'a> {
+    kind: [u8; 4],
+    label: String,
+    /// The superbox's bytes, header included, for hashing
+    raw: &'a [u8],
+    /// Child boxes of a superbox
+    children: Vec<JumbfBox<'a>>,
+    /// Payload of a content box
+    data: &'a [u8],
+}
+
+fn parse_boxes(mut data: &[u8]) -> Result<Vec<JumbfBox<'_>>, String> {
+    let mut boxes = Vec::new();
+    while !data.is_empty() {
+        let len = data.get(..4).map(|l| u32::from_be_bytes(l.try_into().unwrap()) as usize);
+        let Some(len) = len.filter(|&l| l >= 8 && l <= data.len()) else {
+            return Err("Malformed JUMBF box".into());
+        };
+        let (raw, rest) = data.split_at(len);
+        let kind: [u8; 4] = raw[4..8].try_into().unwrap();
+        let parsed = if &kind == b"jumb" {
+            let mut children = parse_boxes(&raw[8..])?;
+            let description = children.first().filter(|d| &d.kind == b"jumd").ok_or("JUMBF box without description")?;
+            let (uuid, label) = description.data.split_at(16.min(description.data.len()));
+            let label = label.get(1..).unwrap_or_default().split(|&b| b == 0).next().unwrap_or_default();
+            JumbfBox {
+                kind: uuid[..4].try_into().map_err(|_| "Malformed JUMBF description")?,
+                label: String::from_utf8_lossy(label).into_owned(),
+                raw,
+                children: children.split_off(1),
+                data: &[],
+            }
+        } else {
+            JumbfBox { kind, label: String::new(), raw, children: Vec::new(), data: &raw[8..] }
+        };
+        boxes.push(parsed);
+        data = rest;
+    }
+    Ok(boxes)
+}
+
+impl<'a> JumbfBox<'a> {
+    fn child(&self, label: &str) -> Result<&JumbfBox<'a>, String> {
+        self.children.iter().find(|c| c.label == label).ok_or_else(|| format!("Manifest has no {}", label))
+    }
+
+    /// Payload of the superbox's content box.
+    fn content(&self) -> Result<&'a [u8], String> {
+        self.children.first().map(|c| c.data).ok_or_else(|| format!("{} is empty", self.label))
+    }
+}
+
+// ─── JPEG ─────────────────────────────────────────────
+
+/// Offset after the SOI marker and any APP0/APP1 (JFIF, EXIF) segments.
+fn manifest_offset(jpeg: &[u8]) -> Result<usize, String> {
+    if !jpeg.starts_with(&[0xFF, 0xD8]) {
+        return Err("Content credentials need JPEG output".into());
+    }
+    let mut offset = 2;
+    // A segment length past the end stops the scan there
+    while let Some(&[0xFF, 0xE0 | 0xE1, hi, lo, ..]) = jpeg.get(offset..) {
+        offset += 2 + u16::from_be_bytes([hi, lo]) as usize;
+    }
+    Ok(offset.min(jpeg.len()))
+}
+
+/// The manifest store split over APP11 segments. Every segment repeats the
+/// box header, followed by the next part of the box content.
+fn app11_segments(store: &[u8]) -> Vec<u8> {
+    let (header, content) = store.split_at(8);
+    let chunk = MAX_SEGMENT_PAYLOAD - 2 - 2 - 4 - header.len();
+    let mut out = Vec::new();
+    for (i, part) in content.chunks(chunk).enumerate() {
+        let length = 2 + 2 + 2 + 4 + header.len() + part.len();
+        out.extend_from_slice(&[0xFF, APP11]);
+        out.extend_from_slice(&(length as u16).to_be_bytes());
+        out.extend_from_slice(&JPEG_XT_ID);
+        // Box instance 1, packets numbered from 1
+        out.extend_from_slice(&1u16.to_be_bytes());
+        out.extend_from_slice(&(i as u32 + 1).to_be_bytes());
+        out.extend_from_slice(header);
+        out.extend_from_slice(part);
+    }
+    out
+}
+
+/// Reassembles the manifest store from a JPEG's APP11 segments.
+fn read_store(jpeg: &[u8]) -> Result<Option<Vec<u8>>, String> {
+    let mut store: Option<Vec<u8>> = None;
+    let mut offset = 2;
+    while let [0xFF, marker, hi, lo, ..] = jpeg[offset.min(jpeg.len())..] {
+        // Stop at the start of scan; everything after is entropy-coded data
+        if marker == 0xDA {
+            break;
+        }
+        let length = u16::from_be_bytes([hi, lo]) as usize;
+        let payload = jpeg.get(offset + 4..offset + 2 + length).ok_or("Truncated JPEG segment")?;
+        if marker == APP11 && payload.len() > 16 && payload[..2] == JPEG_XT_ID && &payload[12..16] == b"jumb" {
+            match &mut store {
+                None => store = Some(payload[8..].to_vec()),
+                Some(store) => store.extend_from_slice(&payload[16..]),
+            }
+        }
+        offset += 2 + length;
+    }
+    if let Some(store) = &mut store {
+        // The repeated header carries the full box length
+        let len = store.len() as u32;
+        store[..4].copy_from_slice(&len.to_be_bytes());
+    }
+    Ok(store)
+}
+
+// ─── Verification ─────────────────────────────────────
+
+/// Checks the content credentials of a JPEG offline: the file hash, the
+/// assertion hashes and the signature against the embedded certificate.
+/// Whether that certificate is trusted is up to the caller. `Ok(None)` when
+/// the file carries no manifest.
+pub fn verify_jpeg(jpeg: &[u8]) -> Result<Option<Credentials>, String> {
+    let Some(store_bytes) = read_store(jpeg)? else {
+        return Ok(None);
+    };
+    let store = parse_boxes(&store_bytes)?;
+    let manifest = store
+        .first()
+        .filter(|s| &s.kind == b"c2pa")
+        .and_then(|s| s.children.last())
+        .ok_or("No C2PA manifest")?;
+    let assertions = manifest.child("c2pa.assertions")?;
+    let claim_bytes = manifest.child("c2pa.claim")?.content()?;
+    let claim: Value = from_cbor(claim_bytes)?;
+
+    for reference in get(&claim, "assertions")?.as_array().ok_or("Malformed claim")? {
+        let url = get(reference, "url")?.as_text().ok_or("Malformed claim")?;
+        let label = url.rsplit('/').next().unwrap_or_default();
+        let assertion = assertions.child(label)?;
+        if get(reference, "hash")?.as_bytes().map(Vec::as_slice) != Some(assertion_hash(assertion.raw).as_slice()) {
+            return Err(format!("Assertion {} was changed", label));
+        }
+    }
+
+    // File hash with the exclusions left out
+    let hash_data: Value = from_cbor(assertions.child("c2pa.hash.data")?.content()?)?;
+    let mut hasher = Sha256::new();
+    let mut position = 0;
+    for exclusion in get(&hash_data, "exclusions")?.as_array().ok_or("Malformed hash assertion")? {
+        let number = |key| -> Result<usize, String> {
+            let value = get(exclusion, key)?.as_integer().ok_or("Malformed hash assertion")?;
+            usize::try_from(value).map_err(|_| "Malformed hash assertion".to_string())
+        };
+        let (start, length) = (number("start")?, number("length")?);
+        hasher.update(jpeg.get(position..start).ok_or("Hash exclusion outside the file")?);
+        position = start.checked_add(length).ok_or("Malformed hash assertion")?;
+    }
+    hasher.update(jpeg.get(position..).ok_or("Hash exclusion outside the file")?);
+    if get(&hash_data, "hash")?.as_bytes().map(Vec::as_slice) != Some(hasher.finalize().as_slice()) {
+        return Err("Image was changed after signing".into());
+    }
+
+    // Signature over the claim, with the signer's certificate
+    let cose: Value = from_cbor(manifest.child("c2pa.signature")?.content()?)?;
+    let parts = match &cose {
+        Value::Tag(COSE_SIGN1_TAG, inner) => inner.as_array(),
+        other => other.as_array(),
+    }
+    .filter(|p| p.len() == 4)
+    .ok_or("Malformed signature")?;
+    let protected = parts[0].as_bytes().ok_or("Malformed signature")?;
+    let headers: Value = from_cbor(protected)?;
+    let chain = headers
+        .as_map()
+        .and_then(|m| m.iter().find(|(k, _)| k.as_integer() == Some(COSE_X5CHAIN.into())))
+        .map(|(_, v)| v)
+        .ok_or("Signature has no certificate")?;
+    let certificate = match chain {
+        Value::Bytes(der) => der,
+        Value::Array(chain) => chain.first().and_then(Value::as_bytes).ok_or("Signature has no certificate")?,
+        _ => return Err("Signature has no certificate".into()),
+    };
+    let signature = parts[3].as_bytes().and_then(|s| Signature::from_slice(s).ok()).ok_or("Malformed signature")?;
+    certificate_key(certificate)?
+        .verify(&sig_structure(protected, claim_bytes)?, &signature)
+        .map_err(|_| "Signature doesn't match the claim".to_string())?;
+
+    // What the manifest states
+    let json = |label: &str| -> Option<serde_json::Value> {
+        serde_json::from_slice(assertions.child(label).ok()?.content().ok()?).ok()
+    };
+    let creative_work = json("stds.schema-org.CreativeWork");
+    let exif = json("stds.exif");
+    let text = |value: Option<&serde_json::Value>| value.and_then(|v| v.as_str()).map(String::from);
+    let actions: Value = from_cbor(assertions.child("c2pa.actions")?.content()?)?;
+    let actions = get(&actions, "actions")?
+        .as_array()
+        .map(|a| a.iter().filter_map(|a| get(a, "action").ok()?.as_text().map(String::from)).collect())
+        .unwrap_or_default();
+    let signer = Certificate::from_der(certificate).map_err(|e| format!("Invalid certificate: {}", e))?;
+
+    Ok(Some(Credentials {
+        creator: text(creative_work.as_ref().and_then(|w| w.pointer("/author/0/name"))),
+        camera_make: text(exif.as_ref().and_then(|e| e.get("exif:Make"))),
+        camera_model: text(exif.as_ref().and_then(|e| e.get("exif:Model"))),
+        actions,
+        signer: signer.tbs_certificate.subject.to_string(),
+    }))
+}
+
+fn certificate_key(der: &[u8]) -> Result<VerifyingKey, String> {
+    let certificate = Certificate::from_der(der).map_err(|e| format!("Invalid certificate: {}", e))?;
+    let key_info = certificate
+        .tbs_certificate
+        .subject_public_key_info
+        .to_der()
+        .map_err(|e| format!("Invalid certificate: {}", e))?;
+    VerifyingKey::from_public_key_der(&key_info).map_err(|_| "Certificate key isn't a P-256 key".to_string())
+}
+
+// ─── CBOR helpers ─────────────────────────────────────
+
+fn map(entries: Vec<(&str, Value)>) -> Value {
+    Value::Map(entries.into_iter().map(|(k, v)| (k.into(), v)).collect())
+}
+
+fn get<'a>(value: &'a Value, key: &str) -> Result<&'a Value, String> {
+    value
+        .as_map()
+        .and_then(|m| m.iter().find(|(k, _)| k.as_text() == Some(key)))
+        .map(|(_, v)| v)
+        .ok_or_else(|| format!("Manifest field {} is missing", key))
+}
+
+fn to_cbor(value: &Value) -> Result<Vec<u8>, String> {
+    let mut out = Vec::new();
+    ciborium::into_writer(value, &mut out).map_err(|e| format!("Failed to encode manifest: {}", e))?;
+    Ok(out)
+}
+
+fn from_cbor(data: &[u8]) -> Result<Value, String> {
+    ciborium::from_reader(data).map_err(|e| format!("Malformed manifest: {}", e))
+}
+
+/// Version 4 style UUID from hash bytes, so the same file gets the same IDs.
+fn uuid_from(hash: &[u8]) -> String {
+    let mut bytes: [u8; 16] = hash[..16].try_into().unwrap();
+    bytes[6] = bytes[6] & 0x0F | 0x40;
+    bytes[8] = bytes[8] & 0x3F | 0x80;
+    let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    format!("{}-{}-{}-{}-{}", &hex[..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::codecs::jpeg::JpegEncoder;
+    use image::{Rgb, RgbImage};
+
+    fn test_signer() -> C2paSigner {
+        C2paSigner::from_pem(include_str!("../tests/c2pa/test-cert.pem"), include_str!("../tests/c2pa/test-key.pem"))
+            .unwrap()
+    }
+
+    fn jpeg() -> Vec<u8> {
+        let mut out = Vec::new();
+        RgbImage::from_fn(64, 48, |x, y| Rgb([x as u8 * 4, y as u8 * 5, 90]))
+            .write_with_encoder(JpegEncoder::new_with_quality(&mut out, 90))
+            .unwrap();
+        out
+    }
+
+    #[test]
+    fn signed_manifest_verifies_offline() {
+        let provenance = Provenance {
+            creator: "Jane Doe".into(),
+            camera_make: "FUJIFILM".into(),
+            camera_model: "X-T5".into(),
+        };
+        let original = jpeg();
+        let mut signed = Vec::new();
+        sign_jpeg(&original, &provenance, &test_signer(), &mut signed).unwrap();
+
+        // Still a valid JPEG with the same pixels
+        assert_eq!(
+            image::load_from_memory(&signed).unwrap().as_bytes(),
+            image::load_from_memory(&original).unwrap().as_bytes()
+        );
+        let credentials = verify_jpeg(&signed).unwrap().unwrap();
+        assert_eq!(credentials.creator.as_deref(), Some("Jane Doe"));
+        assert_eq!(credentials.camera_make.as_deref(), Some("FUJIFILM"));
+        assert_eq!(credentials.camera_model.as_deref(), Some("X-T5"));
+        assert_eq!(credentials.actions, ["c2pa.edited"]);
+        assert!(credentials.signer.contains("DigiCamWM Test Signer"), "{}", credentials.signer);
+        assert_eq!(verify_jpeg(&original).unwrap(), None);
+    }
+
+    #[test]
+    fn tampering_is_detected() {
+        let mut signed = Vec::new();
+        sign_jpeg(&jpeg(), &Provenance::default(), &test_signer(), &mut signed).unwrap();
+
+        let mut pixels = signed.clone();
+        let last = pixels.len() - 10;
+        pixels[last] ^= 0x55;
+        assert!(verify_jpeg(&pixels).unwrap_err().contains("changed"));
+
+        // Rewrite the model in the stds.exif assertion
+        let mut exif = signed.clone();
+        let at = exif.windows(4).position(|w| w == b"exif").unwrap();
+        let at = at + exif[at..].windows(2).position(|w| w == b"{\"").unwrap() + 2;
+        exif[at] = b'X';
+        assert!(verify_jpeg(&exif).is_err());
+
+        let other_key = p256::SecretKey::from_slice(&[7; 32]).unwrap().to_sec1_pem(Default::default()).unwrap();
+        let err = C2paSigner::from_pem(include_str!("../tests/c2pa/test-cert.pem"), &other_key).unwrap_err();
+        assert!(err.contains("doesn't match"), "{}", err);
+    }
+
+    #[test]
+    fn large_manifests_span_several_segments() {
+        let store = superbox(*b"c2pa", "c2pa", &[content_box(*b"json", &vec![b'x'; 150_000])]);
+        let mut jpeg = jpeg();
+        let at = manifest_offset(&jpeg).unwrap();
+        jpeg.splice(at..at, app11_segments(&store));
+        assert_eq!(read_store(&jpeg).unwrap().unwrap(), store);
+    }
+
+    #[test]
+    fn segment_lengths_past_the_end_are_not_followed() {
+        let truncated = [0xFF, 0xD8, 0xFF, 0xE1, 0xFF, 0xF0, 0x00];
+        assert_eq!(manifest_offset(&truncated), Ok(truncated.len()));
+    }
+}
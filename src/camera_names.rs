@@ -0,0 +1,290 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use crate::brands::{normalize, BrandMatch};
+
+// ─── Camera name database ─────────────────────────────
+
+/// How the camera model is printed in the frame.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ModelNameStyle {
+    /// EXIF Model as recorded, e.g. "ILCE-7M4"
+    Raw,
+    /// Marketing name with the brand in front, e.g. "Sony α7 IV"
+    #[default]
+    Friendly,
+}
+
+/// Maps EXIF Make + Model pairs to marketing names.
+///
+/// Keys are the normalized make (brand id or Make string) and the normalized
+/// model with that make stripped from its front, so "NIKON Z 6_2" and "Z 6_2"
+/// under `nikon` hit the same entry.
+#[derive(Clone)]
+pub struct CameraNames {
+    names: HashMap<(String, String), String>,
+}
+
+// ─── User file format ─────────────────────────────────
+
+#[derive(Deserialize)]
+struct CameraNameFile {
+    #[serde(default)]
+    models: Vec<CameraNameEntry>,
+}
+
+#[derive(Deserialize)]
+struct CameraNameEntry {
+    make: String,
+    model: String,
+    name: String,
+}
+
+// ─── Built-in names ───────────────────────────────────
+
+// (brand id, EXIF model, display name)
+const BUILTIN_NAMES: &[(&str, &str, &str)] = &[
+    // Sony
+    ("sony", "ILCE-1", "Sony α1"),
+    ("sony", "ILCE-1M2", "Sony α1 II"),
+    ("sony", "ILCE-7", "Sony α7"),
+    ("sony", "ILCE-7M2", "Sony α7 II"),
+    ("sony", "ILCE-7M3", "Sony α7 III"),
+    ("sony", "ILCE-7M4", "Sony α7 IV"),
+    ("sony", "ILCE-7RM2", "Sony α7R II"),
+    ("sony", "ILCE-7RM3", "Sony α7R III"),
+    ("sony", "ILCE-7RM3A", "Sony α7R IIIA"),
+    ("sony", "ILCE-7RM4", "Sony α7R IV"),
+    ("sony", "ILCE-7RM4A", "Sony α7R IVA"),
+    ("sony", "ILCE-7RM5", "Sony α7R V"),
+    ("sony", "ILCE-7SM2", "Sony α7S II"),
+    ("sony", "ILCE-7SM3", "Sony α7S III"),
+    ("sony", "ILCE-7C", "Sony α7C"),
+    ("sony", "ILCE-7CM2", "Sony α7C II"),
+    ("sony", "ILCE-7CR", "Sony α7CR"),
+    ("sony", "ILCE-9", "Sony α9"),
+    ("sony", "ILCE-9M2", "Sony α9 II"),
+    ("sony", "ILCE-9M3", "Sony α9 III"),
+    ("sony", "ILCE-6100", "Sony α6100"),
+    ("sony", "ILCE-6400", "Sony α6400"),
+    ("sony", "ILCE-6600", "Sony α6600"),
+    ("sony", "ILCE-6700", "Sony α6700"),
+    ("sony", "DSC-RX100M6", "Sony RX100 VI"),
+    ("sony", "DSC-RX100M7", "Sony RX100 VII"),
+    ("sony", "DSC-RX1RM2", "Sony RX1R II"),
+    // Olympus / OM System
+    ("olympus", "E-M1MarkII", "Olympus OM-D E-M1 Mark II"),
+    ("olympus", "E-M1MarkIII", "Olympus OM-D E-M1 Mark III"),
+    ("olympus", "E-M1X", "Olympus OM-D E-M1X"),
+    ("olympus", "E-M5MarkII", "Olympus OM-D E-M5 Mark II"),
+    ("olympus", "E-M5MarkIII", "Olympus OM-D E-M5 Mark III"),
+    ("olympus", "E-M10MarkIII", "Olympus OM-D E-M10 Mark III"),
+    ("olympus", "E-M10MarkIV", "Olympus OM-D E-M10 Mark IV"),
+    ("olympus", "E-P7", "Olympus PEN E-P7"),
+    ("olympus", "OM-1", "OM System OM-1"),
+    ("olympus", "OM-1MarkII", "OM System OM-1 Mark II"),
+    ("olympus", "OM-5", "OM System OM-5"),
+    // Panasonic
+    ("panasonic", "DC-S1", "Panasonic Lumix S1"),
+    ("panasonic", "DC-S1R", "Panasonic Lumix S1R"),
+    ("panasonic", "DC-S1H", "Panasonic Lumix S1H"),
+    ("panasonic", "DC-S5", "Panasonic Lumix S5"),
+    ("panasonic", "DC-S5M2", "Panasonic Lumix S5 II"),
+    ("panasonic", "DC-S5M2X", "Panasonic Lumix S5 IIX"),
+    ("panasonic", "DC-S9", "Panasonic Lumix S9"),
+    ("panasonic", "DC-GH5", "Panasonic Lumix GH5"),
+    ("panasonic", "DC-GH5M2", "Panasonic Lumix GH5 II"),
+    ("panasonic", "DC-GH6", "Panasonic Lumix GH6"),
+    ("panasonic", "DC-G9", "Panasonic Lumix G9"),
+    ("panasonic", "DC-G9M2", "Panasonic Lumix G9 II"),
+    ("panasonic", "DC-GX9", "Panasonic Lumix GX9"),
+    ("panasonic", "DMC-GX85", "Panasonic Lumix GX85"),
+    // Nikon writes the generation as a suffix
+    ("nikon", "NIKON Z 6_2", "Nikon Z6II"),
+    ("nikon", "NIKON Z 7_2", "Nikon Z7II"),
+    ("nikon", "NIKON Z6_3", "Nikon Z6III"),
+    // DJI drones report the camera module
+    ("dji", "FC3411", "DJI Air 2S"),
+    ("dji", "FC3582", "DJI Mini 3 Pro"),
+    ("dji", "FC8482", "DJI Mini 4 Pro"),
+];
+
+/// Normalized model with the make stripped from its front.
+fn model_key(make_key: &str, model: &str) -> String {
+    let model = normalize(model);
+    match model.strip_prefix(make_key).and_then(|rest| rest.strip_prefix(' ')) {
+        Some(rest) => rest.to_string(),
+        None => model,
+    }
+}
+
+impl CameraNames {
+    pub fn builtin() -> Self {
+        let mut names = Self { names: HashMap::new() };
+        for (make, model, name) in BUILTIN_NAMES {
+            names.insert(make, model, name);
+        }
+        names
+    }
+
+    /// Built-in names extended with the user's file, if present. A broken
+    /// user file is logged and ignored rather than failing the run.
+    pub fn load(user_file: Option<&Path>) -> Self {
+        let mut names = Self::builtin();
+        if let Some(path) = user_file.filter(|p| p.exists()) {
+            if let Err(e) = names.extend_from_file(path) {
+                log::warn!("Ignoring camera name file {}: {}", path.display(), e);
+            }
+        }
+        names
+    }
+
+    pub fn extend_from_file(&mut self, path: &Path) -> Result<(), String> {
+        let data = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        self.extend_from_json(&data)
+    }
+
+    /// Merges user entries; they replace built-in names for the same model.
+    pub fn extend_from_json(&mut self, json: &str) -> Result<(), String> {
+        let file: CameraNameFile =
+            serde_json::from_str(json).map_err(|e| format!("Invalid camera name file: {}", e))?;
+        for entry in file.models {
+            self.insert(&entry.make, &entry.model, &entry.name);
+        }
+        Ok(())
+    }
+
+    fn insert(&mut self, make: &str, model: &str, name: &str) {
+        let make_key = normalize(make);
+        let model_key = model_key(&make_key, model);
+        self.names.insert((make_key, model_key), name.to_string());
+    }
+
+    fn lookup(&self, camera_make: &str, camera_model: &str, brand: Option<&BrandMatch>) -> Option<&str> {
+        let make_keys = brand
+            .map(|b| b.brand.id.clone())
+            .into_iter()
+            .chain(std::iter::once(normalize(camera_make)));
+        for make_key in make_keys {
+            let key = (make_key.clone(), model_key(&make_key, camera_model));
+            if let Some(name) = self.names.get(&key) {
+                return Some(name);
+            }
+        }
+        None
+    }
+
+    /// Text shown for the camera in the frame.
+    ///
+    /// Friendly names come from the database; unknown models get the brand's
+    /// canonical name in front unless the Model already starts with it, so
+    /// "Canon EOS R5" never becomes "Canon Canon EOS R5".
+    pub fn display_name(
+        &self,
+        camera_make: &str,
+        camera_model: &str,
+        brand: Option<&BrandMatch>,
+        style: ModelNameStyle,
+    ) -> String {
+        let camera_model = camera_model.trim();
+        if style == ModelNameStyle::Raw {
+            return if camera_model.contains("iPhone") {
+                format!("Apple {}", camera_model)
+            } else {
+                camera_model.to_string()
+            };
+        }
+
+        if let Some(name) = self.lookup(camera_make, camera_model, brand) {
+            return name.to_string();
+        }
+
+        let Some(brand) = brand.map(|b| b.brand) else {
+            return camera_model.to_string();
+        };
+        match brand.model_prefix_len(camera_model) {
+            // "NIKON Z 8" → "Nikon Z 8"
+            Some(len) if camera_model[..len].eq_ignore_ascii_case(&brand.name) => {
+                format!("{}{}", brand.name, &camera_model[len..])
+            }
+            // Another alias of the brand, e.g. "RICOH GR III" under Pentax
+            Some(_) => camera_model.to_string(),
+            None => format!("{} {}", brand.name, camera_model),
+        }
+    }
+}
+
+impl Default for CameraNames {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::brands::BrandRegistry;
+
+    fn friendly(make: &str, model: &str) -> String {
+        let brands = BrandRegistry::builtin();
+        let brand = brands.resolve(make, model);
+        CameraNames::builtin().display_name(make, model, brand.as_ref(), ModelNameStyle::Friendly)
+    }
+
+    #[test]
+    fn maps_cryptic_models_to_marketing_names() {
+        assert_eq!(friendly("SONY", "ILCE-7M4"), "Sony α7 IV");
+        assert_eq!(friendly("OLYMPUS CORPORATION", "E-M1MarkIII"), "Olympus OM-D E-M1 Mark III");
+        assert_eq!(friendly("OM Digital Solutions", "OM-1"), "OM System OM-1");
+        assert_eq!(friendly("Panasonic", "DC-S5M2"), "Panasonic Lumix S5 II");
+        assert_eq!(friendly("NIKON CORPORATION", "NIKON Z 6_2"), "Nikon Z6II");
+    }
+
+    #[test]
+    fn does_not_duplicate_brand_prefix() {
+        assert_eq!(friendly("Canon", "Canon EOS R5"), "Canon EOS R5");
+        assert_eq!(friendly("NIKON CORPORATION", "NIKON Z 8"), "Nikon Z 8");
+        assert_eq!(friendly("FUJIFILM", "X-T5"), "Fujifilm X-T5");
+        assert_eq!(friendly("Apple", "iPhone 15 Pro"), "Apple iPhone 15 Pro");
+        assert_eq!(friendly("RICOH IMAGING COMPANY, LTD.", "RICOH GR III"), "RICOH GR III");
+        assert_eq!(friendly("", "Mystery Cam"), "Mystery Cam");
+    }
+
+    #[test]
+    fn raw_style_keeps_exif_model() {
+        let names = CameraNames::builtin();
+        assert_eq!(names.display_name("SONY", "ILCE-7M4", None, ModelNameStyle::Raw), "ILCE-7M4");
+        assert_eq!(
+            names.display_name("Apple", "iPhone 15 Pro", None, ModelNameStyle::Raw),
+            "Apple iPhone 15 Pro"
+        );
+    }
+
+    #[test]
+    fn user_file_overrides_builtin_names() {
+        let mut names = CameraNames::builtin();
+        names
+            .extend_from_json(
+                r#"{"models": [
+                    {"make": "sony", "model": "ILCE-7M4", "name": "Sony A7 IV"},
+                    {"make": "Leica Camera AG", "model": "LEICA Q3", "name": "Leica Q3"}
+                ]}"#,
+            )
+            .unwrap();
+        let brands = BrandRegistry::builtin();
+        let sony = brands.resolve("SONY", "ILCE-7M4");
+        assert_eq!(
+            names.display_name("SONY", "ILCE-7M4", sony.as_ref(), ModelNameStyle::Friendly),
+            "Sony A7 IV"
+        );
+        assert_eq!(
+            names.display_name("Leica Camera AG", "LEICA Q3", None, ModelNameStyle::Friendly),
+            "Leica Q3"
+        );
+        assert!(names.extend_from_json("{not json").is_err());
+    }
+}
@@ -0,0 +1,266 @@
+use std::sync::OnceLock;
+
+use moxcms::{CmsError, ColorProfile, Layout, TransformExecutor, TransformOptions};
+use serde::Deserialize;
+
+// ─── Output colour space ──────────────────────────────
+
+/// Colour space written to the output file.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ColorSpace {
+    #[default]
+    Srgb,
+    DisplayP3,
+    AdobeRgb,
+    /// Keep the photo's own profile (untagged photos stay sRGB)
+    KeepSource,
+}
+
+impl ColorSpace {
+    /// Parses the names used by the CLI and the frontend.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "srgb" => Some(ColorSpace::Srgb),
+            "display-p3" | "p3" => Some(ColorSpace::DisplayP3),
+            "adobe-rgb" | "adobergb" => Some(ColorSpace::AdobeRgb),
+            "keep-source" | "keep" => Some(ColorSpace::KeepSource),
+            _ => None,
+        }
+    }
+}
+
+// ─── Conversion ───────────────────────────────────────
+
+/// Sample types the pipeline can convert (8-bit and 16-bit RGBA).
+pub(crate) trait Sample: Copy + Default + 'static {
+    fn transform(
+        src: &ColorProfile,
+        dst: &ColorProfile,
+    ) -> Result<Box<dyn TransformExecutor<Self> + Send + Sync>, CmsError>;
+}
+
+impl Sample for u8 {
+    fn transform(
+        src: &ColorProfile,
+        dst: &ColorProfile,
+    ) -> Result<Box<dyn TransformExecutor<u8> + Send + Sync>, CmsError> {
+        src.create_transform_8bit(Layout::Rgba, dst, Layout::Rgba, TransformOptions::default())
+    }
+}
+
+impl Sample for u16 {
+    fn transform(
+        src: &ColorProfile,
+        dst: &ColorProfile,
+    ) -> Result<Box<dyn TransformExecutor<u16> + Send + Sync>, CmsError> {
+        src.create_transform_16bit(Layout::Rgba, dst, Layout::Rgba, TransformOptions::default())
+    }
+}
+
+/// Source and destination profile of one conversion step.
+struct Conversion {
+    src: ColorProfile,
+    dst: ColorProfile,
+}
+
+impl Conversion {
+    fn new(src: &ColorProfile, dst: &ColorProfile) -> Self {
+        Self { src: src.clone(), dst: dst.clone() }
+    }
+}
+
+/// A built transform, reusable across strips of the same image.
+pub(crate) struct Converter<T: Sample>(Option<Box<dyn TransformExecutor<T> + Send + Sync>>);
+
+impl<T: Sample> Converter<T> {
+    fn new(conversion: Option<&Conversion>) -> Result<Self, String> {
+        conversion
+            .map(|c| T::transform(&c.src, &c.dst))
+            .transpose()
+            .map(Converter)
+            .map_err(|e| format!("Failed to build colour transform: {}", e))
+    }
+
+    /// Converts RGBA pixels in place, one row of `width` pixels at a time.
+    pub(crate) fn apply(&self, pixels: &mut [T], width: u32) -> Result<(), String> {
+        let Some(transform) = &self.0 else {
+            return Ok(());
+        };
+        let row_len = (width as usize * 4).max(4);
+        let mut scratch = vec![T::default(); row_len.min(pixels.len())];
+        for row in pixels.chunks_mut(row_len) {
+            let src = &mut scratch[..row.len()];
+            src.copy_from_slice(row);
+            transform
+                .transform(src, row)
+                .map_err(|e| format!("Colour conversion failed: {}", e))?;
+        }
+        Ok(())
+    }
+}
+
+/// Conversions for one image: photo pixels from the embedded profile to the
+/// output space, and frame pixels (drawn in sRGB, like the colours in
+/// `designs.rs`) to that same space. `None` means no conversion is needed.
+pub(crate) struct ColorPipeline {
+    photo: Option<Conversion>,
+    frame: Option<Conversion>,
+    /// ICC profile to embed in the output
+    pub icc_profile: Option<Vec<u8>>,
+}
+
+impl ColorPipeline {
+    /// Plans the conversion for a photo with the given embedded ICC profile.
+    /// Unreadable profiles are logged and the photo is treated as sRGB.
+    pub(crate) fn new(target: ColorSpace, source_icc: Option<&[u8]>) -> Result<Self, String> {
+        let source = source_icc.and_then(|icc| match ColorProfile::new_from_slice(icc) {
+            Ok(profile) => Some(profile),
+            Err(e) => {
+                log::warn!("Ignoring unreadable ICC profile: {}", e);
+                None
+            }
+        });
+        let srgb = ColorProfile::new_srgb();
+
+        let output = match target {
+            ColorSpace::Srgb => srgb.clone(),
+            ColorSpace::DisplayP3 => ColorProfile::new_display_p3(),
+            ColorSpace::AdobeRgb => ColorProfile::new_adobe_rgb(),
+            ColorSpace::KeepSource => {
+                // Photo pixels stay as they are; only the frame is converted
+                let Some(source) = source else {
+                    return Ok(Self { photo: None, frame: None, icc_profile: None });
+                };
+                return Ok(Self {
+                    photo: None,
+                    frame: Some(Conversion::new(&srgb, &source)),
+                    icc_profile: source_icc.map(<[u8]>::to_vec),
+                });
+            }
+        };
+
+        let photo = match &source {
+            Some(source) => Some(Conversion::new(source, &output)),
+            None if target != ColorSpace::Srgb => Some(Conversion::new(&srgb, &output)),
+            None => None,
+        };
+        let frame = match target {
+            ColorSpace::Srgb => None,
+            _ => Some(Conversion::new(&srgb, &output)),
+        };
+        let icc_profile = target_profile(target)?.to_vec();
+
+        Ok(Self { photo, frame, icc_profile: Some(icc_profile) })
+    }
+
+    pub(crate) fn photo_converter<T: Sample>(&self) -> Result<Converter<T>, String> {
+        Converter::new(self.photo.as_ref())
+    }
+
+    pub(crate) fn frame_converter<T: Sample>(&self) -> Result<Converter<T>, String> {
+        Converter::new(self.frame.as_ref())
+    }
+
+    /// Converts RGBA photo pixels, `width` to a row, in place.
+    pub(crate) fn convert_photo<T: Sample>(&self, pixels: &mut [T], width: u32) -> Result<(), String> {
+        self.photo_converter()?.apply(pixels, width)
+    }
+
+    /// Converts RGBA frame pixels, `width` to a row, in place.
+    pub(crate) fn convert_frame<T: Sample>(&self, pixels: &mut [T], width: u32) -> Result<(), String> {
+        self.frame_converter()?.apply(pixels, width)
+    }
+}
+
+// ─── Embedded profiles ────────────────────────────────
+
+/// Creation date written into embedded profiles (2024-01-01 00:00:00).
+/// moxcms stamps the current time, so the same photo would otherwise come
+/// out different on every run.
+const PROFILE_DATE: [u16; 6] = [2024, 1, 1, 0, 0, 0];
+
+/// ICC profile embedded for a target colour space, encoded once.
+pub(crate) fn target_profile(target: ColorSpace) -> Result<&'static [u8], String> {
+    static SRGB: OnceLock<Result<Vec<u8>, String>> = OnceLock::new();
+    static DISPLAY_P3: OnceLock<Result<Vec<u8>, String>> = OnceLock::new();
+    static ADOBE_RGB: OnceLock<Result<Vec<u8>, String>> = OnceLock::new();
+
+    let (cell, profile): (_, fn() -> ColorProfile) = match target {
+        ColorSpace::Srgb | ColorSpace::KeepSource => (&SRGB, ColorProfile::new_srgb),
+        ColorSpace::DisplayP3 => (&DISPLAY_P3, ColorProfile::new_display_p3),
+        ColorSpace::AdobeRgb => (&ADOBE_RGB, ColorProfile::new_adobe_rgb),
+    };
+    cell.get_or_init(|| {
+        let mut icc = profile()
+            .encode()
+            .map_err(|e| format!("Failed to encode ICC profile: {}", e))?;
+        for (field, value) in icc[24..36].chunks_exact_mut(2).zip(PROFILE_DATE) {
+            field.copy_from_slice(&value.to_be_bytes());
+        }
+        Ok(icc)
+    })
+    .as_deref()
+    .map_err(Clone::clone)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn untagged_srgb_output_is_left_alone() {
+        let pipeline = ColorPipeline::new(ColorSpace::Srgb, None).unwrap();
+        let mut px = [200u8, 80, 40, 255];
+        pipeline.convert_photo(&mut px, 1).unwrap();
+        pipeline.convert_frame(&mut px, 1).unwrap();
+        assert_eq!(px, [200, 80, 40, 255]);
+
+        let icc = pipeline.icc_profile.expect("sRGB profile is embedded");
+        assert!(ColorProfile::new_from_slice(&icc).is_ok());
+        // Same bytes on every run, not stamped with the current time
+        assert_eq!(&icc[24..30], &[0x07, 0xe8, 0, 1, 0, 1]);
+    }
+
+    #[test]
+    fn frame_colours_are_converted_to_wide_gamut_output() {
+        let pipeline = ColorPipeline::new(ColorSpace::DisplayP3, None).unwrap();
+        // Pure sRGB red sits inside P3, so it loses saturation there
+        let mut px = [255u8, 0, 0, 255];
+        pipeline.convert_frame(&mut px, 1).unwrap();
+        assert!(px[0] < 245 && px[1] > 30 && px[2] > 20, "{:?}", px);
+        assert_eq!(px[3], 255);
+
+        let mut px16 = [65535u16, 0, 0, 65535];
+        pipeline.convert_frame(&mut px16, 1).unwrap();
+        assert!(px16[0] < 63000 && px16[1] > 7500, "{:?}", px16);
+
+        // Row by row gives the same pixels, including a short last row
+        let mut rows = [255u8, 0, 0, 255].repeat(5);
+        pipeline.convert_frame(&mut rows, 2).unwrap();
+        assert_eq!(rows, px.repeat(5));
+    }
+
+    #[test]
+    fn tagged_photo_is_converted_to_srgb() {
+        let adobe = ColorProfile::new_adobe_rgb().encode().unwrap();
+        let pipeline = ColorPipeline::new(ColorSpace::Srgb, Some(&adobe)).unwrap();
+        let mut px = [40u8, 200, 60, 255];
+        pipeline.convert_photo(&mut px, 1).unwrap();
+        // AdobeRGB green is more saturated than sRGB can show
+        assert!(px[0] < 40 && px[1] > 200, "{:?}", px);
+    }
+
+    #[test]
+    fn keep_source_embeds_original_profile() {
+        let adobe = ColorProfile::new_adobe_rgb().encode().unwrap();
+        let pipeline = ColorPipeline::new(ColorSpace::KeepSource, Some(&adobe)).unwrap();
+        let mut px = [40u8, 200, 60, 255];
+        pipeline.convert_photo(&mut px, 1).unwrap();
+        assert_eq!(px, [40, 200, 60, 255]);
+        assert_eq!(pipeline.icc_profile.as_deref(), Some(adobe.as_slice()));
+
+        let untagged = ColorPipeline::new(ColorSpace::KeepSource, None).unwrap();
+        assert!(untagged.icc_profile.is_none());
+    }
+}
@@ -1,22 +1,46 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::Mutex;
 use tauri::{AppHandle, Emitter, Manager, State};
 
+use crate::batch::{self, Batch, BatchEvent};
+use crate::brands::BrandRegistry;
+use crate::c2pa::C2paSigner;
+use crate::camera_names::{CameraNames, ModelNameStyle};
+use crate::color::ColorSpace;
+use crate::contact_sheet::ContactSheetOptions;
 use crate::designs::{self, DesignInfo};
-use crate::watermark;
+use crate::engine::{self, DesignOutput, FrostOptions, LogoSource, Watermarker};
+use crate::filters::BatchFilter;
+use crate::fonts;
+use crate::histogram::HistogramStyle;
+use crate::invisible::Payload;
+use crate::palette::FrameColor;
+use crate::rights::Rights;
+use crate::rules::{self, DesignRules};
+use crate::sidecar::{self, SidecarSigner, SignatureMode};
+
+/// Contact sheet written to the output folder after a run.
+const CONTACT_SHEET_FILE: &str = "contact-sheet.pdf";
+
+/// Ed25519 key in the app config folder for signature sidecars.
+const SIGNING_KEY_FILE: &str = "signing-key.pem";
 
 // ─── App State ────────────────────────────────────────
 
 pub struct AppState {
     pub current_design_id: Mutex<String>,
+    /// User-selected font family per design ID
+    pub design_fonts: Mutex<HashMap<String, String>>,
 }
 
 impl Default for AppState {
     fn default() -> Self {
         Self {
             current_design_id: Mutex::new("classic".into()),
+            design_fonts: Mutex::new(HashMap::new()),
         }
     }
 }
@@ -39,6 +63,14 @@ pub enum ProcessStatus {
     Complete,
 }
 
+#[derive(Clone, Serialize)]
+pub struct SignatureCheck {
+    pub file: String,
+    pub valid: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
 #[derive(Clone, Serialize)]
 pub struct ImageProcessed {
     pub success: bool,
@@ -47,6 +79,12 @@ pub struct ImageProcessed {
     pub file: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub error: Option<String>,
+    /// Fields set by the photo's sidecar, e.g. `["camera", "caption"]`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub overrides: Vec<String>,
+    /// Why the run's filter left the photo out
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub skipped: Option<String>,
 }
 
 #[derive(Clone, Serialize)]
@@ -62,6 +100,50 @@ pub struct ProcessResult {
     pub message: String,
 }
 
+// ─── Run Options ──────────────────────────────────────
+
+/// Settings of one processing run. Everything but the folders and the
+/// photographer is optional and falls back to the default look.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RunOptions {
+    pub input_dir: String,
+    pub output_dir: String,
+    pub photographer_name: String,
+    #[serde(default)]
+    pub model_names: ModelNameStyle,
+    #[serde(default)]
+    pub color_space: ColorSpace,
+    #[serde(default)]
+    pub frame_color: FrameColor,
+    pub frost_blur: Option<f32>,
+    pub frost_dim: Option<f32>,
+    pub memory_budget_mb: Option<u64>,
+    #[serde(default)]
+    pub transparent_frame: bool,
+    #[serde(default)]
+    pub swatches: usize,
+    pub histogram: Option<HistogramStyle>,
+    pub qr_link: Option<String>,
+    pub invisible_mark: Option<u32>,
+    #[serde(default)]
+    pub content_credentials: bool,
+    pub signatures: Option<SignatureMode>,
+    #[serde(default)]
+    pub contact_sheet: bool,
+    /// Extra designs every photo is also framed in
+    #[serde(default)]
+    pub designs: Vec<String>,
+    #[serde(default)]
+    pub design_output: DesignOutput,
+    #[serde(default)]
+    pub show_title: bool,
+    #[serde(default)]
+    pub filter: BatchFilter,
+    #[serde(default)]
+    pub rights: Rights,
+}
+
 // ─── Commands ─────────────────────────────────────────
 
 #[tauri::command]
@@ -94,34 +176,68 @@ pub fn get_current_design(state: State<'_, AppState>) -> Result<serde_json::Valu
     }))
 }
 
+#[tauri::command]
+pub fn get_font_families() -> Vec<String> {
+    fonts::available_families()
+}
+
+#[tauri::command]
+pub fn set_design_font(
+    state: State<'_, AppState>,
+    design_id: String,
+    font_family: Option<String>,
+) -> Result<serde_json::Value, String> {
+    let mut design_fonts = state.design_fonts.lock().map_err(|e| e.to_string())?;
+    match font_family.as_deref().map(str::trim).filter(|f| !f.is_empty()) {
+        Some(family) => design_fonts.insert(design_id.clone(), family.to_string()),
+        None => design_fonts.remove(&design_id),
+    };
+    Ok(serde_json::json!({ "success": true, "designId": design_id, "fontFamily": font_family }))
+}
+
 #[tauri::command]
 pub async fn start_processing(
     app: AppHandle,
     state: State<'_, AppState>,
-    input_dir: String,
-    output_dir: String,
-    photographer_name: String,
+    options: RunOptions,
 ) -> Result<ProcessResult, String> {
+    options.filter.validate()?;
     let design_id = {
         let current = state.current_design_id.lock().map_err(|e| e.to_string())?;
         current.clone()
     };
+    let design_fonts = state.design_fonts.lock().map_err(|e| e.to_string())?.clone();
+    let watermarker = build_watermarker(&app, &options, &design_id, design_fonts.get(&design_id).cloned())?;
+
+    let config_dir = app.path().app_config_dir().ok();
+    let rules_file = config_dir.as_ref().map(|dir| dir.join(rules::RULES_FILE));
+
+    // Ed25519 signatures use signing-key.pem from the config folder,
+    // created on first use
+    let mut sidecar_signer = match (options.signatures, &config_dir) {
+        (None, _) => None,
+        (Some(mode), Some(dir)) => Some(SidecarSigner::load_or_create(&dir.join(SIGNING_KEY_FILE), mode)?),
+        (Some(_), None) => return Err("No config folder for the signing key".into()),
+    };
 
-    // Resolve models directory (bundled assets)
-    let models_dir = app
-        .path()
-        .resource_dir()
-        .map_err(|e| format!("Failed to resolve resource dir: {}", e))?
-        .join("assets")
-        .join("models");
+    // Extra designs render every photo once each, from a single decode
+    let variants: Vec<Watermarker> = options
+        .designs
+        .iter()
+        .map(|id| watermarker.with_design(id, design_fonts.get(id).cloned()))
+        .collect();
+    // Saved design rules pick the design per photo, unless the run asked
+    // for every photo in several designs
+    let rules = if variants.is_empty() { DesignRules::load(rules_file.as_deref()) } else { DesignRules::default() };
+    let by_rule = rules.watermarkers(&watermarker, |id| design_fonts.get(id).cloned());
 
     // Ensure output directory exists
-    fs::create_dir_all(&output_dir)
+    fs::create_dir_all(&options.output_dir)
         .map_err(|e| format!("Failed to create output dir: {}", e))?;
 
     // List image files
-    let image_extensions = ["jpg", "jpeg", "png", "tiff", "webp"];
-    let entries = fs::read_dir(&input_dir)
+    let image_extensions = ["jpg", "jpeg", "png", "tif", "tiff", "webp"];
+    let entries = fs::read_dir(&options.input_dir)
         .map_err(|e| format!("Failed to read input dir: {}", e))?;
 
     let mut image_files: Vec<String> = Vec::new();
@@ -142,41 +258,66 @@ pub async fn start_processing(
     });
 
     let total = image_files.len();
-    for (i, file) in image_files.iter().enumerate() {
-        let input_path = Path::new(&input_dir).join(file);
-        let output_path = Path::new(&output_dir).join(file);
-
-        // Emit progress
-        let _ = app.emit("process-status", ProcessStatus::Progress {
-            current: i + 1,
-            current_file: file.clone(),
-            total,
-        });
-
-        match watermark::add_watermark_frame(
-            &input_path,
-            &output_path,
-            &design_id,
-            &photographer_name,
-            &models_dir,
-        ) {
-            Ok(_) => {
-                let _ = app.emit("image-processed", ImageProcessed {
-                    success: true,
-                    path: Some(output_path.to_string_lossy().to_string()),
-                    file: file.clone(),
-                    error: None,
-                });
-            }
-            Err(e) => {
-                let _ = app.emit("image-processed", ImageProcessed {
-                    success: false,
-                    path: None,
-                    file: file.clone(),
-                    error: Some(e),
-                });
-            }
+    let photos: Vec<(PathBuf, PathBuf)> = image_files
+        .iter()
+        .map(|file| (Path::new(&options.input_dir).join(file), Path::new(&options.output_dir).join(file)))
+        .collect();
+    let batch = Batch {
+        filter: &options.filter,
+        rules: &by_rule,
+        variants: &variants,
+        design_output: options.design_output,
+        signer: sidecar_signer.as_mut(),
+    };
+    let file_name = |input: &Path| input.file_name().unwrap_or_default().to_string_lossy().to_string();
+    let summary = batch::frame_batch(&photos, batch, |event| match event {
+        BatchEvent::Started { index, input } => {
+            let _ = app.emit("process-status", ProcessStatus::Progress {
+                current: index + 1,
+                current_file: file_name(input),
+                total,
+            });
         }
+        BatchEvent::Skipped { input, reason } => {
+            let _ = app.emit("image-processed", ImageProcessed {
+                success: false,
+                path: None,
+                file: file_name(input),
+                error: None,
+                overrides: Vec::new(),
+                skipped: Some(reason),
+            });
+        }
+        BatchEvent::Framed { input, output, overrides } => {
+            let _ = app.emit("image-processed", ImageProcessed {
+                success: true,
+                path: Some(output.to_string_lossy().to_string()),
+                file: file_name(input),
+                error: None,
+                overrides: overrides.iter().map(|o| o.to_string()).collect(),
+                skipped: None,
+            });
+        }
+        BatchEvent::Failed { input, error } => {
+            let _ = app.emit("image-processed", ImageProcessed {
+                success: false,
+                path: None,
+                file: file_name(input),
+                error: Some(error),
+                overrides: Vec::new(),
+                skipped: None,
+            });
+        }
+    });
+
+    if let Some(signer) = sidecar_signer {
+        signer.finish()?;
+    }
+
+    // Contact sheet of the framed photos, captioned from the originals
+    if options.contact_sheet && !summary.framed.is_empty() {
+        let sheet = Path::new(&options.output_dir).join(CONTACT_SHEET_FILE);
+        watermarker.contact_sheet(&summary.framed, &sheet, &ContactSheetOptions::default())?;
     }
 
     // Emit complete
@@ -184,10 +325,125 @@ pub async fn start_processing(
 
     Ok(ProcessResult {
         success: true,
-        message: format!("Processed {} images", total),
+        message: match summary.skipped {
+            0 => format!("Processed {} images", total),
+            skipped => format!("Processed {} images, skipped {}", total - skipped, skipped),
+        },
     })
 }
 
+/// Frame settings of the run, with the logos, fonts, brand files and
+/// content credentials certificate taken from the app's folders.
+fn build_watermarker(
+    app: &AppHandle,
+    options: &RunOptions,
+    design_id: &str,
+    font_family: Option<String>,
+) -> Result<Watermarker, String> {
+    // Resolve models directory (bundled assets)
+    let resource_dir = app
+        .path()
+        .resource_dir()
+        .map_err(|e| format!("Failed to resolve resource dir: {}", e))?;
+    let models_dir = resource_dir.join("assets").join("models");
+
+    // The bundled fonts are embedded; add any the user dropped into the
+    // config folder
+    let config_dir = app.path().app_config_dir().ok();
+    let font_dirs: Vec<PathBuf> = config_dir.iter().map(|dir| dir.join("fonts")).collect();
+
+    // Built-in brand aliases and model names, extended by the user's
+    // brands.json / camera_names.json if present
+    let brands_file = config_dir.as_ref().map(|dir| dir.join("brands.json"));
+    let camera_names_file = config_dir.as_ref().map(|dir| dir.join("camera_names.json"));
+
+    // Content credentials are signed with c2pa-cert.pem / c2pa-key.pem from
+    // the config folder
+    let signer = match (options.content_credentials, &config_dir) {
+        (false, _) => None,
+        (true, Some(dir)) => Some(C2paSigner::load(&dir.join("c2pa-cert.pem"), &dir.join("c2pa-key.pem"))?),
+        (true, None) => return Err("No config folder for the content credentials certificate".into()),
+    };
+
+    let defaults = FrostOptions::default();
+    let mut builder = Watermarker::builder();
+    if let Some(mb) = options.memory_budget_mb {
+        let bytes = mb
+            .checked_mul(1024 * 1024)
+            .ok_or_else(|| format!("Memory budget of {} MiB is too large", mb))?;
+        builder = builder.memory_budget(bytes);
+    }
+    Ok(builder
+        .design(design_id)
+        .photographer_name(&options.photographer_name)
+        .font_family(font_family)
+        .font_dirs(font_dirs)
+        .brands(BrandRegistry::load(brands_file.as_deref()))
+        .camera_names(CameraNames::load(camera_names_file.as_deref()))
+        .model_names(options.model_names)
+        .color_space(options.color_space)
+        .frame_color(options.frame_color)
+        .frost(FrostOptions {
+            blur: options.frost_blur.unwrap_or(defaults.blur),
+            dim: options.frost_dim.unwrap_or(defaults.dim),
+        })
+        .transparent_frame(options.transparent_frame)
+        .swatches(options.swatches)
+        .histogram(options.histogram)
+        .qr_link(options.qr_link.clone())
+        .invisible_mark(options.invisible_mark)
+        .content_credentials(signer)
+        .show_title(options.show_title)
+        .rights(options.rights.clone())
+        .logos(LogoSource::Directory(models_dir))
+        .build())
+}
+
+/// The saved design rules, or `null` when there are none.
+#[tauri::command]
+pub fn get_design_rules(app: AppHandle) -> Result<serde_json::Value, String> {
+    let config_dir = app.path().app_config_dir().map_err(|e| e.to_string())?;
+    let path = config_dir.join(rules::RULES_FILE);
+    if !path.exists() {
+        return Ok(serde_json::Value::Null);
+    }
+    let data = fs::read_to_string(&path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    serde_json::from_str(&data).map_err(|e| format!("Invalid design rules: {}", e))
+}
+
+/// Checks and saves the design rules used by later runs.
+#[tauri::command]
+pub fn save_design_rules(app: AppHandle, rules: serde_json::Value) -> Result<serde_json::Value, String> {
+    let json = serde_json::to_string_pretty(&rules).map_err(|e| e.to_string())?;
+    DesignRules::from_json(&json)?;
+    let config_dir = app.path().app_config_dir().map_err(|e| e.to_string())?;
+    fs::create_dir_all(&config_dir).map_err(|e| format!("Failed to create config dir: {}", e))?;
+    fs::write(config_dir.join(rules::RULES_FILE), json)
+        .map_err(|e| format!("Failed to save design rules: {}", e))?;
+    Ok(serde_json::json!({ "success": true }))
+}
+
+#[tauri::command]
+pub fn verify_watermark(path: String) -> Result<Option<Payload>, String> {
+    let data = fs::read(&path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    engine::verify_watermark(&data)
+}
+
+/// Checks delivered files against their `.sig` sidecars or the folder's
+/// `signatures.json`, with the public half of the configured signing key.
+#[tauri::command]
+pub fn verify_signatures(app: AppHandle, paths: Vec<String>) -> Result<Vec<SignatureCheck>, String> {
+    let config_dir = app.path().app_config_dir().map_err(|e| e.to_string())?;
+    let key = sidecar::load_verifying_key(&config_dir.join(SIGNING_KEY_FILE))?;
+    Ok(paths
+        .into_iter()
+        .map(|file| {
+            let error = sidecar::verify_file(Path::new(&file), &key).err();
+            SignatureCheck { valid: error.is_none(), file, error }
+        })
+        .collect())
+}
+
 #[tauri::command]
 pub async fn check_for_updates(app: AppHandle) -> Result<(), String> {
     let version = app.package_info().version.to_string();
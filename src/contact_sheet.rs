@@ -0,0 +1,500 @@
+//! Contact sheets: pages with a grid of thumbnails, each captioned with the
+//! file name and key EXIF, under a header with the photographer, the date
+//! range and the design. Pages are written as numbered JPEGs or as one PDF.
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use image::codecs::jpeg::JpegEncoder;
+use image::{DynamicImage, Limits, RgbImage};
+use std::fs;
+use std::io::{BufReader, Cursor, Write};
+use std::path::{Path, PathBuf};
+
+use crate::designs;
+use crate::engine::{Watermarker, DEFAULT_MEMORY_BUDGET};
+use crate::fonts;
+use crate::watermark::{self, ExifInfo};
+
+/// Page size and grid of a contact sheet.
+#[derive(Debug, Clone)]
+pub struct ContactSheetOptions {
+    pub columns: u32,
+    pub rows: u32,
+    /// Page size in pixels
+    pub page_width: u32,
+    pub page_height: u32,
+    /// Resolution the PDF page size is derived from
+    pub dpi: f64,
+    /// Folder with the source photos. Captions of images without EXIF and
+    /// without a [`SheetImage::source`] use the EXIF of the file with the
+    /// same name there.
+    pub source_dir: Option<PathBuf>,
+}
+
+/// A photo on the contact sheet.
+#[derive(Debug, Clone)]
+pub struct SheetImage {
+    /// File the thumbnail is made from
+    pub path: PathBuf,
+    /// Original the caption's EXIF is read from when `path` has none, e.g.
+    /// the input a framed output was made from
+    pub source: Option<PathBuf>,
+}
+
+impl From<PathBuf> for SheetImage {
+    fn from(path: PathBuf) -> Self {
+        Self { path, source: None }
+    }
+}
+
+impl Default for ContactSheetOptions {
+    /// A4 portrait at 150 dpi, 4 × 5 photos per page.
+    fn default() -> Self {
+        Self {
+            columns: 4,
+            rows: 5,
+            page_width: 1240,
+            page_height: 1754,
+            dpi: 150.0,
+            source_dir: None,
+        }
+    }
+}
+
+const MARGIN: f64 = 0.04;
+const JPEG_QUALITY: u8 = 90;
+
+/// One photo on the sheet.
+struct Cell {
+    name: String,
+    /// Thumbnail as a JPEG data URI, `None` if the file couldn't be decoded
+    thumbnail: Option<(String, u32, u32)>,
+    exif: ExifInfo,
+}
+
+impl Watermarker {
+    /// Writes a contact sheet of `images` to `output`: a PDF for a `.pdf`
+    /// path, otherwise JPEG pages numbered `-1`, `-2`, … when there is more
+    /// than one. Returns the files written.
+    pub fn contact_sheet(
+        &self,
+        images: &[SheetImage],
+        output: &Path,
+        options: &ContactSheetOptions,
+    ) -> Result<Vec<PathBuf>, String> {
+        let pages = self.render_contact_sheet(images, options)?;
+        if let Some(parent) = output.parent() {
+            fs::create_dir_all(parent).map_err(|e| format!("Failed to create output directory: {}", e))?;
+        }
+
+        let is_pdf = output
+            .extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| e.eq_ignore_ascii_case("pdf"));
+        let jpegs = pages.iter().map(encode_jpeg).collect::<Result<Vec<_>, _>>()?;
+        if is_pdf {
+            let pdf = write_pdf(&jpegs, options);
+            fs::write(output, pdf).map_err(|e| format!("Failed to write {}: {}", output.display(), e))?;
+            return Ok(vec![output.to_path_buf()]);
+        }
+
+        let mut written = Vec::new();
+        for (i, jpeg) in jpegs.iter().enumerate() {
+            let path = if jpegs.len() == 1 { output.to_path_buf() } else { numbered(output, i + 1) };
+            fs::write(&path, jpeg).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+            written.push(path);
+        }
+        Ok(written)
+    }
+
+    /// Renders the contact sheet pages.
+    pub(crate) fn render_contact_sheet(
+        &self,
+        images: &[SheetImage],
+        options: &ContactSheetOptions,
+    ) -> Result<Vec<RgbImage>, String> {
+        if images.is_empty() {
+            return Err("No images for the contact sheet".into());
+        }
+        let per_page = (options.columns.max(1) * options.rows.max(1)) as usize;
+        let grid = Grid::new(options);
+        let (thumb_w, thumb_h) = grid.thumbnail_size();
+
+        let cells: Vec<Cell> = images.iter().map(|image| self.cell(image, thumb_w, thumb_h, options)).collect();
+        let header = self.header(&cells);
+        let page_count = cells.len().div_ceil(per_page);
+        cells
+            .chunks(per_page)
+            .enumerate()
+            .map(|(i, page)| {
+                let svg = self.page_svg(&grid, &header, page, i + 1, page_count);
+                let (data, width, height) = watermark::render_svg_to_rgba(&svg, &self.fontdb)?;
+                let rgba = image::RgbaImage::from_raw(width, height, data).ok_or("Failed to render contact sheet")?;
+                Ok(DynamicImage::ImageRgba8(rgba).to_rgb8())
+            })
+            .collect()
+    }
+
+    fn cell(&self, image: &SheetImage, thumb_w: u32, thumb_h: u32, options: &ContactSheetOptions) -> Cell {
+        let path = &image.path;
+        let name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+        let mut exif = read_exif_file(path);
+        // The file's own orientation; a source file's tag doesn't apply to
+        // its already rotated output
+        let orientation = exif.orientation;
+        if exif.camera_make.is_empty() && exif.date_time.is_empty() {
+            let source = image
+                .source
+                .clone()
+                .or_else(|| options.source_dir.as_ref().map(|dir| dir.join(&name)))
+                .filter(|p| p.is_file());
+            if let Some(source) = source {
+                exif = read_exif_file(&source);
+            }
+        }
+        let budget = self.options.memory_budget.unwrap_or(DEFAULT_MEMORY_BUDGET);
+        let thumbnail = thumbnail(path, orientation, thumb_w, thumb_h, budget)
+            .map_err(|e| log::warn!("{}: {}", path.display(), e))
+            .ok();
+        Cell { name, thumbnail, exif }
+    }
+
+    /// Header lines: the photographer, then dates and design.
+    fn header(&self, cells: &[Cell]) -> (String, String) {
+        let mut dates: Vec<&str> = cells
+            .iter()
+            .filter_map(|c| c.exif.date_time.get(..10))
+            .collect();
+        dates.sort_unstable();
+        let design = designs::get_design_list()
+            .into_iter()
+            .find(|d| d.id == self.design_id)
+            .map(|d| d.name)
+            .unwrap_or_else(|| self.design_id.clone());
+
+        let mut details = Vec::new();
+        match (dates.first(), dates.last()) {
+            (Some(first), Some(last)) if first != last => details.push(format!("{} – {}", first, last)),
+            (Some(first), _) => details.push(first.to_string()),
+            _ => {}
+        }
+        details.push(format!("{} design", design));
+        details.push(match cells.len() {
+            1 => "1 photo".to_string(),
+            n => format!("{} photos", n),
+        });
+
+        let title = match self.options.photographer_name.trim() {
+            "" => "Contact sheet".to_string(),
+            name => name.to_string(),
+        };
+        (title, details.join("  ·  "))
+    }
+
+    fn page_svg(&self, grid: &Grid, (title, details): &(String, String), cells: &[Cell], page: usize, pages: usize) -> String {
+        let font = fonts::SANS_FAMILY;
+        let mut svg = format!(
+            r##"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}"><rect width="{w}" height="{h}" fill="#FFFFFF"/>"##,
+            w = grid.page_width,
+            h = grid.page_height,
+        );
+        svg += &format!(
+            r##"<text x="{}" y="{}" font-family="{}" font-size="{}" font-weight="700" fill="#222222">{}</text>"##,
+            grid.margin, grid.margin + grid.title_size, font, grid.title_size, designs::escape(title)
+        );
+        svg += &format!(
+            r##"<text x="{}" y="{}" font-family="{}" font-size="{}" fill="#666666">{}</text>"##,
+            grid.margin, grid.margin + grid.title_size * 1.3 + grid.caption_size * 1.4, font, grid.caption_size, designs::escape(details)
+        );
+        if pages > 1 {
+            svg += &format!(
+                r##"<text x="{}" y="{}" font-family="{}" font-size="{}" fill="#666666" text-anchor="end">Page {} of {}</text>"##,
+                grid.page_width - grid.margin, grid.margin + grid.title_size, font, grid.caption_size, page, pages
+            );
+        }
+        svg += &format!(
+            r##"<rect x="{}" y="{}" width="{}" height="1" fill="#CCCCCC"/>"##,
+            grid.margin,
+            grid.header_height - grid.gap / 2.0,
+            grid.page_width - 2.0 * grid.margin
+        );
+
+        for (i, cell) in cells.iter().enumerate() {
+            let (x, y) = grid.cell_origin(i as u32);
+            let (box_w, box_h) = (grid.cell_width, grid.cell_height - grid.caption_height);
+            match &cell.thumbnail {
+                Some((uri, w, h)) => {
+                    svg += &format!(
+                        r##"<image x="{}" y="{}" width="{}" height="{}" href="{}"/>"##,
+                        x + (box_w - *w as f64) / 2.0,
+                        // Bottom-aligned so the caption sits right under the photo
+                        y + box_h - *h as f64,
+                        w,
+                        h,
+                        uri
+                    );
+                }
+                None => {
+                    svg += &format!(
+                        r##"<rect x="{}" y="{}" width="{}" height="{}" fill="#EEEEEE"/><text x="{}" y="{}" font-family="{}" font-size="{}" fill="#999999" text-anchor="middle" dominant-baseline="central">Unreadable</text>"##,
+                        x, y, box_w, box_h, x + box_w / 2.0, y + box_h / 2.0, font, grid.caption_size
+                    );
+                }
+            }
+
+            let max_chars = (grid.cell_width / (grid.caption_size * 0.55)) as usize;
+            let caption_y = y + box_h + grid.caption_size * 1.3;
+            svg += &format!(
+                r##"<text x="{}" y="{}" font-family="{}" font-size="{}" font-weight="700" fill="#222222">{}</text>"##,
+                x, caption_y, font, grid.caption_size, designs::escape(&truncate(&cell.name, max_chars))
+            );
+            let exif_line = self.exif_caption(&cell.exif);
+            if !exif_line.is_empty() {
+                svg += &format!(
+                    r##"<text x="{}" y="{}" font-family="{}" font-size="{}" fill="#666666">{}</text>"##,
+                    x, caption_y + grid.caption_size * 1.3, font, grid.caption_size * 0.9,
+                    designs::escape(&truncate(&exif_line, (max_chars as f64 / 0.9) as usize))
+                );
+            }
+        }
+        svg + "</svg>"
+    }
+
+    /// Camera, then the exposure triangle, as the frames show them.
+    fn exif_caption(&self, exif: &ExifInfo) -> String {
+        let mut parts = Vec::new();
+        if !exif.camera_make.is_empty() {
+            let brand = self.brands.resolve(&exif.camera_make, &exif.camera_model);
+            parts.push(self.camera_names.display_name(
+                &exif.camera_make,
+                &exif.camera_model,
+                brand.as_ref(),
+                self.options.model_names,
+            ));
+        }
+        parts.extend([&exif.focal_length, &exif.f_number, &exif.exposure_time].into_iter().filter(|p| !p.is_empty()).cloned());
+        if !exif.iso.is_empty() {
+            parts.push(format!("ISO {}", exif.iso));
+        }
+        parts.join("  ")
+    }
+}
+
+/// Positions of the header and cells on a page.
+struct Grid {
+    columns: u32,
+    page_width: f64,
+    page_height: f64,
+    margin: f64,
+    gap: f64,
+    title_size: f64,
+    caption_size: f64,
+    header_height: f64,
+    caption_height: f64,
+    cell_width: f64,
+    cell_height: f64,
+}
+
+impl Grid {
+    fn new(options: &ContactSheetOptions) -> Self {
+        let (columns, rows) = (options.columns.max(1), options.rows.max(1));
+        let page_width = options.page_width as f64;
+        let page_height = options.page_height as f64;
+        let margin = (page_width * MARGIN).round();
+        let gap = (margin * 0.5).round();
+        let caption_size = f64::max(10.0, (page_width / 95.0).round());
+        let title_size = caption_size * 2.0;
+        let header_height = margin + title_size * 1.3 + caption_size * 2.2 + gap;
+        let caption_height = caption_size * 3.0;
+        let cell_width = (page_width - 2.0 * margin - (columns - 1) as f64 * gap) / columns as f64;
+        let cell_height = (page_height - header_height - margin - (rows - 1) as f64 * gap) / rows as f64;
+        Self {
+            columns,
+            page_width,
+            page_height,
+            margin,
+            gap,
+            title_size,
+            caption_size,
+            header_height,
+            caption_height,
+            cell_width,
+            cell_height,
+        }
+    }
+
+    /// Largest thumbnail that fits a cell above its caption.
+    fn thumbnail_size(&self) -> (u32, u32) {
+        (
+            self.cell_width.max(1.0) as u32,
+            (self.cell_height - self.caption_height).max(1.0) as u32,
+        )
+    }
+
+    fn cell_origin(&self, index: u32) -> (f64, f64) {
+        let (column, row) = (index % self.columns, index / self.columns);
+        (
+            self.margin + column as f64 * (self.cell_width + self.gap),
+            self.header_height + row as f64 * (self.cell_height + self.gap),
+        )
+    }
+}
+
+fn read_exif_file(path: &Path) -> ExifInfo {
+    match fs::File::open(path) {
+        Ok(file) => watermark::read_exif(&mut BufReader::new(file)),
+        Err(_) => watermark::parse_exif(&[]),
+    }
+}
+
+/// Decodes a photo and scales it to fit `max_w` × `max_h`, as a JPEG data
+/// URI with its size.
+fn thumbnail(path: &Path, orientation: u32, max_w: u32, max_h: u32, budget: u64) -> Result<(String, u32, u32), String> {
+    let file = fs::File::open(path).map_err(|e| format!("Failed to read image: {}", e))?;
+    let mut reader = BufReader::new(file);
+    let mut limits = Limits::no_limits();
+    limits.max_alloc = Some(budget);
+    let (decoder, _) = watermark::open_decoder(&mut reader, limits)?;
+    let image = DynamicImage::from_decoder(decoder).map_err(|e| format!("Failed to decode image: {}", e))?;
+    let thumb = watermark::auto_orient(image, orientation).thumbnail(max_w, max_h).to_rgb8();
+
+    let mut jpeg = Cursor::new(Vec::new());
+    thumb
+        .write_with_encoder(JpegEncoder::new_with_quality(&mut jpeg, 85))
+        .map_err(|e| format!("Failed to encode thumbnail: {}", e))?;
+    Ok((
+        format!("data:image/jpeg;base64,{}", BASE64.encode(jpeg.get_ref())),
+        thumb.width(),
+        thumb.height(),
+    ))
+}
+
+fn encode_jpeg(page: &RgbImage) -> Result<Vec<u8>, String> {
+    let mut out = Cursor::new(Vec::new());
+    page.write_with_encoder(JpegEncoder::new_with_quality(&mut out, JPEG_QUALITY))
+        .map_err(|e| format!("Failed to write JPEG: {}", e))?;
+    Ok(out.into_inner())
+}
+
+/// A PDF with one page per JPEG, each drawn over the whole page.
+fn write_pdf(pages: &[Vec<u8>], options: &ContactSheetOptions) -> Vec<u8> {
+    let points = |px: u32| px as f64 * 72.0 / options.dpi;
+    let (width, height) = (points(options.page_width), points(options.page_height));
+
+    let mut pdf = b"%PDF-1.4\n%\xE2\xE3\xCF\xD3\n".to_vec();
+    let mut offsets = Vec::new();
+    let mut object = |pdf: &mut Vec<u8>, body: &[u8]| {
+        offsets.push(pdf.len());
+        pdf.extend_from_slice(format!("{} 0 obj\n", offsets.len()).as_bytes());
+        pdf.extend_from_slice(body);
+        pdf.extend_from_slice(b"\nendobj\n");
+    };
+
+    // Objects 1 and 2 are the catalog and page tree; each page then takes
+    // three: the page, its image and its content stream
+    let kids: Vec<String> = (0..pages.len()).map(|i| format!("{} 0 R", 3 + i * 3)).collect();
+    object(&mut pdf, b"<< /Type /Catalog /Pages 2 0 R >>");
+    object(
+        &mut pdf,
+        format!("<< /Type /Pages /Kids [{}] /Count {} >>", kids.join(" "), pages.len()).as_bytes(),
+    );
+    for (i, jpeg) in pages.iter().enumerate() {
+        let page = 3 + i * 3;
+        object(
+            &mut pdf,
+            format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {:.2} {:.2}] /Resources << /XObject << /Im0 {} 0 R >> >> /Contents {} 0 R >>",
+                width, height, page + 1, page + 2
+            )
+            .as_bytes(),
+        );
+        let mut image = format!(
+            "<< /Type /XObject /Subtype /Image /Width {} /Height {} /ColorSpace /DeviceRGB /BitsPerComponent 8 /Filter /DCTDecode /Length {} >>\nstream\n",
+            options.page_width, options.page_height, jpeg.len()
+        )
+        .into_bytes();
+        image.extend_from_slice(jpeg);
+        image.extend_from_slice(b"\nendstream");
+        object(&mut pdf, &image);
+        let content = format!("q {:.2} 0 0 {:.2} 0 0 cm /Im0 Do Q", width, height);
+        object(
+            &mut pdf,
+            format!("<< /Length {} >>\nstream\n{}\nendstream", content.len(), content).as_bytes(),
+        );
+    }
+
+    let xref = pdf.len();
+    let _ = write!(pdf, "xref\n0 {}\n0000000000 65535 f \n", offsets.len() + 1);
+    for offset in &offsets {
+        let _ = writeln!(pdf, "{:010} 00000 n ", offset);
+    }
+    let _ = write!(pdf, "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n", offsets.len() + 1, xref);
+    pdf
+}
+
+/// `sheet.jpg` → `sheet-2.jpg`.
+fn numbered(path: &Path, page: usize) -> PathBuf {
+    let stem = path.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+    let name = match path.extension() {
+        Some(ext) => format!("{}-{}.{}", stem, page, ext.to_string_lossy()),
+        None => format!("{}-{}", stem, page),
+    };
+    path.with_file_name(name)
+}
+
+fn truncate(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+    let kept: String = text.chars().take(max_chars.saturating_sub(1)).collect();
+    format!("{}…", kept.trim_end())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+    use image::Rgb;
+
+    #[test]
+    fn pages_hold_a_grid_of_captioned_thumbnails() {
+        let dir = TempDir::new("sheet");
+        let images: Vec<PathBuf> = (0..5)
+            .map(|i| {
+                let path = dir.join(format!("IMG_{}.png", i));
+                let (w, h) = if i % 2 == 0 { (300, 200) } else { (200, 300) };
+                RgbImage::from_pixel(w, h, Rgb([200, 40 * i as u8, 60])).save(&path).unwrap();
+                path
+            })
+            .collect();
+        fs::write(dir.join("broken.jpg"), b"not an image").unwrap();
+        let mut inputs: Vec<SheetImage> = images.into_iter().map(SheetImage::from).collect();
+        inputs.push(dir.join("broken.jpg").into());
+
+        let wm = Watermarker::builder().system_fonts(false).photographer_name("Jane & Co").build();
+        let options = ContactSheetOptions { columns: 2, rows: 2, page_width: 600, page_height: 800, ..Default::default() };
+        let pages = wm.render_contact_sheet(&inputs, &options).unwrap();
+        assert_eq!(pages.len(), 2);
+        assert_eq!(pages[0].dimensions(), (600, 800));
+
+        // The first thumbnail sits in the top-left cell, below the header
+        let grid = Grid::new(&options);
+        let (x, y) = grid.cell_origin(0);
+        let (w, h) = grid.thumbnail_size();
+        let center = pages[0].get_pixel((x + w as f64 / 2.0) as u32, (y + h as f64 / 2.0) as u32);
+        assert!(center[0] > 180 && center[1] < 30, "{:?}", center);
+
+        let written = wm.contact_sheet(&inputs, &dir.join("sheet.jpg"), &options).unwrap();
+        assert_eq!(written, [dir.join("sheet-1.jpg"), dir.join("sheet-2.jpg")]);
+        let written = wm.contact_sheet(&inputs, &dir.join("sheet.pdf"), &options).unwrap();
+        let pdf = fs::read(&written[0]).unwrap();
+        assert!(pdf.starts_with(b"%PDF-1.4") && pdf.ends_with(b"%%EOF\n"));
+        assert_eq!(String::from_utf8_lossy(&pdf).matches("/Type /Page ").count(), 2);
+    }
+
+    #[test]
+    fn long_captions_are_shortened() {
+        assert_eq!(truncate("IMG_0001.jpg", 20), "IMG_0001.jpg");
+        assert_eq!(truncate("a very long file name.jpg", 8), "a very…");
+    }
+}
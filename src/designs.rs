@@ -1,4 +1,9 @@
 use serde::Serialize;
+use std::borrow::Cow;
+
+use crate::histogram::Histogram;
+use crate::palette;
+use crate::qr::QrCode;
 
 #[derive(Debug, Clone, Serialize)]
 pub struct DesignInfo {
@@ -23,6 +28,12 @@ pub fn get_design_list() -> Vec<DesignInfo> {
             description: "Elegant dark frame for your photos".into(),
             thumbnail_path: "assets/designs/dark-landscape.jpg".into(),
         },
+        DesignInfo {
+            id: "frosted".into(),
+            name: "Frosted".into(),
+            description: "Blurred, dimmed extension of the photo behind the info".into(),
+            thumbnail_path: "assets/designs/frosted-landscape.jpg".into(),
+        },
         DesignInfo {
             id: "minimal".into(),
             name: "Minimal".into(),
@@ -60,7 +71,20 @@ pub struct PortraitParams {
     pub date_time_string: String,
     pub font_size: f64,
     pub small_font_size: f64,
+    pub font_family: String,
     pub photographer_name: String,
+    /// Colours to draw with; `None` uses the design's own
+    pub palette: Option<FramePalette>,
+    /// The photo's palette as SVG colours, drawn as swatches; empty for none
+    pub swatches: Vec<String>,
+    /// Drawn next to the exposure info when present
+    pub histogram: Option<Histogram>,
+    /// Link to the photo or portfolio, drawn as a QR code when present
+    pub qr: Option<QrCode>,
+    /// The photo's XMP title and caption, for designs that place them
+    /// themselves; empty when it has none
+    pub title: String,
+    pub caption: String,
 }
 
 pub struct LandscapeParams {
@@ -83,13 +107,175 @@ pub struct LandscapeParams {
     pub adjusted_divider_bottom: f64,
     pub font_size: f64,
     pub small_font_size: f64,
+    pub font_family: String,
     pub photographer_name: String,
+    /// Colours to draw with; `None` uses the design's own
+    pub palette: Option<FramePalette>,
+    /// The photo's palette as SVG colours, drawn as swatches; empty for none
+    pub swatches: Vec<String>,
+    /// Drawn next to the exposure info when present
+    pub histogram: Option<Histogram>,
+    /// Link to the photo or portfolio, drawn as a QR code when present
+    pub qr: Option<QrCode>,
+    /// The photo's XMP title and caption, for designs that place them
+    /// themselves; empty when it has none
+    pub title: String,
+    pub caption: String,
+}
+
+/// Colours a design draws with, as SVG colour values.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FramePalette {
+    pub background: String,
+    /// End of the background gradient (same as `background` for flat designs)
+    pub background_end: String,
+    /// Camera model and brand name
+    pub text: String,
+    /// Exposure info and photographer name
+    pub secondary_text: String,
+    /// Date and other least prominent text
+    pub muted_text: String,
+    /// Divider lines
+    pub rule: String,
+}
+
+/// Returns the built-in colours of the given design.
+pub fn get_palette(design_id: &str) -> FramePalette {
+    let palette = |colors: [&str; 6]| FramePalette {
+        background: colors[0].into(),
+        background_end: colors[1].into(),
+        text: colors[2].into(),
+        secondary_text: colors[3].into(),
+        muted_text: colors[4].into(),
+        rule: colors[5].into(),
+    };
+    match design_id {
+        "dark" => palette(["#222222", "#1a1a1a", "#FFFFFF", "#AAAAAA", "#888888", "#444444"]),
+        // No background of its own; drawn over the blurred photo
+        "frosted" => palette(["#303030", "#303030", "#FFFFFF", "#E6E6E6", "#CCCCCC", "#FFFFFF"]),
+        "minimal" => palette(["#FFFFFF", "#FFFFFF", "#333333", "#999999", "#999999", "#EEEEEE"]),
+        "vintage" => palette(["#F8F5E9", "#F8F5E9", "#70573B", "#91785E", "#91785E", "#91785E"]),
+        "simple" => palette(["#FFFFFF", "#FFFFFF", "#333333", "#777777", "#999999", "#EEEEEE"]),
+        "micro" => palette(["#FFFFFF", "#FFFFFF", "#333333", "#777777", "#777777", "#EEEEEE"]),
+        _ => palette(["#FFFFFF", "#F8F8F8", "#111111", "#333333", "#666666", "#CCCCCC"]),
+    }
+}
+
+fn palette_or<'a>(palette: &'a Option<FramePalette>, design_id: &str) -> Cow<'a, FramePalette> {
+    match palette {
+        Some(palette) => Cow::Borrowed(palette),
+        None => Cow::Owned(get_palette(design_id)),
+    }
+}
+
+/// Escapes text for an SVG `<text>` element.
+pub(crate) fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+/// Row of colour swatches with their hex codes underneath. `x` is the middle
+/// of the row, or its right edge with `anchor` "end"; `y` is the middle of the
+/// squares.
+fn swatch_row(colors: &[String], x: f64, y: f64, size: f64, anchor: &str, label_fill: &str, font: &str) -> String {
+    if colors.is_empty() {
+        return String::new();
+    }
+    // Width of a hex code is about 1.26 sizes, so labels don't touch
+    let step = size * 1.45;
+    let width = step * (colors.len() - 1) as f64 + size;
+    let left = if anchor == "end" { x - width } else { x - width / 2.0 };
+
+    colors
+        .iter()
+        .enumerate()
+        .map(|(i, color)| {
+            let sx = left + step * i as f64;
+            format!(
+                r##"<rect x="{sx:.1}" y="{sy:.1}" width="{size:.1}" height="{size:.1}" fill="{color}" stroke="{label_fill}" stroke-opacity="0.25" stroke-width="1"/><text x="{lx:.1}" y="{ly:.1}" font-family="{font}" font-size="{fs:.1}" font-weight="400" fill="{label_fill}" text-anchor="middle" dominant-baseline="middle">{color}</text>"##,
+                sy = y - size / 2.0,
+                lx = sx + size / 2.0,
+                ly = y + size * 0.85,
+                fs = size * 0.3,
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("")
+}
+
+/// Histogram in the box at (`x`, `y`), or nothing when the photo has none.
+fn histogram_el(histogram: &Option<Histogram>, x: f64, y: f64, width: f64, height: f64, color: &str) -> String {
+    histogram
+        .as_ref()
+        .map(|h| h.svg_element(x, y, width, height, color))
+        .unwrap_or_default()
+}
+
+/// QR code as a square of `size` at (`x`, `y`), or nothing when there is no
+/// link. Always dark modules on a light plate, as scanners expect, using
+/// whichever of the text and background colours is darker.
+fn qr_el(qr: &Option<QrCode>, x: f64, y: f64, size: f64, c: &FramePalette) -> String {
+    let Some(qr) = qr else {
+        return String::new();
+    };
+    let luminance = |color: &str| palette::parse_hex(color).map(relative_luminance);
+    let (dark, light) = match (luminance(&c.text), luminance(&c.background)) {
+        (Some(text), Some(background)) if text > background => (c.background.as_str(), c.text.as_str()),
+        (Some(_), Some(_)) => (c.text.as_str(), c.background.as_str()),
+        _ => ("#000000", "#FFFFFF"),
+    };
+    qr.svg_element(x, y, size, dark, light)
+}
+
+/// Largest swatch size, up to `max`, for which the row fits in `space`.
+fn swatch_size(colors: &[String], space: f64, max: f64) -> f64 {
+    (space / (colors.len() as f64 * 1.45)).clamp(0.0, max)
+}
+
+/// Histogram, QR code and swatches of the classic, dark and frosted portrait
+/// frames.
+fn portrait_extras(p: &PortraitParams, c: &FramePalette) -> String {
+    let histogram = histogram_el(&p.histogram, p.image_width as f64 - p.frame_height * 1.1, p.frame_height * 1.35, p.frame_height * 0.8, p.frame_height * 0.5, &c.secondary_text);
+    let qr = qr_el(&p.qr, p.frame_height * 0.3, p.frame_height * 1.2, p.frame_height * 0.7, c);
+    let swatches = swatch_row(&p.swatches, p.center_x, p.frame_height * 1.12, p.frame_height * 0.14, "middle", &c.muted_text, &p.font_family);
+    format!("{}\n  {}\n  {}", histogram, qr, swatches)
+}
+
+/// Histogram, QR code and swatches of the classic, dark and frosted landscape
+/// frames, fitted between the camera name and the exposure.
+fn landscape_extras(p: &LandscapeParams, c: &FramePalette) -> String {
+    let gap_left = p.left_text_x + p.camera_info.len() as f64 * p.font_size * 0.72;
+    let info_w = p.exposure_info.len().max(p.date_time_string.len()) as f64 * p.small_font_size * 0.55;
+    let hist_h = p.adjusted_divider_bottom - p.adjusted_divider_top;
+    let qr_x = p.right_text_x - info_w - p.frame_height * 0.2 - hist_h;
+    let qr = qr_el(&p.qr, qr_x, p.adjusted_divider_top, hist_h, c);
+    let info_left = if p.qr.is_some() { qr_x } else { p.right_text_x - info_w };
+    let hist_x = info_left - p.frame_height * 0.2 - hist_h * 1.6;
+    // Left out rather than drawn over a long camera name
+    let show_histogram = p.histogram.is_some() && hist_x > gap_left;
+    let histogram = if show_histogram {
+        histogram_el(&p.histogram, hist_x, p.adjusted_divider_top, hist_h * 1.6, hist_h, &c.secondary_text)
+    } else {
+        String::new()
+    };
+    let gap_right = if show_histogram { hist_x } else { info_left };
+    let size = swatch_size(&p.swatches, gap_right - gap_left - p.frame_height * 0.4, p.frame_height * 0.22);
+    let swatches = swatch_row(
+        &p.swatches,
+        (gap_left + gap_right) / 2.0,
+        p.center_y - p.frame_height * 0.06,
+        size,
+        "middle",
+        &c.muted_text,
+        &p.font_family,
+    );
+    format!("{}\n  {}\n  {}", histogram, qr, swatches)
 }
 
 pub fn render_portrait(design_id: &str, p: &PortraitParams) -> String {
     match design_id {
         "classic" => render_classic_portrait(p),
         "dark" => render_dark_portrait(p),
+        "frosted" => render_frosted_portrait(p),
         "minimal" => render_minimal_portrait(p),
         "vintage" => render_vintage_portrait(p),
         "simple" => render_simple_portrait(p),
@@ -102,6 +288,7 @@ pub fn render_landscape(design_id: &str, p: &LandscapeParams) -> String {
     match design_id {
         "classic" => render_classic_landscape(p),
         "dark" => render_dark_landscape(p),
+        "frosted" => render_frosted_landscape(p),
         "minimal" => render_minimal_landscape(p),
         "vintage" => render_vintage_landscape(p),
         "simple" => render_simple_landscape(p),
@@ -113,6 +300,7 @@ pub fn render_landscape(design_id: &str, p: &LandscapeParams) -> String {
 // ─── Classic ──────────────────────────────────────────
 
 fn render_classic_portrait(p: &PortraitParams) -> String {
+    let c = palette_or(&p.palette, "classic");
     let fh = p.frame_height;
     let total_h = (fh * 2.0) as u32;
     let camera_y = fh * 0.4;
@@ -125,20 +313,27 @@ fn render_classic_portrait(p: &PortraitParams) -> String {
         p.date_time_string.clone()
     };
 
+    let extras = portrait_extras(p, &c);
+
     format!(
         r##"<svg width="{w}" height="{h}" xmlns="http://www.w3.org/2000/svg">
   <defs>
     <linearGradient id="grad" x1="0%" y1="0%" x2="100%" y2="0%">
-      <stop offset="0%" style="stop-color:#FFFFFF;stop-opacity:1.0" />
-      <stop offset="100%" style="stop-color:#F8F8F8;stop-opacity:1.0" />
+      <stop offset="0%" style="stop-color:{bg};stop-opacity:1.0" />
+      <stop offset="100%" style="stop-color:{bg_end};stop-opacity:1.0" />
     </linearGradient>
   </defs>
   <rect width="{w}" height="{h}" fill="url(#grad)"/>
-  <text x="{cx}" y="{cy}" font-family="Arial, sans-serif" font-size="{fs}" font-weight="700" fill="#111111" text-anchor="middle" dominant-baseline="middle">{camera}</text>
+  <text x="{cx}" y="{cy}" font-family="{font}" font-size="{fs}" font-weight="700" fill="{text}" text-anchor="middle" dominant-baseline="middle">{camera}</text>
   {logo}
-  <text x="{cx}" y="{ey}" font-family="Arial, sans-serif" font-size="{sfs}" font-weight="700" fill="#666666" text-anchor="middle" dominant-baseline="middle">{exposure}</text>
-  <text x="{cx}" y="{dy}" font-family="Arial, sans-serif" font-size="{sfs}" font-weight="700" fill="#666666" text-anchor="middle" dominant-baseline="middle">{date}</text>
+  <text x="{cx}" y="{ey}" font-family="{font}" font-size="{sfs}" font-weight="700" fill="{muted}" text-anchor="middle" dominant-baseline="middle">{exposure}</text>
+  <text x="{cx}" y="{dy}" font-family="{font}" font-size="{sfs}" font-weight="700" fill="{muted}" text-anchor="middle" dominant-baseline="middle">{date}</text>
+  {extras}
 </svg>"##,
+        bg = c.background,
+        bg_end = c.background_end,
+        text = c.text,
+        muted = c.muted_text,
         w = p.image_width,
         h = total_h,
         cx = p.center_x,
@@ -151,17 +346,22 @@ fn render_classic_portrait(p: &PortraitParams) -> String {
         exposure = p.exposure_info,
         dy = date_y,
         date = date_display,
+        extras = extras,
+        font = p.font_family,
     )
 }
 
 fn render_classic_landscape(p: &LandscapeParams) -> String {
+    let c = palette_or(&p.palette, "classic");
     let photographer_el = if !p.photographer_name.is_empty() {
         format!(
-            r##"<text x="{x}" y="{y}" font-family="Arial, sans-serif" font-size="{fs}" font-weight="500" fill="#333333" dominant-baseline="central">Taken by {name}</text>"##,
+            r##"<text x="{x}" y="{y}" font-family="{font}" font-size="{fs}" font-weight="500" fill="{secondary}" dominant-baseline="central">Taken by {name}</text>"##,
+            secondary = c.secondary_text,
             x = p.left_text_x,
             y = p.center_y + p.text_adjustment + p.font_size * 0.9,
             fs = p.small_font_size,
             name = p.photographer_name,
+            font = p.font_family,
         )
     } else {
         String::new()
@@ -169,8 +369,10 @@ fn render_classic_landscape(p: &LandscapeParams) -> String {
 
     let exposure_el = if !p.exposure_info.is_empty() {
         format!(
-            r##"<text x="{x}" y="{y}" font-family="Arial, sans-serif" font-size="{fs}" font-weight="700" fill="#333333" text-anchor="end" dominant-baseline="central">{info}</text>"##,
+            r##"<text x="{x}" y="{y}" font-family="{font}" font-size="{fs}" font-weight="700" fill="{secondary}" text-anchor="end" dominant-baseline="central">{info}</text>"##,
+            secondary = c.secondary_text,
             x = p.right_text_x, y = p.exposure_y, fs = p.small_font_size, info = p.exposure_info,
+            font = p.font_family,
         )
     } else {
         String::new()
@@ -178,29 +380,38 @@ fn render_classic_landscape(p: &LandscapeParams) -> String {
 
     let date_el = if !p.date_time_string.is_empty() {
         format!(
-            r##"<text x="{x}" y="{y}" font-family="Arial, sans-serif" font-size="{fs}" font-weight="700" fill="#666666" text-anchor="end" dominant-baseline="central">{date}</text>"##,
+            r##"<text x="{x}" y="{y}" font-family="{font}" font-size="{fs}" font-weight="700" fill="{muted}" text-anchor="end" dominant-baseline="central">{date}</text>"##,
+            muted = c.muted_text,
             x = p.right_text_x, y = p.date_y, fs = p.small_font_size, date = p.date_time_string,
+            font = p.font_family,
         )
     } else {
         String::new()
     };
 
+    let extras = landscape_extras(p, &c);
+
     format!(
         r##"<svg width="{w}" height="{fh}" xmlns="http://www.w3.org/2000/svg">
   <defs>
     <linearGradient id="grad" x1="0%" y1="0%" x2="100%" y2="0%">
-      <stop offset="0%" style="stop-color:#FFFFFF;stop-opacity:1.0" />
-      <stop offset="100%" style="stop-color:#F8F8F8;stop-opacity:1.0" />
+      <stop offset="0%" style="stop-color:{bg};stop-opacity:1.0" />
+      <stop offset="100%" style="stop-color:{bg_end};stop-opacity:1.0" />
     </linearGradient>
   </defs>
   <rect width="{w}" height="{fh}" fill="url(#grad)"/>
-  <text x="{ltx}" y="{cty}" font-family="Arial, sans-serif" font-size="{fs}" font-weight="700" fill="#111111" dominant-baseline="central" letter-spacing="0.5">{camera}</text>
+  <text x="{ltx}" y="{cty}" font-family="{font}" font-size="{fs}" font-weight="700" fill="{text}" dominant-baseline="central" letter-spacing="0.5">{camera}</text>
   {photographer}
   {logo}
-  <line x1="{dx}" y1="{dt}" x2="{dx}" y2="{db}" stroke="#CCCCCC" stroke-width="2"/>
+  <line x1="{dx}" y1="{dt}" x2="{dx}" y2="{db}" stroke="{rule}" stroke-width="2"/>
   {exposure_el}
   {date_el}
+  {extras}
 </svg>"##,
+        bg = c.background,
+        bg_end = c.background_end,
+        text = c.text,
+        rule = c.rule,
         w = p.image_width,
         fh = p.frame_height as u32,
         ltx = p.left_text_x,
@@ -214,12 +425,15 @@ fn render_classic_landscape(p: &LandscapeParams) -> String {
         db = p.adjusted_divider_bottom,
         exposure_el = exposure_el,
         date_el = date_el,
+        extras = extras,
+        font = p.font_family,
     )
 }
 
 // ─── Dark ─────────────────────────────────────────────
 
 fn render_dark_portrait(p: &PortraitParams) -> String {
+    let c = palette_or(&p.palette, "dark");
     let fh = p.frame_height;
     let total_h = (fh * 2.0) as u32;
     let camera_y = fh * 0.4;
@@ -232,20 +446,28 @@ fn render_dark_portrait(p: &PortraitParams) -> String {
         p.date_time_string.clone()
     };
 
+    let extras = portrait_extras(p, &c);
+
     format!(
         r##"<svg width="{w}" height="{h}" xmlns="http://www.w3.org/2000/svg">
   <defs>
     <linearGradient id="grad" x1="0%" y1="0%" x2="100%" y2="0%">
-      <stop offset="0%" style="stop-color:#222222;stop-opacity:1.0" />
-      <stop offset="100%" style="stop-color:#1a1a1a;stop-opacity:1.0" />
+      <stop offset="0%" style="stop-color:{bg};stop-opacity:1.0" />
+      <stop offset="100%" style="stop-color:{bg_end};stop-opacity:1.0" />
     </linearGradient>
   </defs>
   <rect width="{w}" height="{h}" fill="url(#grad)"/>
-  <text x="{cx}" y="{cy}" font-family="Arial, sans-serif" font-size="{fs}" font-weight="700" fill="#FFFFFF" text-anchor="middle" dominant-baseline="middle">{camera}</text>
+  <text x="{cx}" y="{cy}" font-family="{font}" font-size="{fs}" font-weight="700" fill="{text}" text-anchor="middle" dominant-baseline="middle">{camera}</text>
   {logo}
-  <text x="{cx}" y="{ey}" font-family="Arial, sans-serif" font-size="{sfs}" font-weight="700" fill="#AAAAAA" text-anchor="middle" dominant-baseline="middle">{exposure}</text>
-  <text x="{cx}" y="{dy}" font-family="Arial, sans-serif" font-size="{sfs}" font-weight="700" fill="#888888" text-anchor="middle" dominant-baseline="middle">{date}</text>
+  <text x="{cx}" y="{ey}" font-family="{font}" font-size="{sfs}" font-weight="700" fill="{secondary}" text-anchor="middle" dominant-baseline="middle">{exposure}</text>
+  <text x="{cx}" y="{dy}" font-family="{font}" font-size="{sfs}" font-weight="700" fill="{muted}" text-anchor="middle" dominant-baseline="middle">{date}</text>
+  {extras}
 </svg>"##,
+        bg = c.background,
+        bg_end = c.background_end,
+        text = c.text,
+        secondary = c.secondary_text,
+        muted = c.muted_text,
         w = p.image_width,
         h = total_h,
         cx = p.center_x,
@@ -258,17 +480,22 @@ fn render_dark_portrait(p: &PortraitParams) -> String {
         exposure = p.exposure_info,
         dy = date_y,
         date = date_display,
+        extras = extras,
+        font = p.font_family,
     )
 }
 
 fn render_dark_landscape(p: &LandscapeParams) -> String {
+    let c = palette_or(&p.palette, "dark");
     let photographer_el = if !p.photographer_name.is_empty() {
         format!(
-            r##"<text x="{x}" y="{y}" font-family="Arial, sans-serif" font-size="{fs}" font-weight="500" fill="#AAAAAA" dominant-baseline="central">Taken by {name}</text>"##,
+            r##"<text x="{x}" y="{y}" font-family="{font}" font-size="{fs}" font-weight="500" fill="{secondary}" dominant-baseline="central">Taken by {name}</text>"##,
+            secondary = c.secondary_text,
             x = p.left_text_x,
             y = p.center_y + p.text_adjustment + p.font_size * 0.9,
             fs = p.small_font_size,
             name = p.photographer_name,
+            font = p.font_family,
         )
     } else {
         String::new()
@@ -276,8 +503,10 @@ fn render_dark_landscape(p: &LandscapeParams) -> String {
 
     let exposure_el = if !p.exposure_info.is_empty() {
         format!(
-            r##"<text x="{x}" y="{y}" font-family="Arial, sans-serif" font-size="{fs}" font-weight="700" fill="#AAAAAA" text-anchor="end" dominant-baseline="central">{info}</text>"##,
+            r##"<text x="{x}" y="{y}" font-family="{font}" font-size="{fs}" font-weight="700" fill="{secondary}" text-anchor="end" dominant-baseline="central">{info}</text>"##,
+            secondary = c.secondary_text,
             x = p.right_text_x, y = p.exposure_y, fs = p.small_font_size, info = p.exposure_info,
+            font = p.font_family,
         )
     } else {
         String::new()
@@ -285,29 +514,157 @@ fn render_dark_landscape(p: &LandscapeParams) -> String {
 
     let date_el = if !p.date_time_string.is_empty() {
         format!(
-            r##"<text x="{x}" y="{y}" font-family="Arial, sans-serif" font-size="{fs}" font-weight="700" fill="#888888" text-anchor="end" dominant-baseline="central">{date}</text>"##,
+            r##"<text x="{x}" y="{y}" font-family="{font}" font-size="{fs}" font-weight="700" fill="{muted}" text-anchor="end" dominant-baseline="central">{date}</text>"##,
+            muted = c.muted_text,
             x = p.right_text_x, y = p.date_y, fs = p.small_font_size, date = p.date_time_string,
+            font = p.font_family,
         )
     } else {
         String::new()
     };
 
+    let extras = landscape_extras(p, &c);
+
     format!(
         r##"<svg width="{w}" height="{fh}" xmlns="http://www.w3.org/2000/svg">
   <defs>
     <linearGradient id="grad" x1="0%" y1="0%" x2="100%" y2="0%">
-      <stop offset="0%" style="stop-color:#222222;stop-opacity:1.0" />
-      <stop offset="100%" style="stop-color:#1a1a1a;stop-opacity:1.0" />
+      <stop offset="0%" style="stop-color:{bg};stop-opacity:1.0" />
+      <stop offset="100%" style="stop-color:{bg_end};stop-opacity:1.0" />
     </linearGradient>
   </defs>
   <rect width="{w}" height="{fh}" fill="url(#grad)"/>
-  <text x="{ltx}" y="{cty}" font-family="Arial, sans-serif" font-size="{fs}" font-weight="700" fill="#FFFFFF" dominant-baseline="central" letter-spacing="0.5">{camera}</text>
+  <text x="{ltx}" y="{cty}" font-family="{font}" font-size="{fs}" font-weight="700" fill="{text}" dominant-baseline="central" letter-spacing="0.5">{camera}</text>
+  {photographer}
+  {logo}
+  <line x1="{dx}" y1="{dt}" x2="{dx}" y2="{db}" stroke="{rule}" stroke-width="2"/>
+  {exposure_el}
+  {date_el}
+  {extras}
+</svg>"##,
+        bg = c.background,
+        bg_end = c.background_end,
+        text = c.text,
+        rule = c.rule,
+        w = p.image_width,
+        fh = p.frame_height as u32,
+        ltx = p.left_text_x,
+        cty = p.center_y + p.text_adjustment,
+        fs = p.font_size,
+        camera = p.camera_info,
+        photographer = photographer_el,
+        logo = p.logo_element,
+        dx = p.divider_x,
+        dt = p.adjusted_divider_top,
+        db = p.adjusted_divider_bottom,
+        exposure_el = exposure_el,
+        date_el = date_el,
+        extras = extras,
+        font = p.font_family,
+    )
+}
+
+// ─── Frosted ──────────────────────────────────────────
+
+fn render_frosted_portrait(p: &PortraitParams) -> String {
+    let c = palette_or(&p.palette, "frosted");
+    let fh = p.frame_height;
+    let total_h = (fh * 2.0) as u32;
+    let camera_y = fh * 0.4;
+    let exposure_y = fh * 1.4;
+    let date_y = fh * 1.8;
+
+    let date_display = if !p.photographer_name.is_empty() {
+        format!("{} | Taken by {}", p.date_time_string, p.photographer_name)
+    } else {
+        p.date_time_string.clone()
+    };
+
+    let extras = portrait_extras(p, &c);
+
+    format!(
+        r##"<svg width="{w}" height="{h}" xmlns="http://www.w3.org/2000/svg">
+  <line x1="0" y1="1" x2="{w}" y2="1" stroke="{rule}" stroke-opacity="0.3" stroke-width="2"/>
+  <text x="{cx}" y="{cy}" font-family="{font}" font-size="{fs}" font-weight="700" fill="{text}" text-anchor="middle" dominant-baseline="middle">{camera}</text>
+  {logo}
+  <text x="{cx}" y="{ey}" font-family="{font}" font-size="{sfs}" font-weight="700" fill="{secondary}" text-anchor="middle" dominant-baseline="middle">{exposure}</text>
+  <text x="{cx}" y="{dy}" font-family="{font}" font-size="{sfs}" font-weight="700" fill="{muted}" text-anchor="middle" dominant-baseline="middle">{date}</text>
+  {extras}
+</svg>"##,
+        text = c.text,
+        secondary = c.secondary_text,
+        muted = c.muted_text,
+        rule = c.rule,
+        w = p.image_width,
+        h = total_h,
+        cx = p.center_x,
+        cy = camera_y,
+        fs = p.font_size,
+        camera = p.camera_info,
+        logo = p.logo_element,
+        ey = exposure_y,
+        sfs = p.small_font_size,
+        exposure = p.exposure_info,
+        dy = date_y,
+        date = date_display,
+        extras = extras,
+        font = p.font_family,
+    )
+}
+
+fn render_frosted_landscape(p: &LandscapeParams) -> String {
+    let c = palette_or(&p.palette, "frosted");
+    let photographer_el = if !p.photographer_name.is_empty() {
+        format!(
+            r##"<text x="{x}" y="{y}" font-family="{font}" font-size="{fs}" font-weight="500" fill="{secondary}" dominant-baseline="central">Taken by {name}</text>"##,
+            secondary = c.secondary_text,
+            x = p.left_text_x,
+            y = p.center_y + p.text_adjustment + p.font_size * 0.9,
+            fs = p.small_font_size,
+            name = p.photographer_name,
+            font = p.font_family,
+        )
+    } else {
+        String::new()
+    };
+
+    let exposure_el = if !p.exposure_info.is_empty() {
+        format!(
+            r##"<text x="{x}" y="{y}" font-family="{font}" font-size="{fs}" font-weight="700" fill="{secondary}" text-anchor="end" dominant-baseline="central">{info}</text>"##,
+            secondary = c.secondary_text,
+            x = p.right_text_x, y = p.exposure_y, fs = p.small_font_size, info = p.exposure_info,
+            font = p.font_family,
+        )
+    } else {
+        String::new()
+    };
+
+    let date_el = if !p.date_time_string.is_empty() {
+        format!(
+            r##"<text x="{x}" y="{y}" font-family="{font}" font-size="{fs}" font-weight="700" fill="{muted}" text-anchor="end" dominant-baseline="central">{date}</text>"##,
+            muted = c.muted_text,
+            x = p.right_text_x, y = p.date_y, fs = p.small_font_size, date = p.date_time_string,
+            font = p.font_family,
+        )
+    } else {
+        String::new()
+    };
+
+    let extras = landscape_extras(p, &c);
+
+    format!(
+        r##"<svg width="{w}" height="{fh}" xmlns="http://www.w3.org/2000/svg">
+  <line x1="0" y1="1" x2="{w}" y2="1" stroke="{rule}" stroke-opacity="0.3" stroke-width="2"/>
+  <text x="{ltx}" y="{cty}" font-family="{font}" font-size="{fs}" font-weight="700" fill="{text}" dominant-baseline="central" letter-spacing="0.5">{camera}</text>
   {photographer}
   {logo}
-  <line x1="{dx}" y1="{dt}" x2="{dx}" y2="{db}" stroke="#444444" stroke-width="2"/>
+  <line x1="{dx}" y1="{dt}" x2="{dx}" y2="{db}" stroke="{rule}" stroke-opacity="0.4" stroke-width="2"/>
   {exposure_el}
   {date_el}
+  {extras}
 </svg>"##,
+        text = c.text,
+        rule = c.rule,
         w = p.image_width,
         fh = p.frame_height as u32,
         ltx = p.left_text_x,
@@ -321,35 +678,51 @@ fn render_dark_landscape(p: &LandscapeParams) -> String {
         db = p.adjusted_divider_bottom,
         exposure_el = exposure_el,
         date_el = date_el,
+        extras = extras,
+        font = p.font_family,
     )
 }
 
 // ─── Minimal ──────────────────────────────────────────
 
 fn render_minimal_portrait(p: &PortraitParams) -> String {
+    let c = palette_or(&p.palette, "minimal");
     let fh = p.frame_height;
     let total_h = (fh * 2.0) as u32;
 
     let photographer_el = if !p.photographer_name.is_empty() {
         format!(
-            r##"<text x="{cx}" y="{y}" font-family="Arial, sans-serif" font-size="{fs}" font-weight="300" fill="#999999" text-anchor="middle" dominant-baseline="middle">Taken by {name}</text>"##,
+            r##"<text x="{cx}" y="{y}" font-family="{font}" font-size="{fs}" font-weight="300" fill="{secondary}" text-anchor="middle" dominant-baseline="middle">Taken by {name}</text>"##,
+            secondary = c.secondary_text,
             cx = p.center_x,
             y = fh * 1.8,
             fs = p.small_font_size * 0.9,
             name = p.photographer_name,
+            font = p.font_family,
         )
     } else {
         String::new()
     };
 
+    let histogram = histogram_el(&p.histogram, p.image_width as f64 - fh * 0.94, fh * 0.8, fh * 0.64, fh * 0.4, &c.secondary_text);
+    let qr = qr_el(&p.qr, fh * 0.3, fh * 0.72, fh * 0.6, &c);
+    let swatches = swatch_row(&p.swatches, p.center_x, fh, fh * 0.14, "middle", &c.muted_text, &p.font_family);
+
     format!(
         r##"<svg width="{w}" height="{h}" xmlns="http://www.w3.org/2000/svg">
-  <rect width="{w}" height="{h}" fill="#FFFFFF"/>
-  <line x1="0" y1="0" x2="{w}" y2="0" stroke="#EEEEEE" stroke-width="2"/>
-  <text x="{cx}" y="{cy}" font-family="Arial, sans-serif" font-size="{fs}" font-weight="300" fill="#333333" text-anchor="middle" dominant-baseline="middle">{camera}</text>
-  <text x="{cx}" y="{iy}" font-family="Arial, sans-serif" font-size="{sfs}" font-weight="300" fill="#999999" text-anchor="middle" dominant-baseline="middle">{exposure} · {date}</text>
+  <rect width="{w}" height="{h}" fill="{bg}"/>
+  <line x1="0" y1="0" x2="{w}" y2="0" stroke="{rule}" stroke-width="2"/>
+  <text x="{cx}" y="{cy}" font-family="{font}" font-size="{fs}" font-weight="300" fill="{text}" text-anchor="middle" dominant-baseline="middle">{camera}</text>
+  <text x="{cx}" y="{iy}" font-family="{font}" font-size="{sfs}" font-weight="300" fill="{secondary}" text-anchor="middle" dominant-baseline="middle">{exposure} · {date}</text>
   {photographer}
+  {histogram}
+  {qr}
+  {swatches}
 </svg>"##,
+        bg = c.background,
+        text = c.text,
+        secondary = c.secondary_text,
+        rule = c.rule,
         w = p.image_width,
         h = total_h,
         cx = p.center_x,
@@ -361,30 +734,52 @@ fn render_minimal_portrait(p: &PortraitParams) -> String {
         exposure = p.exposure_info,
         date = p.date_time_string,
         photographer = photographer_el,
+        histogram = histogram,
+        qr = qr,
+        swatches = swatches,
+        font = p.font_family,
     )
 }
 
 fn render_minimal_landscape(p: &LandscapeParams) -> String {
+    let c = palette_or(&p.palette, "minimal");
+    // QR code at the right edge, pushing the right-aligned elements inwards
+    let qr_size = p.frame_height * 0.6;
+    let qr_x = p.image_width as f64 - p.frame_height * 0.05 - qr_size;
+    let qr = qr_el(&p.qr, qr_x, p.center_y - qr_size / 2.0, qr_size, &c);
+    let right_x = if p.qr.is_some() { qr_x - p.frame_height * 0.2 } else { p.image_width as f64 - 20.0 };
     let photographer_el = if !p.photographer_name.is_empty() {
         format!(
-            r##"<text x="{x}" y="{y}" font-family="Arial, sans-serif" font-size="{fs}" font-weight="300" fill="#999999" text-anchor="end" dominant-baseline="central">Taken by {name}</text>"##,
-            x = p.image_width as f64 - 20.0,
+            r##"<text x="{x}" y="{y}" font-family="{font}" font-size="{fs}" font-weight="300" fill="{secondary}" text-anchor="end" dominant-baseline="central">Taken by {name}</text>"##,
+            secondary = c.secondary_text,
+            x = right_x,
             y = p.center_y + 80.0,
             fs = p.small_font_size,
             name = p.photographer_name,
+            font = p.font_family,
         )
     } else {
         String::new()
     };
 
+    let histogram = histogram_el(&p.histogram, p.left_text_x + (p.exposure_info.len() + p.date_time_string.len() + 3) as f64 * p.small_font_size * 0.55 + p.frame_height * 0.2, p.center_y + 80.0 - p.frame_height * 0.15, p.frame_height * 0.48, p.frame_height * 0.3, &c.secondary_text);
+    let swatches = swatch_row(&p.swatches, right_x, p.center_y - p.frame_height * 0.06, p.frame_height * 0.22, "end", &c.muted_text, &p.font_family);
+
     format!(
         r##"<svg width="{w}" height="{fh}" xmlns="http://www.w3.org/2000/svg">
-  <rect width="{w}" height="{fh}" fill="#FFFFFF"/>
-  <line x1="0" y1="0" x2="{w}" y2="0" stroke="#EEEEEE" stroke-width="2"/>
-  <text x="{ltx}" y="{cy}" font-family="Arial, sans-serif" font-size="{cfs}" font-weight="300" fill="#333333" dominant-baseline="central">{camera}</text>
+  <rect width="{w}" height="{fh}" fill="{bg}"/>
+  <line x1="0" y1="0" x2="{w}" y2="0" stroke="{rule}" stroke-width="2"/>
+  <text x="{ltx}" y="{cy}" font-family="{font}" font-size="{cfs}" font-weight="300" fill="{text}" dominant-baseline="central">{camera}</text>
   {photographer}
-  <text x="{ltx}" y="{ey}" font-family="Arial, sans-serif" font-size="{sfs}" font-weight="300" fill="#999999" dominant-baseline="central">{exposure} · {date}</text>
+  <text x="{ltx}" y="{ey}" font-family="{font}" font-size="{sfs}" font-weight="300" fill="{secondary}" dominant-baseline="central">{exposure} · {date}</text>
+  {histogram}
+  {qr}
+  {swatches}
 </svg>"##,
+        bg = c.background,
+        text = c.text,
+        secondary = c.secondary_text,
+        rule = c.rule,
         w = p.image_width,
         fh = p.frame_height as u32,
         ltx = p.left_text_x,
@@ -396,6 +791,10 @@ fn render_minimal_landscape(p: &LandscapeParams) -> String {
         sfs = p.small_font_size,
         exposure = p.exposure_info,
         date = p.date_time_string,
+        histogram = histogram,
+        qr = qr,
+        swatches = swatches,
+        font = p.font_family,
     )
 }
 
@@ -404,6 +803,7 @@ fn render_minimal_landscape(p: &LandscapeParams) -> String {
 const GRAIN_PATTERN: &str = "iVBORw0KGgoAAAANSUhEUgAAAGQAAABkCAYAAABw4pVUAAAHsklEQVR4nO2d25bjIAxEyf9/89TLdPcknkpJCYG5ZR3O8bKTOKmSAYPNz8/3i1bD79MDaGtvQdZ6C7LWW5C13oKs9RZkrbcga70FWestyFpvQdZ6C7LWW5C13oKs9RZkrbcga70FWeu/pwfQETO/1/4nIs/k/C7Y9pwly9IFaYJ4V44LdMzLaLmOngWpSVrMyr+KWirNxnKngkgT9TZWjbzptiZbl6QFa71nYS9hkCNQMTGjoLxmlui9npnmjKT2ERKWVE+UZZUZrulEVKl5Ni7HSvWDmZnnHySds5LU35uaICYQb0MrRRX/lKQKsC+L4qwkdXd9AzM3RbYlfx3VJ4jdif35ufLmw+KT11+i+gQB6ZuMnJKTktCch2hdgiQErz1X6B7qXiV5nIXGK1XVuf7fZEIRiZuRWvdm4sVj6wFv/FB7TMtZQt4MCak5vUxZ5Ej0uuhsYvXUjKdJXdcXmrIAquyLzUjePZcpp2UauK2NnK+i1EGyDPl7z4xI66WC0P01GdRLSS8kImVxDdH58hZN9G9bkVrRSb+bCKIiXBsH2N7r0ZfsaWFV/Jx1VWRG2nsz2rl5ETK9v4wfAuSlKyQWjYzEHO+93j1rAerJzJC26KiSaV3EIcnv3hzP6xYo5v5gE33vJrPR1JNEMEkXpDLas4KYLh2ttUl2oDcrE7KL0hfVm7C2e81hn5K8lKfrZV1ZJGJaRoIsBJTdJiXUGGm3FyX0lLUhtb6dJsJul4Rz49Kvkb6DNeiOonyVOsITGEn9mM1ze4uvclx4iQBW+9o5zWRdkc4SUu/zJ1IWqs+91UlkRQ2K1VtSKtojyqZpx15JF/lpVGSET7wCkQ5jXWx16HQ00olnHyQNXjl1Isx7nMlY7GMQhm3NVDn2N50eH7yKNTP5o6+0k8L3XAFQolZEW6KJsegeNbJ48k4kijjrhUTC5ZHDqkujYpVKXDK6KmtCS0a9JpEcuxx7ZB809i6nDu90KMiyqrhwzc1o72vFnNGTFOGkMLCXehWJilCXlEh9Zg6JdeGnxBQ7uQnvnpKuClQKIKfLP5S37RW4qHw3ERoLa+/kITsCsPY6+nqMPobaBrJZlTwVwNeQWl4WM0F/Y2cKcw9yQ1GeG7MQv0M2qT0lpVOkHF1UvJfcPzrM6MalZSqTw6pcXf8xJc0N1juZ5Vk9PzRXWYFSke0xtonYwEmZGdI0Y2pyGzBBiNmklX0oGJ3NnVEht/XZ600aSIzslflT1Qvbj46t6KiUS1POilOxLsMl/Y75KRS5ImkzG/K8KJjnSWfDyN6nELc87lD0YiYQZmmVacyaqt51lWC0aiyd5aonRHkdFYK0Z0kFC/VocAjcKwFeQYW40ejo2Mc9l0IiIy5cEB2T+ShoHzWBkRVcls/7tRz1MyJ7rmzco1PuGpsiKSnrTj9kxBPP5FjZlvlO+5r3WeAE3nGLPipREU71ekDKitwwkgWGxpJMK5p4gzKGhGhZHB6w13J1tLy8LXvQdAmOmtSdlNfaE0VBHtZE4oy8xuSJnBNaJ+8Zjv5I8DdK1L2vdiFHjpmX642jA04wkRNnQmJkVrrh0sMcU+5BZq1J8iJT2ZZkS/qrQP0nRH5lC1HL8hojXXE0Y1nPU8U9UjDXno3iepWyqKZ1VG7Nq1SvGCnq15Ao5FlRw5W/qz7Eg5o7iz2UKV+aCwYrT0WiSqN7znTzMi8aMsXX1kodNd7bM4XaM2PJJkjWlDDCvFgI5Sg0aFQgPRFtZX4zsozXTqPqlATtkwHjlPGDre1RB4j+TJ3cRVA8GuY5ThH8Vl0vNGZctmOMOyH7B2zthU68IZH0wyMZFYctXlJVy8sUNwyv9Ft3WSgIi4TIhO/JyrK9sVeGPro4GCllTS2baj/ld0IF+Z0N0Y6OQryu2exreFpy9EcWJ9DvK4/3YQai06NyUoQiJJasJVGaY+TJQwgyK6pAlQ62FyWAcLJc9akenuPWHuhjpbwVqboCQiO2UjOLQs5Oz6I4kV5WtJAoknHB7X2Gl0svzWK0mVhDtHkzjSbRZgTJ+AraiDCa4G1OjirF2cOiTtuGG5kgJpdGzuDZ57z7o2e8ipGXnXybTJ3U82Kyg7MF8H7XDLGARhcy+nP6rTDbV5mpx+klYCs4OQkbtO62QoIvPrZFDQvdB/kGb4/cC1VVRYx4VO6iAZnW5iclaDETXRn1LZDnjC51Lav/tWujgng5vFO5ecejf9ogz5XG/TyiqeZ1ilsKGvmGIttfaeFGfsMSOUUJTTll9YynVVC5OLpH7TU8Y9SZW9Sz/IgR29R2gTepi5Ys+89nFAiVvyPnLkvxrHKkWTdnETMSTcbZT3LNoQVzeCLXaIMQYlJkStKffwRqUDK/5dptdpYkQ6QXvYeSyfIUmWqnEkS0pNUfAXb7IcimT4kAJJ6N0mLs4keDpXlXlH9QbYsGO5cz9vQhwzIY7IISt4ImLeoLjjiTF6T717avgowVqEh61vjM5EeOSubvKxojSTG051MyLIm9hqvMOg1Z/a1uS6L2M4uc8WZtnSCriaxsrXmXVFRjplOav7/5ayoKIRNN9A3H0Ue9PiJIJ7oE+S0KUcE858iACYmTF1cTi1x/xCB37XKPm9S0t/WpdFrQ36JvavU6MqmyAAAAJXRFWHRkYXRlOmNyZWF0ZQAyMDE3LTAyLTI0VDA5OjE0OjM5KzAwOjAwkjvGJgAAACV0RVh0ZGF0ZTptb2RpZnkAMjAxNy0wMi0yNFQwOToxNDozOSswMDowMONmfpoAAAAASUVORK5CYII=";
 
 fn render_vintage_portrait(p: &PortraitParams) -> String {
+    let c = palette_or(&p.palette, "vintage");
     let fh = p.frame_height;
     let total_h = (fh * 2.0) as u32;
     let camera_y = fh * 0.4;
@@ -412,16 +812,22 @@ fn render_vintage_portrait(p: &PortraitParams) -> String {
 
     let photographer_el = if !p.photographer_name.is_empty() {
         format!(
-            r##"<text x="{x}" y="{y}" font-family="Courier, monospace" font-size="{fs}" font-weight="400" fill="#91785E" text-anchor="end" dominant-baseline="middle">Taken by {name}</text>"##,
+            r##"<text x="{x}" y="{y}" font-family="{font}" font-size="{fs}" font-weight="400" fill="{secondary}" text-anchor="end" dominant-baseline="middle">Taken by {name}</text>"##,
+            secondary = c.secondary_text,
             x = p.image_width as f64 - 20.0,
             y = fh * 1.6,
             fs = p.small_font_size * 0.8,
             name = p.photographer_name,
+            font = p.font_family,
         )
     } else {
         String::new()
     };
 
+    let histogram = histogram_el(&p.histogram, 20.0 + p.exposure_info.len() as f64 * p.small_font_size * 0.62 + fh * 0.2, fh * 0.7 - fh * 0.26, fh * 0.48, fh * 0.3, &c.secondary_text);
+    let qr = qr_el(&p.qr, 20.0, fh * 1.15, fh * 0.7, &c);
+    let swatches = swatch_row(&p.swatches, p.center_x, fh * 1.15, fh * 0.14, "middle", &c.muted_text, &p.font_family);
+
     format!(
         r##"<svg width="{w}" height="{h}" xmlns="http://www.w3.org/2000/svg">
   <defs>
@@ -429,13 +835,19 @@ fn render_vintage_portrait(p: &PortraitParams) -> String {
       <image href="data:image/png;base64,{grain}" x="0" y="0" width="100" height="100" />
     </pattern>
   </defs>
-  <rect width="{w}" height="{h}" fill="#F8F5E9"/>
+  <rect width="{w}" height="{h}" fill="{bg}"/>
   <rect width="{w}" height="{h}" fill="url(#grain)" opacity="0.1"/>
-  <text x="20" y="{cy}" font-family="Courier, monospace" font-size="{fs}" font-weight="700" fill="#70573B">{camera}</text>
-  <text x="20" y="{ey}" font-family="Courier, monospace" font-size="{sfs}" font-weight="400" fill="#91785E">{exposure}</text>
-  <text x="{rx}" y="{dy}" font-family="Courier, monospace" font-size="{dsfs}" font-weight="400" fill="#91785E" text-anchor="end">{date}</text>
+  <text x="20" y="{cy}" font-family="{font}" font-size="{fs}" font-weight="700" fill="{text}">{camera}</text>
+  <text x="20" y="{ey}" font-family="{font}" font-size="{sfs}" font-weight="400" fill="{secondary}">{exposure}</text>
+  <text x="{rx}" y="{dy}" font-family="{font}" font-size="{dsfs}" font-weight="400" fill="{secondary}" text-anchor="end">{date}</text>
   {photographer}
+  {histogram}
+  {qr}
+  {swatches}
 </svg>"##,
+        bg = c.background,
+        text = c.text,
+        secondary = c.secondary_text,
         w = p.image_width,
         h = total_h,
         grain = GRAIN_PATTERN,
@@ -450,22 +862,34 @@ fn render_vintage_portrait(p: &PortraitParams) -> String {
         dsfs = p.small_font_size * 0.8,
         date = p.date_time_string,
         photographer = photographer_el,
+        histogram = histogram,
+        qr = qr,
+        swatches = swatches,
+        font = p.font_family,
     )
 }
 
 fn render_vintage_landscape(p: &LandscapeParams) -> String {
+    let c = palette_or(&p.palette, "vintage");
     let photographer_el = if !p.photographer_name.is_empty() {
         format!(
-            r##"<text x="{x}" y="{y}" font-family="Courier, monospace" font-size="{fs}" font-weight="400" fill="#91785E" text-anchor="end">Taken by {name}</text>"##,
+            r##"<text x="{x}" y="{y}" font-family="{font}" font-size="{fs}" font-weight="400" fill="{secondary}" text-anchor="end">Taken by {name}</text>"##,
+            secondary = c.secondary_text,
             x = p.image_width as f64 - 20.0,
             y = p.center_y + 70.0,
             fs = p.small_font_size * 0.8,
             name = p.photographer_name,
+            font = p.font_family,
         )
     } else {
         String::new()
     };
 
+    let histogram = histogram_el(&p.histogram, 20.0 + p.exposure_info.len() as f64 * p.small_font_size * 0.62 + p.frame_height * 0.2, p.center_y + 70.0 - p.frame_height * 0.26, p.frame_height * 0.48, p.frame_height * 0.3, &c.secondary_text);
+    // Right of the centred swatch row, clear of the date and photographer
+    let qr = qr_el(&p.qr, p.image_width as f64 / 2.0 + p.frame_height * 1.5, p.center_y - p.frame_height * 0.3, p.frame_height * 0.6, &c);
+    let swatches = swatch_row(&p.swatches, p.image_width as f64 / 2.0, p.center_y - p.frame_height * 0.06, p.frame_height * 0.22, "middle", &c.muted_text, &p.font_family);
+
     format!(
         r##"<svg width="{w}" height="{fh}" xmlns="http://www.w3.org/2000/svg">
   <defs>
@@ -473,13 +897,19 @@ fn render_vintage_landscape(p: &LandscapeParams) -> String {
       <image href="data:image/png;base64,{grain}" x="0" y="0" width="100" height="100" />
     </pattern>
   </defs>
-  <rect width="{w}" height="{fh}" fill="#F8F5E9"/>
+  <rect width="{w}" height="{fh}" fill="{bg}"/>
   <rect width="{w}" height="{fh}" fill="url(#grain)" opacity="0.1"/>
-  <text x="20" y="{cy1}" font-family="Courier, monospace" font-size="{cfs}" font-weight="700" fill="#70573B">{camera}</text>
+  <text x="20" y="{cy1}" font-family="{font}" font-size="{cfs}" font-weight="700" fill="{text}">{camera}</text>
   {photographer}
-  <text x="20" y="{ey}" font-family="Courier, monospace" font-size="{sfs}" font-weight="400" fill="#91785E">{exposure}</text>
-  <text x="{rx}" y="{dcy}" font-family="Courier, monospace" font-size="{dsfs}" font-weight="400" fill="#91785E" text-anchor="end">{date}</text>
+  <text x="20" y="{ey}" font-family="{font}" font-size="{sfs}" font-weight="400" fill="{secondary}">{exposure}</text>
+  <text x="{rx}" y="{dcy}" font-family="{font}" font-size="{dsfs}" font-weight="400" fill="{secondary}" text-anchor="end">{date}</text>
+  {histogram}
+  {qr}
+  {swatches}
 </svg>"##,
+        bg = c.background,
+        text = c.text,
+        secondary = c.secondary_text,
         w = p.image_width,
         fh = p.frame_height as u32,
         grain = GRAIN_PATTERN,
@@ -494,6 +924,10 @@ fn render_vintage_landscape(p: &LandscapeParams) -> String {
         dcy = p.center_y,
         dsfs = p.small_font_size * 0.8,
         date = p.date_time_string,
+        histogram = histogram,
+        qr = qr,
+        swatches = swatches,
+        font = p.font_family,
     )
 }
 
@@ -510,6 +944,7 @@ fn extract_logo_href(logo_element: &str) -> Option<String> {
 }
 
 fn render_simple_portrait(p: &PortraitParams) -> String {
+    let c = palette_or(&p.palette, "simple");
     let fh = p.frame_height;
     let total_h = (fh * 2.0) as u32;
     let cx = p.center_x;
@@ -527,33 +962,44 @@ fn render_simple_portrait(p: &PortraitParams) -> String {
     } else {
         let brand = p.camera_info.split_whitespace().next().unwrap_or("");
         format!(
-            r##"<text x="{cx}" y="{y}" font-family="Arial, sans-serif" font-size="{fs}" font-weight="500" fill="#333333" text-anchor="middle" dominant-baseline="middle">{brand}</text>"##,
+            r##"<text x="{cx}" y="{y}" font-family="{font}" font-size="{fs}" font-weight="500" fill="{text}" text-anchor="middle" dominant-baseline="middle">{brand}</text>"##,
+            text = c.text,
             cx = cx,
             y = logo_y,
             fs = p.font_size * 1.2,
             brand = brand,
+            font = p.font_family,
         )
     };
 
     let photographer_el = if !p.photographer_name.is_empty() {
         format!(
-            r##"<text x="{cx}" y="{y}" font-family="Arial, sans-serif" font-size="{fs}" font-weight="300" fill="#777777" text-anchor="middle" dominant-baseline="middle">{name}</text>"##,
+            r##"<text x="{cx}" y="{y}" font-family="{font}" font-size="{fs}" font-weight="300" fill="{secondary}" text-anchor="middle" dominant-baseline="middle">{name}</text>"##,
+            secondary = c.secondary_text,
             cx = cx,
             y = photographer_y,
             fs = p.small_font_size * 0.8,
             name = p.photographer_name,
+            font = p.font_family,
         )
     } else {
         String::new()
     };
 
+    let qr = qr_el(&p.qr, p.image_width as f64 - fh * 0.9, fh * 1.25, fh * 0.6, &c);
+    let swatches = swatch_row(&p.swatches, cx, fh * 1.85, fh * 0.1, "middle", &c.muted_text, &p.font_family);
+
     format!(
         r##"<svg width="{w}" height="{h}" xmlns="http://www.w3.org/2000/svg">
-  <rect width="{w}" height="{h}" fill="#FFFFFF"/>
+  <rect width="{w}" height="{h}" fill="{bg}"/>
   {logo}
-  <text x="{cx}" y="{cy}" font-family="Arial, sans-serif" font-size="{fs}" font-weight="400" fill="#333333" text-anchor="middle" dominant-baseline="middle">{camera}</text>
+  <text x="{cx}" y="{cy}" font-family="{font}" font-size="{fs}" font-weight="400" fill="{text}" text-anchor="middle" dominant-baseline="middle">{camera}</text>
   {photographer}
+  {qr}
+  {swatches}
 </svg>"##,
+        bg = c.background,
+        text = c.text,
         w = p.image_width,
         h = total_h,
         logo = logo_display,
@@ -562,10 +1008,14 @@ fn render_simple_portrait(p: &PortraitParams) -> String {
         fs = p.font_size * 0.9,
         camera = p.camera_info,
         photographer = photographer_el,
+        qr = qr,
+        swatches = swatches,
+        font = p.font_family,
     )
 }
 
 fn render_simple_landscape(p: &LandscapeParams) -> String {
+    let c = palette_or(&p.palette, "simple");
     let cx = p.image_width as f64 / 2.0;
     let logo_y = p.center_y - 45.0;
     let text_y = p.center_y + 80.0;
@@ -580,17 +1030,20 @@ fn render_simple_landscape(p: &LandscapeParams) -> String {
     } else {
         let brand = p.camera_info.split_whitespace().next().unwrap_or("");
         format!(
-            r##"<text x="{cx}" y="{y}" font-family="Arial, sans-serif" font-size="{fs}" font-weight="500" fill="#333333" text-anchor="middle" dominant-baseline="middle">{brand}</text>"##,
+            r##"<text x="{cx}" y="{y}" font-family="{font}" font-size="{fs}" font-weight="500" fill="{text}" text-anchor="middle" dominant-baseline="middle">{brand}</text>"##,
+            text = c.text,
             cx = cx,
             y = logo_y,
             fs = p.font_size * 1.2,
             brand = brand,
+            font = p.font_family,
         )
     };
 
     let photographer_tspan = if !p.photographer_name.is_empty() {
         format!(
-            r##"<tspan dx="10" font-size="{fs}" font-weight="300" fill="#999999">by {name}</tspan>"##,
+            r##"<tspan dx="10" font-size="{fs}" font-weight="300" fill="{muted}">by {name}</tspan>"##,
+            muted = c.muted_text,
             fs = p.small_font_size * 0.7,
             name = p.photographer_name,
         )
@@ -598,15 +1051,23 @@ fn render_simple_landscape(p: &LandscapeParams) -> String {
         String::new()
     };
 
+    let edge = p.frame_height * 0.05;
+    let qr = qr_el(&p.qr, edge, p.center_y - p.frame_height * 0.3, p.frame_height * 0.6, &c);
+    let swatches = swatch_row(&p.swatches, p.image_width as f64 - edge, p.center_y, p.frame_height * 0.22, "end", &c.muted_text, &p.font_family);
+
     format!(
         r##"<svg width="{w}" height="{fh}" xmlns="http://www.w3.org/2000/svg">
-  <rect width="{w}" height="{fh}" fill="#FFFFFF"/>
+  <rect width="{w}" height="{fh}" fill="{bg}"/>
   {logo}
-  <text x="{cx}" y="{ty}" font-family="Arial, sans-serif" text-anchor="middle" dominant-baseline="central">
-    <tspan font-size="{fs}" font-weight="400" fill="#333333">{camera}</tspan>
+  <text x="{cx}" y="{ty}" font-family="{font}" text-anchor="middle" dominant-baseline="central">
+    <tspan font-size="{fs}" font-weight="400" fill="{text}">{camera}</tspan>
     {photographer}
   </text>
+  {qr}
+  {swatches}
 </svg>"##,
+        bg = c.background,
+        text = c.text,
         w = p.image_width,
         fh = p.frame_height as u32,
         logo = logo_display,
@@ -615,12 +1076,16 @@ fn render_simple_landscape(p: &LandscapeParams) -> String {
         fs = p.font_size * 0.9,
         camera = p.camera_info,
         photographer = photographer_tspan,
+        qr = qr,
+        swatches = swatches,
+        font = p.font_family,
     )
 }
 
 // ─── Micro ────────────────────────────────────────────
 
 fn render_micro_portrait(p: &PortraitParams) -> String {
+    let c = palette_or(&p.palette, "micro");
     let fh = p.frame_height;
     let thin_h = (fh * 0.5).round() as u32;
     let middle_y = thin_h as f64 * 0.5;
@@ -649,11 +1114,13 @@ fn render_micro_portrait(p: &PortraitParams) -> String {
 
     let photographer_el = if !p.photographer_name.is_empty() {
         format!(
-            r##"<text x="{x}" y="{y}" font-family="Arial, sans-serif" font-size="{fs}" font-weight="300" fill="#777777" dominant-baseline="middle">by {name}</text>"##,
+            r##"<text x="{x}" y="{y}" font-family="{font}" font-size="{fs}" font-weight="300" fill="{secondary}" dominant-baseline="middle">by {name}</text>"##,
+            secondary = c.secondary_text,
             x = photographer_x,
             y = camera_y,
             fs = p.small_font_size * 0.7,
             name = p.photographer_name,
+            font = p.font_family,
         )
     } else {
         String::new()
@@ -661,24 +1128,39 @@ fn render_micro_portrait(p: &PortraitParams) -> String {
 
     let exposure_el = if !p.exposure_info.is_empty() {
         format!(
-            r##"<text x="{x}" y="{y}" font-family="Arial, sans-serif" font-size="{fs}" font-weight="300" fill="#777777" dominant-baseline="middle">{info}</text>"##,
+            r##"<text x="{x}" y="{y}" font-family="{font}" font-size="{fs}" font-weight="300" fill="{secondary}" dominant-baseline="middle">{info}</text>"##,
+            secondary = c.secondary_text,
             x = left_margin,
             y = exposure_y,
             fs = p.small_font_size * 0.7,
             info = p.exposure_info,
+            font = p.font_family,
         )
     } else {
         String::new()
     };
 
+    let gap = thin_h as f64 * 0.05;
+    let histogram = histogram_el(&p.histogram, left_margin + p.exposure_info.len() as f64 * p.small_font_size * 0.7 * 0.55 + gap, exposure_y - p.small_font_size * 0.4, p.small_font_size * 1.3, p.small_font_size * 0.8, &c.secondary_text);
+    let qr_size = thin_h as f64 * 0.8;
+    let qr_x = p.image_width as f64 - thin_h as f64 * 0.08 - qr_size;
+    let qr = qr_el(&p.qr, qr_x, thin_h as f64 * 0.1, qr_size, &c);
+    let swatches_x = if p.qr.is_some() { qr_x - gap } else { p.image_width as f64 - thin_h as f64 * 0.08 };
+    let swatches = swatch_row(&p.swatches, swatches_x, thin_h as f64 * 0.4, thin_h as f64 * 0.4, "end", &c.muted_text, &p.font_family);
+
     format!(
         r##"<svg width="{w}" height="{h}" xmlns="http://www.w3.org/2000/svg">
-  <rect width="{w}" height="{h}" fill="#FFFFFF"/>
+  <rect width="{w}" height="{h}" fill="{bg}"/>
   {logo}
-  <text x="{lm}" y="{cy}" font-family="Arial, sans-serif" font-size="{sfs}" font-weight="500" fill="#333333" dominant-baseline="middle">{camera}</text>
+  <text x="{lm}" y="{cy}" font-family="{font}" font-size="{sfs}" font-weight="500" fill="{text}" dominant-baseline="middle">{camera}</text>
   {photographer}
   {exposure}
+  {histogram}
+  {qr}
+  {swatches}
 </svg>"##,
+        bg = c.background,
+        text = c.text,
         w = p.image_width,
         h = thin_h,
         logo = logo_display,
@@ -688,10 +1170,15 @@ fn render_micro_portrait(p: &PortraitParams) -> String {
         camera = p.camera_info,
         photographer = photographer_el,
         exposure = exposure_el,
+        histogram = histogram,
+        qr = qr,
+        swatches = swatches,
+        font = p.font_family,
     )
 }
 
 fn render_micro_landscape(p: &LandscapeParams) -> String {
+    let c = palette_or(&p.palette, "micro");
     let fh = p.frame_height;
     let thin_h = (fh * 0.5).round() as u32;
     let middle_y = thin_h as f64 * 0.5;
@@ -720,11 +1207,13 @@ fn render_micro_landscape(p: &LandscapeParams) -> String {
 
     let photographer_el = if !p.photographer_name.is_empty() {
         format!(
-            r##"<text x="{x}" y="{y}" font-family="Arial, sans-serif" font-size="{fs}" font-weight="300" fill="#777777" dominant-baseline="middle">by {name}</text>"##,
+            r##"<text x="{x}" y="{y}" font-family="{font}" font-size="{fs}" font-weight="300" fill="{secondary}" dominant-baseline="middle">by {name}</text>"##,
+            secondary = c.secondary_text,
             x = photographer_x,
             y = camera_y,
             fs = p.small_font_size * 0.7,
             name = p.photographer_name,
+            font = p.font_family,
         )
     } else {
         String::new()
@@ -732,24 +1221,39 @@ fn render_micro_landscape(p: &LandscapeParams) -> String {
 
     let exposure_el = if !p.exposure_info.is_empty() {
         format!(
-            r##"<text x="{x}" y="{y}" font-family="Arial, sans-serif" font-size="{fs}" font-weight="300" fill="#777777" dominant-baseline="middle">{info}</text>"##,
+            r##"<text x="{x}" y="{y}" font-family="{font}" font-size="{fs}" font-weight="300" fill="{secondary}" dominant-baseline="middle">{info}</text>"##,
+            secondary = c.secondary_text,
             x = left_margin,
             y = exposure_y,
             fs = p.small_font_size * 0.7,
             info = p.exposure_info,
+            font = p.font_family,
         )
     } else {
         String::new()
     };
 
+    let gap = thin_h as f64 * 0.05;
+    let histogram = histogram_el(&p.histogram, left_margin + p.exposure_info.len() as f64 * p.small_font_size * 0.7 * 0.55 + gap, exposure_y - p.small_font_size * 0.4, p.small_font_size * 1.3, p.small_font_size * 0.8, &c.secondary_text);
+    let qr_size = thin_h as f64 * 0.8;
+    let qr_x = p.image_width as f64 - thin_h as f64 * 0.08 - qr_size;
+    let qr = qr_el(&p.qr, qr_x, thin_h as f64 * 0.1, qr_size, &c);
+    let swatches_x = if p.qr.is_some() { qr_x - gap } else { p.image_width as f64 - thin_h as f64 * 0.08 };
+    let swatches = swatch_row(&p.swatches, swatches_x, thin_h as f64 * 0.4, thin_h as f64 * 0.4, "end", &c.muted_text, &p.font_family);
+
     format!(
         r##"<svg width="{w}" height="{h}" xmlns="http://www.w3.org/2000/svg">
-  <rect width="{w}" height="{h}" fill="#FFFFFF"/>
+  <rect width="{w}" height="{h}" fill="{bg}"/>
   {logo}
-  <text x="{lm}" y="{cy}" font-family="Arial, sans-serif" font-size="{sfs}" font-weight="500" fill="#333333" dominant-baseline="middle">{camera}</text>
+  <text x="{lm}" y="{cy}" font-family="{font}" font-size="{sfs}" font-weight="500" fill="{text}" dominant-baseline="middle">{camera}</text>
   {photographer}
   {exposure}
+  {histogram}
+  {qr}
+  {swatches}
 </svg>"##,
+        bg = c.background,
+        text = c.text,
         w = p.image_width,
         h = thin_h,
         logo = logo_display,
@@ -759,9 +1263,37 @@ fn render_micro_landscape(p: &LandscapeParams) -> String {
         camera = p.camera_info,
         photographer = photographer_el,
         exposure = exposure_el,
+        histogram = histogram,
+        qr = qr,
+        swatches = swatches,
+        font = p.font_family,
     )
 }
 
+/// Returns the dominant frame background colour of the given design.
+pub fn get_background_color(design_id: &str) -> [u8; 3] {
+    palette::parse_hex(&get_palette(design_id).background).expect("built-in palette colour")
+}
+
+/// Whether the design is drawn over a blurred copy of the photo rather than
+/// its own background.
+pub fn uses_photo_backdrop(design_id: &str) -> bool {
+    design_id == "frosted"
+}
+
+/// WCAG relative luminance of an sRGB colour, from 0.0 (black) to 1.0 (white).
+pub fn relative_luminance(rgb: [u8; 3]) -> f64 {
+    let channel = |c: u8| {
+        let c = c as f64 / 255.0;
+        if c <= 0.03928 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    0.2126 * channel(rgb[0]) + 0.7152 * channel(rgb[1]) + 0.0722 * channel(rgb[2])
+}
+
 /// Returns the SVG height used by the watermark for the given design/orientation.
 pub fn get_frame_svg_height(design_id: &str, frame_height: f64, is_portrait: bool) -> u32 {
     let is_micro = design_id == "micro";
@@ -0,0 +1,779 @@
+use resvg::usvg::fontdb;
+use serde::Deserialize;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::brands::BrandRegistry;
+use crate::c2pa::C2paSigner;
+use crate::camera_names::{CameraNames, ModelNameStyle};
+use crate::color::ColorSpace;
+use crate::fonts;
+use crate::histogram::HistogramStyle;
+use crate::invisible;
+use crate::palette::{self, FrameColor};
+use crate::rights::Rights;
+use crate::watermark;
+
+// ─── Options ──────────────────────────────────────────
+
+/// Peak memory used for decoding and compositing when no budget is set.
+pub const DEFAULT_MEMORY_BUDGET: u64 = 2 * 1024 * 1024 * 1024;
+
+/// Where brand logos are loaded from.
+#[derive(Debug, Clone, Default)]
+pub enum LogoSource {
+    /// Directory of `<brand>.png` files, like the bundled `assets/models`
+    Directory(PathBuf),
+    /// No logos; designs fall back to the brand name as text
+    #[default]
+    None,
+}
+
+/// Look of the blurred photo behind the frosted design.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FrostOptions {
+    /// Blur radius as a fraction of the frame height, so the look is the
+    /// same at any resolution
+    pub blur: f32,
+    /// How much the blurred photo is darkened, from 0.0 (not at all) to 1.0 (black)
+    pub dim: f32,
+}
+
+impl Default for FrostOptions {
+    fn default() -> Self {
+        Self { blur: 0.3, dim: 0.45 }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct WatermarkOptions {
+    pub photographer_name: String,
+    /// Font family tried before the design's bundled font
+    pub font_family: Option<String>,
+    pub model_names: ModelNameStyle,
+    pub color_space: ColorSpace,
+    pub frame_color: FrameColor,
+    pub frost: FrostOptions,
+    /// Peak memory for one image, in bytes. Larger images are streamed to
+    /// the encoder; ones that don't fit even then are rejected.
+    pub memory_budget: Option<u64>,
+    /// Leave the area behind the frame transparent instead of white in PNG
+    /// and WebP output, for designs with (semi-)transparent backgrounds
+    pub transparent_frame: bool,
+    /// How many of the photo's dominant colours are shown as swatches in
+    /// the frame, up to [`palette::MAX_SWATCHES`]; 0 for none
+    pub swatches: usize,
+    /// Histogram drawn next to the exposure info, `None` for none
+    pub histogram: Option<HistogramStyle>,
+    /// Link drawn as a QR code in the frame; `{name}` is replaced by the
+    /// photo's file name without extension
+    pub qr_link: Option<String>,
+    /// Photographer ID hidden in the photo by an invisible watermark, along
+    /// with an ID derived from the file name; `None` for no watermark
+    pub invisible_mark: Option<u32>,
+    /// Signer for a C2PA manifest on JPEG output, stating the creator, the
+    /// camera and the frame edit; `None` for no content credentials
+    pub content_credentials: Option<C2paSigner>,
+    /// Print the photo's XMP title (or its caption when it has no title)
+    /// before the date; a sidecar caption takes its place
+    pub show_title: bool,
+    /// Copyright notice, usage terms, contact details and credit line,
+    /// written with the photographer as creator into the metadata of JPEG,
+    /// PNG and WebP output
+    pub rights: Rights,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    Jpeg,
+    Png,
+    WebP,
+    Tiff,
+}
+
+impl OutputFormat {
+    /// Picks the format from the file extension, defaulting to JPEG.
+    pub fn from_path(path: &Path) -> Self {
+        let ext = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or("jpg")
+            .to_lowercase();
+        match ext.as_str() {
+            "png" => OutputFormat::Png,
+            "webp" => OutputFormat::WebP,
+            "tif" | "tiff" => OutputFormat::Tiff,
+            _ => OutputFormat::Jpeg,
+        }
+    }
+
+    /// Whether the format keeps transparency; the others are flattened
+    /// onto white.
+    pub fn supports_alpha(self) -> bool {
+        matches!(self, OutputFormat::Png | OutputFormat::WebP)
+    }
+}
+
+/// How the outputs of a run with several designs are told apart.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DesignOutput {
+    /// `photo-dark.jpg` next to `photo-classic.jpg`
+    #[default]
+    Suffix,
+    /// `dark/photo.jpg` next to `classic/photo.jpg`
+    Subfolder,
+}
+
+impl DesignOutput {
+    /// Parses the names used by the CLI and the frontend.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "suffix" => Some(DesignOutput::Suffix),
+            "subfolder" => Some(DesignOutput::Subfolder),
+            _ => None,
+        }
+    }
+
+    /// Where the `design_id` version of `output_path` goes.
+    pub fn path_for(self, output_path: &Path, design_id: &str) -> PathBuf {
+        let file_name = output_path.file_name().unwrap_or_default();
+        match self {
+            DesignOutput::Suffix => {
+                let stem = output_path.file_stem().unwrap_or_default().to_string_lossy();
+                let name = match output_path.extension() {
+                    Some(ext) => format!("{}-{}.{}", stem, design_id, ext.to_string_lossy()),
+                    None => format!("{}-{}", stem, design_id),
+                };
+                output_path.with_file_name(name)
+            }
+            DesignOutput::Subfolder => output_path
+                .parent()
+                .unwrap_or(Path::new(""))
+                .join(design_id)
+                .join(file_name),
+        }
+    }
+}
+
+// ─── Watermarker ──────────────────────────────────────
+
+/// Tauri-free watermark engine: one design plus everything needed to render
+/// it. Build once per run and reuse it for every image.
+#[derive(Clone)]
+pub struct Watermarker {
+    pub(crate) design_id: String,
+    pub(crate) options: WatermarkOptions,
+    pub(crate) brands: BrandRegistry,
+    pub(crate) camera_names: CameraNames,
+    pub(crate) logos: LogoSource,
+    pub(crate) fontdb: Arc<fontdb::Database>,
+}
+
+pub struct WatermarkerBuilder {
+    design_id: String,
+    options: WatermarkOptions,
+    brands: Option<BrandRegistry>,
+    camera_names: Option<CameraNames>,
+    logos: LogoSource,
+    font_dirs: Vec<PathBuf>,
+    system_fonts: bool,
+}
+
+impl Watermarker {
+    pub fn builder() -> WatermarkerBuilder {
+        WatermarkerBuilder {
+            design_id: "classic".into(),
+            options: WatermarkOptions::default(),
+            brands: None,
+            camera_names: None,
+            logos: LogoSource::None,
+            font_dirs: Vec::new(),
+            system_fonts: true,
+        }
+    }
+
+    pub fn design_id(&self) -> &str {
+        &self.design_id
+    }
+
+    pub fn options(&self) -> &WatermarkOptions {
+        &self.options
+    }
+
+    /// Frames an encoded image and returns the encoded result.
+    pub fn process_bytes(&self, image_data: &[u8], format: OutputFormat) -> Result<Vec<u8>, String> {
+        let mut out = Cursor::new(Vec::new());
+        watermark::process(&mut Cursor::new(image_data), self, format, None, &mut out)?;
+        Ok(out.into_inner())
+    }
+
+    /// Frames `input_path` and writes it to `output_path`, choosing the
+    /// format from the output extension.
+    pub fn process_path(&self, input_path: &Path, output_path: &Path) -> Result<(), String> {
+        watermark::add_watermark_frame(input_path, output_path, self)
+    }
+
+    /// The same settings in another design, with that design's font choice
+    /// (`None` for its bundled font).
+    pub fn with_design(&self, design_id: &str, font_family: Option<String>) -> Watermarker {
+        let mut wm = self.clone();
+        wm.design_id = design_id.to_string();
+        wm.options.font_family = font_family;
+        wm
+    }
+
+    /// Returns the frame SVG the image would get, without rendering it.
+    pub fn render_frame_svg(&self, image_data: &[u8]) -> Result<String, String> {
+        watermark::frame_svg_for(image_data, self)
+    }
+}
+
+/// What framing a photo with [`process_path_designs`] did.
+#[derive(Debug, Clone, Default)]
+pub struct FramedPhoto {
+    /// Fields the photo's sidecar set, as in [`Overrides::applied`]
+    ///
+    /// [`Overrides::applied`]: crate::overrides::Overrides::applied
+    pub overrides: Vec<&'static str>,
+    /// Result of each output, in order
+    pub results: Vec<Result<(), String>>,
+}
+
+/// Frames `input_path` once per watermarker, typically the same settings in
+/// several designs, decoding the photo only once. Outputs should share a
+/// format. Fails if the photo can't be read; otherwise returns the result of
+/// each output in order, along with the sidecar overrides applied.
+pub fn process_path_designs(input_path: &Path, outputs: &[(&Watermarker, PathBuf)]) -> Result<FramedPhoto, String> {
+    watermark::add_watermark_frames(input_path, outputs)
+}
+
+/// Reads the invisible watermark from an encoded image, framed or with the
+/// frame cropped off. `Ok(None)` when the image carries none.
+pub fn verify_watermark(image_data: &[u8]) -> Result<Option<invisible::Payload>, String> {
+    let image = image::load_from_memory(image_data).map_err(|e| format!("Failed to decode image: {}", e))?;
+    Ok(invisible::detect(&image))
+}
+
+impl WatermarkerBuilder {
+    pub fn design(mut self, design_id: &str) -> Self {
+        self.design_id = design_id.to_string();
+        self
+    }
+
+    pub fn options(mut self, options: WatermarkOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    pub fn photographer_name(mut self, name: &str) -> Self {
+        self.options.photographer_name = name.to_string();
+        self
+    }
+
+    pub fn font_family(mut self, family: Option<String>) -> Self {
+        self.options.font_family = family;
+        self
+    }
+
+    pub fn model_names(mut self, style: ModelNameStyle) -> Self {
+        self.options.model_names = style;
+        self
+    }
+
+    pub fn color_space(mut self, color_space: ColorSpace) -> Self {
+        self.options.color_space = color_space;
+        self
+    }
+
+    pub fn frame_color(mut self, frame_color: FrameColor) -> Self {
+        self.options.frame_color = frame_color;
+        self
+    }
+
+    pub fn frost(mut self, frost: FrostOptions) -> Self {
+        self.options.frost = frost;
+        self
+    }
+
+    pub fn memory_budget(mut self, bytes: u64) -> Self {
+        self.options.memory_budget = Some(bytes);
+        self
+    }
+
+    pub fn transparent_frame(mut self, enabled: bool) -> Self {
+        self.options.transparent_frame = enabled;
+        self
+    }
+
+    pub fn swatches(mut self, count: usize) -> Self {
+        self.options.swatches = count;
+        self
+    }
+
+    pub fn histogram(mut self, style: Option<HistogramStyle>) -> Self {
+        self.options.histogram = style;
+        self
+    }
+
+    pub fn qr_link(mut self, link: Option<String>) -> Self {
+        self.options.qr_link = link;
+        self
+    }
+
+    pub fn invisible_mark(mut self, photographer_id: Option<u32>) -> Self {
+        self.options.invisible_mark = photographer_id;
+        self
+    }
+
+    pub fn content_credentials(mut self, signer: Option<C2paSigner>) -> Self {
+        self.options.content_credentials = signer;
+        self
+    }
+
+    pub fn show_title(mut self, enabled: bool) -> Self {
+        self.options.show_title = enabled;
+        self
+    }
+
+    pub fn rights(mut self, rights: Rights) -> Self {
+        self.options.rights = rights;
+        self
+    }
+
+    /// Brand registry used for logo lookup; defaults to the built-in one.
+    pub fn brands(mut self, brands: BrandRegistry) -> Self {
+        self.brands = Some(brands);
+        self
+    }
+
+    /// Model name database; defaults to the built-in one.
+    pub fn camera_names(mut self, names: CameraNames) -> Self {
+        self.camera_names = Some(names);
+        self
+    }
+
+    pub fn logos(mut self, logos: LogoSource) -> Self {
+        self.logos = logos;
+        self
+    }
+
+    /// Extra font directories loaded on top of the bundled fonts.
+    pub fn font_dirs(mut self, dirs: Vec<PathBuf>) -> Self {
+        self.font_dirs = dirs;
+        self
+    }
+
+    /// Whether system fonts are available as glyph fallback. Disable for
+    /// output that must not depend on the host.
+    pub fn system_fonts(mut self, enabled: bool) -> Self {
+        self.system_fonts = enabled;
+        self
+    }
+
+    /// Out-of-range options, whether set one by one or through
+    /// [`options`](Self::options), are clamped here.
+    pub fn build(self) -> Watermarker {
+        let options = self.options;
+        Watermarker {
+            design_id: self.design_id,
+            options: WatermarkOptions {
+                swatches: options.swatches.min(palette::MAX_SWATCHES),
+                frost: FrostOptions {
+                    blur: options.frost.blur.max(0.0),
+                    dim: options.frost.dim.clamp(0.0, 1.0),
+                },
+                qr_link: options.qr_link.filter(|l| !l.is_empty()),
+                ..options
+            },
+            brands: self.brands.unwrap_or_default(),
+            camera_names: self.camera_names.unwrap_or_default(),
+            logos: self.logos,
+            fontdb: fonts::load(&self.font_dirs, self.system_fonts),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color;
+    use crate::test_support::TempDir;
+    use image::codecs::jpeg::JpegEncoder;
+    use image::{DynamicImage, ImageBuffer, ImageDecoder, ImageEncoder, ImageFormat, ImageReader, Rgb, RgbImage};
+
+    fn builder() -> WatermarkerBuilder {
+        Watermarker::builder().system_fonts(false)
+    }
+
+    /// `img` encoded as `format`, to frame.
+    fn encode(img: impl Into<DynamicImage>, format: ImageFormat) -> Vec<u8> {
+        let mut out = Cursor::new(Vec::new());
+        img.into().write_to(&mut out, format).unwrap();
+        out.into_inner()
+    }
+
+    /// PNG photo coloured by `pixel`.
+    fn fixture(width: u32, height: u32, pixel: impl Fn(u32, u32) -> Rgb<u8>) -> Vec<u8> {
+        encode(RgbImage::from_fn(width, height, pixel), ImageFormat::Png)
+    }
+
+    /// Frames `input` as `format` and decodes the result.
+    fn frame(wm: &Watermarker, input: &[u8], format: OutputFormat) -> DynamicImage {
+        let out = wm.process_bytes(input, format).unwrap();
+        image::load_from_memory(&out).unwrap()
+    }
+
+    fn sample_jpeg() -> Vec<u8> {
+        encode(RgbImage::from_pixel(120, 80, Rgb([90, 140, 200])), ImageFormat::Jpeg)
+    }
+
+    #[test]
+    fn process_bytes_encodes_every_format() {
+        let wm = builder().build();
+        let input = sample_jpeg();
+        for (format, expected) in [
+            (OutputFormat::Jpeg, ImageFormat::Jpeg),
+            (OutputFormat::Png, ImageFormat::Png),
+            (OutputFormat::WebP, ImageFormat::WebP),
+            (OutputFormat::Tiff, ImageFormat::Tiff),
+        ] {
+            let out = wm.process_bytes(&input, format).unwrap();
+            assert_eq!(image::guess_format(&out).unwrap(), expected);
+            let framed = image::load_from_memory(&out).unwrap();
+            assert_eq!(framed.width(), 120);
+            assert!(framed.height() > 80);
+        }
+    }
+
+    #[test]
+    fn output_is_tagged_with_target_profile() {
+        let img = RgbImage::from_pixel(64, 48, Rgb([40, 200, 60]));
+        let adobe = moxcms::ColorProfile::new_adobe_rgb().encode().unwrap();
+        let mut input = Vec::new();
+        let mut encoder = JpegEncoder::new_with_quality(&mut input, 95);
+        encoder.set_icc_profile(adobe).unwrap();
+        img.write_with_encoder(encoder).unwrap();
+
+        let wm = builder().color_space(ColorSpace::DisplayP3).build();
+        let p3 = color::target_profile(ColorSpace::DisplayP3).unwrap().to_vec();
+        for format in [OutputFormat::Jpeg, OutputFormat::Png, OutputFormat::WebP] {
+            let out = wm.process_bytes(&input, format).unwrap();
+            let mut decoder = ImageReader::new(Cursor::new(out))
+                .with_guessed_format()
+                .unwrap()
+                .into_decoder()
+                .unwrap();
+            assert_eq!(decoder.icc_profile().unwrap(), Some(p3.clone()), "{:?}", format);
+        }
+
+        // image's TIFF decoder doesn't find the ICC tag, so read it directly
+        let tiff = wm.process_bytes(&input, OutputFormat::Tiff).unwrap();
+        let mut decoder = tiff::decoder::Decoder::new(Cursor::new(tiff)).unwrap();
+        assert_eq!(decoder.get_tag_u8_vec(tiff::tags::Tag::IccProfile).unwrap(), p3);
+    }
+
+    #[test]
+    fn sixteen_bit_masters_keep_their_depth() {
+        // 1000 isn't representable in 8 bits (1000 / 257 ≈ 3.9)
+        let img: ImageBuffer<Rgb<u16>, Vec<u16>> = ImageBuffer::from_pixel(64, 48, Rgb([1000, 30000, 65000]));
+        let input = encode(img, ImageFormat::Png);
+
+        let wm = builder().build();
+        for format in [OutputFormat::Png, OutputFormat::Tiff] {
+            let framed = frame(&wm, &input, format).into_rgb16();
+            assert_eq!(framed.get_pixel(10, 10), &Rgb([1000, 30000, 65000]), "{:?}", format);
+        }
+        // 8-bit formats still get a plain 8-bit image
+        assert_eq!(frame(&wm, &input, OutputFormat::Jpeg).color(), image::ColorType::Rgb8);
+    }
+
+    #[test]
+    fn tiff_profile_survives_keep_source() {
+        let input = fixture(64, 48, |_, _| Rgb([40, 200, 60]));
+        let adobe_tiff = builder().color_space(ColorSpace::AdobeRgb).build().process_bytes(&input, OutputFormat::Tiff).unwrap();
+        let adobe = tiff::decoder::Decoder::new(Cursor::new(&adobe_tiff))
+            .unwrap()
+            .get_tag_u8_vec(tiff::tags::Tag::IccProfile)
+            .unwrap();
+        assert_eq!(adobe, color::target_profile(ColorSpace::AdobeRgb).unwrap());
+
+        let keep = builder().color_space(ColorSpace::KeepSource).build();
+        let out = keep.process_bytes(&adobe_tiff, OutputFormat::Tiff).unwrap();
+        let mut decoder = tiff::decoder::Decoder::new(Cursor::new(out)).unwrap();
+        assert_eq!(decoder.get_tag_u8_vec(tiff::tags::Tag::IccProfile).unwrap(), adobe);
+    }
+
+    #[test]
+    fn streamed_output_matches_in_memory() {
+        // Translucent gradient with a profile, so blending and conversion run
+        let img: ImageBuffer<image::Rgba<u16>, Vec<u16>> = ImageBuffer::from_fn(300, 200, |x, y| {
+            image::Rgba([(x * 200) as u16, (y * 300) as u16, 40000, 50000 + y as u16 * 50])
+        });
+        let adobe = moxcms::ColorProfile::new_adobe_rgb().encode().unwrap();
+        let mut input16 = Vec::new();
+        let mut encoder = image::codecs::png::PngEncoder::new(&mut input16);
+        encoder.set_icc_profile(adobe).unwrap();
+        img.write_with_encoder(encoder).unwrap();
+        let input8 = encode(DynamicImage::ImageRgba16(img).to_rgba8(), ImageFormat::Png);
+
+        let in_memory = builder().color_space(ColorSpace::DisplayP3).build();
+        // Too small for the full canvas, enough for strips
+        let streaming = builder().color_space(ColorSpace::DisplayP3).memory_budget(1_500_000).build();
+
+        for input in [&input16, &input8] {
+            for format in [OutputFormat::Jpeg, OutputFormat::Png, OutputFormat::Tiff] {
+                let expected = frame(&in_memory, input, format);
+                let actual = frame(&streaming, input, format);
+                assert_eq!(actual.color(), expected.color(), "{:?}", format);
+                assert!(actual.as_bytes() == expected.as_bytes(), "{:?} differs", format);
+            }
+        }
+
+        // Streamed TIFFs are compressed like the in-memory ones
+        let flat = fixture(300, 200, |_, _| Rgb([90, 140, 200]));
+        let tiff = streaming.process_bytes(&flat, OutputFormat::Tiff).unwrap();
+        assert!(tiff.len() < 300 * 220 * 3 / 4, "{} bytes", tiff.len());
+    }
+
+    #[test]
+    fn oversized_images_are_rejected() {
+        let wm = builder().memory_budget(10_000).build();
+        let err = wm.process_bytes(&sample_jpeg(), OutputFormat::Png).unwrap_err();
+        assert!(err.contains("memory budget"), "{}", err);
+
+        // Streaming fits this budget, but WebP can't be streamed
+        let input = encode(RgbImage::new(400, 300), ImageFormat::Jpeg);
+        let wm = builder().memory_budget(1_000_000).build();
+        let err = wm.process_bytes(&input, OutputFormat::WebP).unwrap_err();
+        assert!(err.contains("memory budget"), "{}", err);
+        assert!(wm.process_bytes(&input, OutputFormat::Jpeg).is_ok());
+    }
+
+    #[test]
+    fn transparent_sources_keep_alpha_where_supported() {
+        let img = ImageBuffer::from_fn(64, 48, |x, _| image::Rgba([200u8, 40, 40, if x < 32 { 0 } else { 255 }]));
+        let input = encode(img, ImageFormat::Png);
+
+        let wm = builder().build();
+        for format in [OutputFormat::Png, OutputFormat::WebP] {
+            let framed = frame(&wm, &input, format).into_rgba8();
+            assert_eq!(framed.get_pixel(10, 10)[3], 0, "{:?}", format);
+            assert_eq!(framed.get_pixel(40, 10), &image::Rgba([200, 40, 40, 255]));
+            // The frame itself stays opaque
+            assert_eq!(framed.get_pixel(10, 47 + 5)[3], 255);
+        }
+
+        let flattened = frame(&wm, &input, OutputFormat::Jpeg).into_rgb8();
+        assert!(flattened.get_pixel(10, 10).0.iter().all(|&c| c > 245));
+    }
+
+    #[test]
+    fn adaptive_frame_is_tinted_with_the_photo() {
+        let input = fixture(200, 120, |_, _| Rgb([30, 90, 160]));
+        let wm = builder().frame_color(FrameColor::Adaptive).build();
+        assert!(wm.render_frame_svg(&input).unwrap().contains("#1E5AA0"));
+
+        let framed = frame(&wm, &input, OutputFormat::Png).into_rgb8();
+        let corner = framed.get_pixel(1, framed.height() - 2);
+        assert!(corner.0.iter().zip([30, 90, 160]).all(|(&a, b)| a.abs_diff(b) <= 3), "{:?}", corner);
+    }
+
+    #[test]
+    fn swatches_show_the_photos_colours() {
+        let input = fixture(200, 120, |x, _| if x < 100 { Rgb([200, 40, 40]) } else { Rgb([30, 60, 200]) });
+        assert!(!builder().build().render_frame_svg(&input).unwrap().contains("#C82828"));
+
+        for design in ["classic", "micro"] {
+            let wm = builder().design(design).swatches(6).build();
+            let svg = wm.render_frame_svg(&input).unwrap();
+            for color in ["#C82828", "#1E3CC8"] {
+                assert_eq!(svg.matches(&format!(r#"fill="{}""#, color)).count(), 1, "{}", svg);
+                assert!(svg.contains(&format!(">{}</text>", color)), "{}", svg);
+            }
+            frame(&wm, &input, OutputFormat::Png);
+        }
+        assert_eq!(Watermarker::builder().swatches(20).build().options.swatches, palette::MAX_SWATCHES);
+        let options = WatermarkOptions { swatches: 20, ..Default::default() };
+        assert_eq!(Watermarker::builder().options(options).build().options.swatches, palette::MAX_SWATCHES);
+    }
+
+    #[test]
+    fn histogram_is_drawn_where_designs_show_one() {
+        let input = fixture(600, 360, |x, y| Rgb([x as u8, y as u8, 90]));
+        let svg = |design: &str, style| builder().design(design).histogram(style).build().render_frame_svg(&input).unwrap();
+
+        assert!(!svg("classic", None).contains("<path"));
+        for design in ["classic", "dark", "frosted", "minimal", "vintage", "micro"] {
+            assert!(svg(design, Some(HistogramStyle::Filled)).contains("<path"), "{}", design);
+        }
+        assert_eq!(svg("classic", Some(HistogramStyle::Channels)).matches("<path").count(), 3);
+        frame(&builder().histogram(Some(HistogramStyle::Line)).build(), &input, OutputFormat::Jpeg);
+    }
+
+    #[test]
+    fn qr_code_links_to_the_photo() {
+        let input = fixture(200, 120, |_, _| Rgb([120, 120, 120]));
+        let svg = |design: &str, link: &str| {
+            builder().design(design).qr_link(Some(link.into())).build().render_frame_svg(&input).unwrap()
+        };
+        for design in crate::designs::get_design_list() {
+            assert!(svg(&design.id, "https://instagram.com/jane").contains("crispEdges"), "{}", design.id);
+        }
+        // Per-image links need a file name, which bytes don't have
+        assert!(!svg("classic", "https://gallery.example/{name}").contains("crispEdges"));
+
+        let dir = TempDir::new("qr");
+        std::fs::write(dir.join("IMG_0042.png"), &input).unwrap();
+        let framed = |link: Option<String>| {
+            builder().qr_link(link).build().process_path(&dir.join("IMG_0042.png"), &dir.join("out.png")).unwrap();
+            std::fs::read(dir.join("out.png")).unwrap()
+        };
+        assert_ne!(framed(Some("https://gallery.example/{name}".into())), framed(None));
+    }
+
+    #[test]
+    fn invisible_mark_is_read_back() {
+        let input = fixture(600, 400, |x, y| Rgb([(x / 3) as u8, (y / 2) as u8, ((x * 7 + y * 13) % 50) as u8 + 80]));
+        let marking = || builder().invisible_mark(Some(4242));
+        let mark = |image_id| Some(invisible::Payload { photographer_id: 4242, image_id });
+
+        let dir = TempDir::new("mark");
+        std::fs::write(dir.join("IMG_0042.png"), &input).unwrap();
+        marking().build().process_path(&dir.join("IMG_0042.png"), &dir.join("out.jpg")).unwrap();
+        let framed = std::fs::read(dir.join("out.jpg")).unwrap();
+        assert_eq!(verify_watermark(&framed).unwrap(), mark(invisible::image_id("IMG_0042")));
+
+        // Streamed strips carry the same pattern; bytes have no name
+        let streaming = marking().memory_budget(2_500_000).build();
+        let streamed = streaming.process_bytes(&input, OutputFormat::Png).unwrap();
+        let expected = frame(&marking().build(), &input, OutputFormat::Png);
+        assert!(image::load_from_memory(&streamed).unwrap().as_bytes() == expected.as_bytes());
+        assert_eq!(verify_watermark(&streamed).unwrap(), mark(0));
+
+        let plain = builder().build().process_bytes(&input, OutputFormat::Png).unwrap();
+        assert_eq!(verify_watermark(&plain).unwrap(), None);
+    }
+
+    #[test]
+    fn one_decode_frames_every_design() {
+        let dir = TempDir::new("designs");
+        let input = dir.join("IMG_0007.png");
+        std::fs::write(&input, fixture(600, 400, |x, y| Rgb([(x / 3) as u8, (y / 2) as u8, 120]))).unwrap();
+
+        // In memory, and streamed from the borrowed photo
+        for budget in [None, Some(2_500_000)] {
+            let mut base = builder();
+            if let Some(budget) = budget {
+                base = base.memory_budget(budget);
+            }
+            let base = base.build();
+            let designs = ["classic", "dark", "frosted"].map(|id| base.with_design(id, None));
+            let outputs: Vec<(&Watermarker, PathBuf)> = designs
+                .iter()
+                .map(|wm| (wm, DesignOutput::Subfolder.path_for(&dir.join("out.png"), wm.design_id())))
+                .collect();
+            let results = process_path_designs(&input, &outputs).unwrap().results;
+            assert!(results.iter().all(Result::is_ok), "{:?}", results);
+
+            // Keeping the photo may tip the run over to streaming, so
+            // compare pixels rather than encoder output
+            let pixels = |path: &Path| image::open(path).unwrap().into_bytes();
+            for (wm, path) in &outputs {
+                wm.process_path(&input, &dir.join("single.png")).unwrap();
+                assert!(pixels(path) == pixels(&dir.join("single.png")), "{}", path.display());
+            }
+        }
+        assert!(process_path_designs(&dir.join("missing.png"), &[]).unwrap().results.is_empty());
+    }
+
+    #[test]
+    fn design_outputs_are_told_apart() {
+        let path = Path::new("out/IMG_1.jpg");
+        assert_eq!(DesignOutput::Suffix.path_for(path, "dark"), Path::new("out/IMG_1-dark.jpg"));
+        assert_eq!(DesignOutput::Subfolder.path_for(path, "dark"), Path::new("out/dark/IMG_1.jpg"));
+        assert_eq!(DesignOutput::from_name("Subfolder"), Some(DesignOutput::Subfolder));
+    }
+
+    #[test]
+    fn xmp_title_is_shown_before_the_date() {
+        let xmp = r#"<x:xmpmeta xmlns:x="adobe:ns:meta/"><rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">
+<rdf:Description xmlns:dc="http://purl.org/dc/elements/1.1/"><dc:title><rdf:Alt><rdf:li xml:lang="x-default">Evening tram</rdf:li></rdf:Alt></dc:title></rdf:Description>
+</rdf:RDF></x:xmpmeta>"#;
+        let segment = [b"http://ns.adobe.com/xap/1.0/\0".as_slice(), xmp.as_bytes()].concat();
+        let jpeg = sample_jpeg();
+        let mut input = jpeg[..2].to_vec();
+        input.extend_from_slice(&[0xFF, 0xE1]);
+        input.extend_from_slice(&(segment.len() as u16 + 2).to_be_bytes());
+        input.extend_from_slice(&segment);
+        input.extend_from_slice(&jpeg[2..]);
+
+        let svg = |show_title| builder().show_title(show_title).build().render_frame_svg(&input).unwrap();
+        assert!(!svg(false).contains("Evening tram"));
+        assert!(svg(true).contains("Evening tram"));
+    }
+
+    #[test]
+    fn sidecar_picks_the_design() {
+        let dir = TempDir::new("design-sidecar");
+        let photo = dir.join("IMG_0001.jpg");
+        std::fs::write(&photo, sample_jpeg()).unwrap();
+        let framed = |design: &str| {
+            builder().design(design).build().process_path(&photo, &dir.join("out.png")).unwrap();
+            std::fs::read(dir.join("out.png")).unwrap()
+        };
+        let dark = framed("dark");
+
+        std::fs::write(dir.join("IMG_0001.dcwm.json"), r#"{ "design": "dark" }"#).unwrap();
+        assert_eq!(framed("classic"), dark);
+        let wm = builder().build();
+        let framed_photo = process_path_designs(&photo, &[(&wm, dir.join("out.png"))]).unwrap();
+        assert_eq!(framed_photo.overrides, ["design"]);
+
+        std::fs::write(dir.join("IMG_0001.dcwm.json"), r#"{ "design": "neon" }"#).unwrap();
+        assert!(wm.process_path(&photo, &dir.join("out.png")).is_err());
+    }
+
+    #[test]
+    fn jpeg_output_carries_content_credentials() {
+        let input = fixture(600, 400, |_, _| Rgb([90, 120, 150]));
+        let signer = crate::c2pa::C2paSigner::from_pem(
+            include_str!("../tests/c2pa/test-cert.pem"),
+            include_str!("../tests/c2pa/test-key.pem"),
+        )
+        .unwrap();
+        let signing = || builder().photographer_name("Jane Doe").content_credentials(Some(signer.clone()));
+
+        for wm in [signing().build(), signing().memory_budget(2_500_000).build()] {
+            let framed = wm.process_bytes(&input, OutputFormat::Jpeg).unwrap();
+            let credentials = crate::c2pa::verify_jpeg(&framed).unwrap().unwrap();
+            assert_eq!(credentials.creator.as_deref(), Some("Jane Doe"));
+            assert_eq!(credentials.camera_model, None);
+            assert_eq!(credentials.actions, ["c2pa.edited"]);
+        }
+
+        // Only JPEG output is signed
+        frame(&signing().build(), &input, OutputFormat::Png);
+
+        // The signed file is held in memory, so it counts against the budget
+        let input = encode(RgbImage::new(400, 300), ImageFormat::Jpeg);
+        assert!(builder().memory_budget(1_000_000).build().process_bytes(&input, OutputFormat::Jpeg).is_ok());
+        let err = signing().memory_budget(1_000_000).build().process_bytes(&input, OutputFormat::Jpeg).unwrap_err();
+        assert!(err.contains("memory budget"), "{}", err);
+    }
+
+    #[test]
+    fn frosted_frame_follows_blur_and_dim() {
+        let input = fixture(200, 120, |_, _| Rgb([200, 160, 80]));
+        let corner = |frost: FrostOptions| {
+            let framed = frame(&builder().design("frosted").frost(frost).build(), &input, OutputFormat::Png).into_rgb8();
+            *framed.get_pixel(1, framed.height() - 2)
+        };
+
+        // Flat photo: the blurred copy is the same colour, darkened by `dim`
+        assert_eq!(corner(FrostOptions { blur: 0.3, dim: 0.0 }), Rgb([200, 160, 80]));
+        assert_eq!(corner(FrostOptions { blur: 0.0, dim: 0.5 }), Rgb([100, 80, 40]));
+        assert_eq!(corner(FrostOptions { blur: 0.3, dim: 2.0 }), Rgb([0, 0, 0]));
+    }
+}
@@ -0,0 +1,259 @@
+//! Filters that pick which photos of a batch get framed, e.g. only the
+//! client's picks. Photos are checked on their metadata before decoding;
+//! the ones left out are reported as skipped with the reason.
+
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+
+use crate::rules::{Orientation, PhotoFacts};
+use crate::watermark;
+
+/// Criteria left out let every photo through.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct BatchFilter {
+    /// Lowest XMP star rating
+    pub min_rating: Option<i32>,
+    /// XMP colour label, e.g. `Red`
+    pub label: Option<String>,
+    /// First and last capture date, both included: `2024-05-01`, or down
+    /// to the second as in `2024-05-01 18:30`
+    pub date_from: Option<String>,
+    pub date_to: Option<String>,
+    /// Case-insensitive parts of the EXIF make, model and lens
+    pub make: Option<String>,
+    pub model: Option<String>,
+    pub lens: Option<String>,
+    pub orientation: Option<Orientation>,
+    /// File size bounds in bytes
+    pub min_size: Option<u64>,
+    pub max_size: Option<u64>,
+}
+
+/// Digits of a date, year first: `2024.05.01 18:30:00` → `20240501183000`.
+fn date_digits(date: &str) -> String {
+    date.chars().filter(char::is_ascii_digit).collect()
+}
+
+/// A year, then two-digit month, day, hour, minute and second, with any
+/// separators: `2024`, `2024-05`, `2024-05-01 18:30`, ...
+fn is_valid_date(date: &str) -> bool {
+    if date.chars().any(char::is_alphabetic) {
+        return false;
+    }
+    let groups: Vec<&str> = date.split(|c: char| !c.is_ascii_digit()).filter(|g| !g.is_empty()).collect();
+    match groups.split_first() {
+        Some((year, rest)) => year.len() == 4 && rest.len() <= 5 && rest.iter().all(|g| g.len() == 2),
+        None => false,
+    }
+}
+
+fn orientation_name(orientation: Option<Orientation>) -> &'static str {
+    match orientation {
+        Some(Orientation::Portrait) => "portrait",
+        Some(Orientation::Landscape) => "landscape",
+        None => "square",
+    }
+}
+
+fn contains(text: &str, part: &str) -> bool {
+    text.to_lowercase().contains(&part.to_lowercase())
+}
+
+fn megabytes(bytes: u64) -> String {
+    format!("{:.1} MB", bytes as f64 / 1_000_000.0)
+}
+
+impl BatchFilter {
+    fn needs_metadata(&self) -> bool {
+        self.min_rating.is_some()
+            || self.label.is_some()
+            || self.date_from.is_some()
+            || self.date_to.is_some()
+            || self.make.is_some()
+            || self.model.is_some()
+            || self.lens.is_some()
+            || self.orientation.is_some()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        !self.needs_metadata() && self.min_size.is_none() && self.max_size.is_none()
+    }
+
+    /// Checks the dates, so a typo fails the run instead of skipping every
+    /// photo.
+    pub fn validate(&self) -> Result<(), String> {
+        for date in [&self.date_from, &self.date_to].into_iter().flatten() {
+            if !is_valid_date(date) {
+                return Err(format!("Invalid date '{}': expected e.g. 2024-05-01 or 2024-05-01 18:30", date));
+            }
+        }
+        Ok(())
+    }
+
+    /// Why the photo at `input_path` is left out, or `None` to frame it.
+    /// Only the file's header is read.
+    pub fn skip_reason(&self, input_path: &Path) -> Result<Option<String>, String> {
+        let size = fs::metadata(input_path)
+            .map_err(|e| format!("Failed to read {}: {}", input_path.display(), e))?
+            .len();
+        if let Some(reason) = self.size_reason(size) {
+            return Ok(Some(reason));
+        }
+        if !self.needs_metadata() {
+            return Ok(None);
+        }
+        let (exif, width, height) = watermark::read_photo_facts(input_path)?;
+        Ok(self.metadata_reason(&PhotoFacts { exif: &exif, width, height }))
+    }
+
+    fn size_reason(&self, size: u64) -> Option<String> {
+        match (self.min_size, self.max_size) {
+            (Some(min), _) if size < min => Some(format!("{}, under {}", megabytes(size), megabytes(min))),
+            (_, Some(max)) if size > max => Some(format!("{}, over {}", megabytes(size), megabytes(max))),
+            _ => None,
+        }
+    }
+
+    fn metadata_reason(&self, photo: &PhotoFacts) -> Option<String> {
+        let exif = photo.exif;
+        let xmp = &exif.xmp;
+        let rating = xmp.rating.unwrap_or(0);
+        if let Some(min) = self.min_rating.filter(|&min| rating < min) {
+            return Some(match rating {
+                -1 => "rejected".into(),
+                0 => "unrated".into(),
+                stars => format!("rated {}, under {}", stars, min),
+            });
+        }
+        if let Some(label) = self.label.as_ref().filter(|l| !xmp.label.eq_ignore_ascii_case(l)) {
+            return Some(match xmp.label.as_str() {
+                "" => format!("no {} label", label),
+                other => format!("label {}, not {}", other, label),
+            });
+        }
+
+        if self.date_from.is_some() || self.date_to.is_some() {
+            let taken = date_digits(&exif.date_time);
+            if taken.is_empty() {
+                return Some("no capture date".into());
+            }
+            // Compared to the bound's precision, so a day includes all of it
+            let at_precision = |bound: &str| {
+                let limit = date_digits(bound);
+                (taken[..limit.len().min(taken.len())].to_string(), limit)
+            };
+            if let Some(from) = &self.date_from {
+                let (taken, limit) = at_precision(from);
+                if taken < limit {
+                    return Some(format!("taken {}, before {}", exif.date_time, from));
+                }
+            }
+            if let Some(to) = &self.date_to {
+                let (taken, limit) = at_precision(to);
+                if taken > limit {
+                    return Some(format!("taken {}, after {}", exif.date_time, to));
+                }
+            }
+        }
+
+        let model = watermark::known_model(exif);
+        let fields = [
+            (&self.make, "make", exif.camera_make.as_str()),
+            (&self.model, "model", model),
+            (&self.lens, "lens", exif.lens_model.as_str()),
+        ];
+        for (wanted, what, actual) in fields {
+            if let Some(wanted) = wanted.as_ref().filter(|w| !contains(actual, w)) {
+                return Some(match actual {
+                    "" => format!("no {} recorded", what),
+                    actual => format!("{} {}, not {}", what, actual, wanted),
+                });
+            }
+        }
+
+        match (self.orientation, photo.orientation()) {
+            (Some(wanted), actual) if actual != Some(wanted) => {
+                Some(format!("{}, not {}", orientation_name(actual), orientation_name(Some(wanted))))
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+    use crate::watermark::ExifInfo;
+
+    fn photo() -> ExifInfo {
+        let mut exif = watermark::parse_exif(&[]);
+        exif.camera_make = "FUJIFILM".into();
+        exif.camera_model = "X-T5".into();
+        exif.lens_model = "XF23mmF1.4 R LM WR".into();
+        exif.date_time = "2024.05.01 18:30:00".into();
+        exif.xmp.rating = Some(4);
+        exif.xmp.label = "Red".into();
+        exif
+    }
+
+    fn reason(filter: &BatchFilter, exif: &ExifInfo) -> Option<String> {
+        filter.metadata_reason(&PhotoFacts { exif, width: 600, height: 400 })
+    }
+
+    #[test]
+    fn keeps_photos_matching_every_criterion() {
+        let filter = BatchFilter {
+            min_rating: Some(4),
+            label: Some("red".into()),
+            date_from: Some("2024-05-01".into()),
+            date_to: Some("2024-05-01 18:30".into()),
+            make: Some("fuji".into()),
+            lens: Some("23mm".into()),
+            orientation: Some(Orientation::Landscape),
+            ..Default::default()
+        };
+        filter.validate().unwrap();
+        assert_eq!(reason(&filter, &photo()), None);
+
+        let mut unrated = photo();
+        unrated.xmp.rating = None;
+        assert_eq!(reason(&filter, &unrated).as_deref(), Some("unrated"));
+        let mut later = photo();
+        later.date_time = "2024.05.01 18:31:00".into();
+        assert_eq!(
+            reason(&filter, &later).as_deref(),
+            Some("taken 2024.05.01 18:31:00, after 2024-05-01 18:30")
+        );
+        let portrait = BatchFilter { orientation: Some(Orientation::Portrait), ..Default::default() };
+        assert_eq!(reason(&portrait, &photo()).as_deref(), Some("landscape, not portrait"));
+        let nikon = BatchFilter { make: Some("nikon".into()), ..Default::default() };
+        assert_eq!(reason(&nikon, &photo()).as_deref(), Some("make FUJIFILM, not nikon"));
+    }
+
+    #[test]
+    fn checks_sizes_and_dates() {
+        let filter = BatchFilter { min_size: Some(1_000_000), max_size: Some(20_000_000), ..Default::default() };
+        assert_eq!(filter.size_reason(500_000).as_deref(), Some("0.5 MB, under 1.0 MB"));
+        assert_eq!(filter.size_reason(5_000_000), None);
+        assert!(filter.size_reason(25_000_000).is_some());
+
+        assert!(BatchFilter { date_from: Some("May 2024".into()), ..Default::default() }.validate().is_err());
+        assert!(BatchFilter { date_to: Some("2024-5-1".into()), ..Default::default() }.validate().is_err());
+    }
+
+    #[test]
+    fn sidecar_overrides_are_filtered_on() {
+        let dir = TempDir::new("filters");
+        let photo = dir.join("IMG_0001.jpg");
+        image::RgbImage::new(60, 40).save(&photo).unwrap();
+        fs::write(dir.join("IMG_0001.dcwm.json"), r#"{ "make": "Leica", "date": "1998.04.12" }"#).unwrap();
+
+        let leica = BatchFilter { make: Some("leica".into()), date_to: Some("1999".into()), ..Default::default() };
+        assert_eq!(leica.skip_reason(&photo).unwrap(), None);
+        let fuji = BatchFilter { make: Some("fuji".into()), ..Default::default() };
+        assert_eq!(fuji.skip_reason(&photo).unwrap().as_deref(), Some("make Leica, not fuji"));
+    }
+}
@@ -0,0 +1,203 @@
+use resvg::usvg::{self, fontdb};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+// ─── Bundled fonts ────────────────────────────────────
+
+pub const SANS_FAMILY: &str = "DejaVu Sans";
+pub const MONO_FAMILY: &str = "DejaVu Sans Mono";
+
+const BUNDLED_FONTS: &[&[u8]] = &[
+    include_bytes!("../assets/fonts/DejaVuSans.ttf"),
+    include_bytes!("../assets/fonts/DejaVuSans-Bold.ttf"),
+    include_bytes!("../assets/fonts/DejaVuSansMono.ttf"),
+    include_bytes!("../assets/fonts/DejaVuSansMono-Bold.ttf"),
+];
+
+/// Tried in order for glyphs the primary font lacks (CJK and emoji in
+/// photographer names). Whichever of these the system provides is used.
+const FALLBACK_FAMILIES: &[&str] = &[
+    "Noto Sans CJK SC",
+    "Noto Sans CJK JP",
+    "Noto Sans CJK KR",
+    "Source Han Sans SC",
+    "PingFang SC",
+    "Hiragino Sans",
+    "Apple SD Gothic Neo",
+    "Microsoft YaHei",
+    "Yu Gothic",
+    "Malgun Gothic",
+    "WenQuanYi Micro Hei",
+    "Noto Color Emoji",
+    "Apple Color Emoji",
+    "Segoe UI Emoji",
+    "Twemoji Mozilla",
+];
+
+// ─── Shared database ──────────────────────────────────
+
+struct SharedFonts {
+    font_dirs: Vec<PathBuf>,
+    system_fonts: bool,
+    db: Arc<fontdb::Database>,
+}
+
+static SHARED_FONTS: Mutex<Option<SharedFonts>> = Mutex::new(None);
+
+fn build_database(font_dirs: &[PathBuf], system_fonts: bool) -> fontdb::Database {
+    let mut db = fontdb::Database::new();
+    for data in BUNDLED_FONTS {
+        db.load_font_data(data.to_vec());
+    }
+    for dir in font_dirs {
+        db.load_fonts_dir(dir);
+    }
+    // System fonts come last so they only serve as glyph fallback; the
+    // bundled families always win for the text the designs ask for.
+    if system_fonts {
+        db.load_system_fonts();
+    }
+    db.set_sans_serif_family(SANS_FAMILY);
+    db.set_serif_family(SANS_FAMILY);
+    db.set_monospace_family(MONO_FAMILY);
+    db
+}
+
+/// Rebuilds the shared font database with extra font directories, such as the
+/// user's font folder. A no-op if nothing changed.
+pub fn configure(font_dirs: &[PathBuf], system_fonts: bool) {
+    let Ok(mut shared) = SHARED_FONTS.lock() else {
+        return;
+    };
+    if let Some(current) = shared.as_ref() {
+        if current.font_dirs == font_dirs && current.system_fonts == system_fonts {
+            return;
+        }
+    }
+    *shared = Some(SharedFonts {
+        font_dirs: font_dirs.to_vec(),
+        system_fonts,
+        db: Arc::new(build_database(font_dirs, system_fonts)),
+    });
+}
+
+/// The shared font database, built with the bundled fonts plus system
+/// fallback on first use.
+pub fn database() -> Arc<fontdb::Database> {
+    let mut shared = SHARED_FONTS.lock().unwrap_or_else(|e| e.into_inner());
+    shared
+        .get_or_insert_with(|| SharedFonts {
+            font_dirs: Vec::new(),
+            system_fonts: true,
+            db: Arc::new(build_database(&[], true)),
+        })
+        .db
+        .clone()
+}
+
+/// Configures the shared database and returns it.
+pub fn load(font_dirs: &[PathBuf], system_fonts: bool) -> Arc<fontdb::Database> {
+    configure(font_dirs, system_fonts);
+    database()
+}
+
+/// Font family names available for per-design selection.
+pub fn available_families() -> Vec<String> {
+    let db = database();
+    let mut families: Vec<String> = db
+        .faces()
+        .filter_map(|face| face.families.first().map(|(name, _)| name.clone()))
+        .collect();
+    families.sort();
+    families.dedup();
+    families
+}
+
+// ─── Family selection ─────────────────────────────────
+
+/// CSS `font-family` list for a design: the user's choice first (if any),
+/// then the bundled family the design was drawn with.
+pub fn family_for_design(design_id: &str, user_family: Option<&str>) -> String {
+    let (bundled, generic) = match design_id {
+        "vintage" => (MONO_FAMILY, "monospace"),
+        _ => (SANS_FAMILY, "sans-serif"),
+    };
+
+    // Quotes and markup would break out of the SVG attribute
+    let user_family = user_family
+        .map(|f| f.replace(['\'', '"', '<', '>', '&'], ""))
+        .filter(|f| !f.trim().is_empty());
+
+    match user_family {
+        Some(family) => format!("'{}', '{}', {}", family.trim(), bundled, generic),
+        None => format!("'{}', {}", bundled, generic),
+    }
+}
+
+fn has_glyph(db: &fontdb::Database, id: fontdb::ID, c: char) -> bool {
+    db.with_face_data(id, |data, index| {
+        ttf_parser::Face::parse(data, index)
+            .ok()
+            .and_then(|face| face.glyph_index(c))
+            .is_some()
+    })
+    .unwrap_or(false)
+}
+
+/// Font resolver that tries the CJK/emoji fallback chain before scanning the
+/// whole database for a glyph.
+pub fn resolver() -> usvg::FontResolver<'static> {
+    usvg::FontResolver {
+        select_font: usvg::FontResolver::default_font_selector(),
+        select_fallback: Box::new(|c, exclude_fonts, db| {
+            let base = exclude_fonts.first().and_then(|id| db.face(*id));
+            let weight = base.map(|f| f.weight).unwrap_or_default();
+
+            for name in FALLBACK_FAMILIES {
+                let query = fontdb::Query {
+                    families: &[fontdb::Family::Name(name)],
+                    weight,
+                    ..Default::default()
+                };
+                if let Some(id) = db.query(&query) {
+                    if !exclude_fonts.contains(&id) && has_glyph(db, id, c) {
+                        return Some(id);
+                    }
+                }
+            }
+
+            (usvg::FontResolver::default_fallback_selector())(c, exclude_fonts, db)
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bundled_families_are_loaded() {
+        let db = build_database(&[], false);
+        for family in [SANS_FAMILY, MONO_FAMILY] {
+            let query = fontdb::Query {
+                families: &[fontdb::Family::Name(family)],
+                ..Default::default()
+            };
+            assert!(db.query(&query).is_some(), "missing {}", family);
+        }
+    }
+
+    #[test]
+    fn user_family_is_sanitized_and_prepended() {
+        assert_eq!(family_for_design("classic", None), "'DejaVu Sans', sans-serif");
+        assert_eq!(
+            family_for_design("vintage", Some("Courier Prime")),
+            "'Courier Prime', 'DejaVu Sans Mono', monospace"
+        );
+        assert_eq!(
+            family_for_design("classic", Some("Evil\"/><script>")),
+            "'Evil/script', 'DejaVu Sans', sans-serif"
+        );
+        assert_eq!(family_for_design("classic", Some("  ")), "'DejaVu Sans', sans-serif");
+    }
+}
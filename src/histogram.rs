@@ -0,0 +1,171 @@
+//! Luminance and RGB histograms of the photo, drawn like a camera's review screen.
+
+use image::DynamicImage;
+use serde::Deserialize;
+
+use crate::palette;
+
+/// Longest side of the thumbnail the histogram is counted over.
+const SAMPLE_SIZE: u32 = 512;
+
+/// Points per curve; 256 bins are summed in groups to keep the SVG small.
+const POINTS: usize = 64;
+
+/// Per-channel curve colours, drawn half transparent so overlaps mix.
+const CHANNEL_COLORS: [&str; 3] = ["#E5403A", "#3DBA4E", "#3A7BE5"];
+
+/// How the histogram is drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HistogramStyle {
+    /// Filled luminance area
+    Filled,
+    /// Luminance outline
+    Line,
+    /// Red, green and blue areas over each other
+    Channels,
+}
+
+impl HistogramStyle {
+    /// Parses the names used by the CLI and the frontend.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "filled" => Some(HistogramStyle::Filled),
+            "line" => Some(HistogramStyle::Line),
+            "channels" => Some(HistogramStyle::Channels),
+            _ => None,
+        }
+    }
+}
+
+/// Pixel counts per 8-bit sRGB value.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Histogram {
+    pub style: HistogramStyle,
+    pub luminance: [u32; 256],
+    /// Red, green and blue
+    pub channels: [[u32; 256]; 3],
+}
+
+impl Histogram {
+    /// Counts a thumbnail of the photo in sRGB, as the camera would show it.
+    /// Luminance uses the Rec. 709 weights on the encoded values.
+    pub fn compute(
+        image: &DynamicImage,
+        icc_profile: Option<&[u8]>,
+        style: HistogramStyle,
+    ) -> Result<Self, String> {
+        let mut histogram = Histogram { style, luminance: [0; 256], channels: [[0; 256]; 3] };
+        for px in palette::srgb_sample(image, icc_profile, SAMPLE_SIZE)? {
+            for (channel, value) in histogram.channels.iter_mut().zip(px) {
+                channel[value as usize] += 1;
+            }
+            let [r, g, b] = px.map(|c| c as f64);
+            let luma = (0.2126 * r + 0.7152 * g + 0.0722 * b).round() as usize;
+            histogram.luminance[luma.min(255)] += 1;
+        }
+        Ok(histogram)
+    }
+
+    /// SVG paths filling the box at (`x`, `y`) of `width` by `height`.
+    /// `color` is used for the luminance styles and the baseline.
+    pub fn svg_element(&self, x: f64, y: f64, width: f64, height: f64, color: &str) -> String {
+        let curves: Vec<[f64; POINTS]> = match self.style {
+            HistogramStyle::Filled | HistogramStyle::Line => vec![downsample(&self.luminance)],
+            HistogramStyle::Channels => self.channels.iter().map(downsample).collect(),
+        };
+        // Clipped shadows and highlights would flatten everything else
+        let peak = curves
+            .iter()
+            .flat_map(|c| c[1..POINTS - 1].iter())
+            .fold(0.0f64, |max, &v| max.max(v));
+        if peak == 0.0 {
+            return String::new();
+        }
+
+        let outline = |curve: &[f64; POINTS]| {
+            curve
+                .iter()
+                .enumerate()
+                .map(|(i, &v)| {
+                    let px = x + width * i as f64 / (POINTS - 1) as f64;
+                    let py = y + height * (1.0 - (v / peak).min(1.0));
+                    format!("{:.1},{:.1}", px, py)
+                })
+                .collect::<Vec<_>>()
+                .join(" L")
+        };
+        let area = |curve: &[f64; POINTS]| {
+            format!("M{:.1},{:.1} L{} L{:.1},{:.1} Z", x, y + height, outline(curve), x + width, y + height)
+        };
+
+        let mut svg = match self.style {
+            HistogramStyle::Filled => {
+                format!(r##"<path d="{}" fill="{}" fill-opacity="0.8"/>"##, area(&curves[0]), color)
+            }
+            HistogramStyle::Line => format!(
+                r##"<path d="M{}" fill="none" stroke="{}" stroke-width="{:.1}" stroke-linejoin="round"/>"##,
+                outline(&curves[0]),
+                color,
+                (height / 40.0).max(1.0),
+            ),
+            HistogramStyle::Channels => curves
+                .iter()
+                .zip(CHANNEL_COLORS)
+                .map(|(curve, fill)| format!(r##"<path d="{}" fill="{}" fill-opacity="0.5"/>"##, area(curve), fill))
+                .collect(),
+        };
+        svg.push_str(&format!(
+            r##"<line x1="{:.1}" y1="{:.1}" x2="{:.1}" y2="{:.1}" stroke="{}" stroke-opacity="0.5" stroke-width="1"/>"##,
+            x,
+            y + height,
+            x + width,
+            y + height,
+            color,
+        ));
+        svg
+    }
+}
+
+fn downsample(bins: &[u32; 256]) -> [f64; POINTS] {
+    let group = bins.len() / POINTS;
+    std::array::from_fn(|i| bins[i * group..(i + 1) * group].iter().map(|&n| n as f64).sum())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgb, RgbImage};
+
+    #[test]
+    fn counts_follow_the_pixels() {
+        let img = RgbImage::from_fn(100, 50, |x, _| if x < 25 { Rgb([255, 0, 0]) } else { Rgb([128, 128, 128]) });
+        let histogram = Histogram::compute(&DynamicImage::ImageRgb8(img), None, HistogramStyle::Filled).unwrap();
+        let total: u32 = histogram.luminance.iter().sum();
+        assert_eq!(histogram.luminance[128], total * 3 / 4);
+        assert_eq!(histogram.luminance[54], total / 4);
+        assert_eq!(histogram.channels[0][255], total / 4);
+        assert_eq!(histogram.channels[1][0], total / 4);
+    }
+
+    #[test]
+    fn styles_draw_their_paths() {
+        let img = RgbImage::from_fn(64, 64, |x, y| Rgb([(x * 4) as u8, (y * 4) as u8, 100]));
+        let image = DynamicImage::ImageRgb8(img);
+        let draw = |style| {
+            Histogram::compute(&image, None, style).unwrap().svg_element(10.0, 20.0, 160.0, 100.0, "#333333")
+        };
+
+        let filled = draw(HistogramStyle::Filled);
+        assert_eq!(filled.matches("<path").count(), 1);
+        assert!(filled.contains(r##"fill="#333333""##) && filled.contains(" Z\""));
+
+        let line = draw(HistogramStyle::Line);
+        assert!(line.contains(r#"fill="none""#) && !line.contains(" Z\""));
+
+        let channels = draw(HistogramStyle::Channels);
+        for color in CHANNEL_COLORS {
+            assert!(channels.contains(color), "{}", channels);
+        }
+    }
+}
@@ -1,9 +1,38 @@
+pub mod batch;
+pub mod brands;
+pub mod c2pa;
+pub mod camera_names;
+pub mod color;
+pub mod contact_sheet;
+#[cfg(feature = "desktop")]
 mod commands;
-mod designs;
-mod watermark;
+pub mod designs;
+pub mod engine;
+pub mod filters;
+pub mod fonts;
+pub mod histogram;
+pub mod invisible;
+pub mod overrides;
+pub mod palette;
+pub mod qr;
+pub mod rights;
+pub mod rules;
+pub mod sidecar;
+mod stream;
+#[cfg(test)]
+mod test_support;
+pub mod watermark;
+pub mod xmp;
 
+pub use engine::{
+    process_path_designs, verify_watermark, DesignOutput, FramedPhoto, FrostOptions, LogoSource, OutputFormat,
+    WatermarkOptions, Watermarker, WatermarkerBuilder,
+};
+
+#[cfg(feature = "desktop")]
 use commands::AppState;
 
+#[cfg(feature = "desktop")]
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
@@ -29,7 +58,13 @@ pub fn run() {
             commands::get_designs,
             commands::set_design,
             commands::get_current_design,
+            commands::get_font_families,
+            commands::set_design_font,
             commands::start_processing,
+            commands::get_design_rules,
+            commands::save_design_rules,
+            commands::verify_watermark,
+            commands::verify_signatures,
             commands::check_for_updates,
         ])
         .run(tauri::generate_context!())
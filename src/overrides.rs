@@ -0,0 +1,254 @@
+//! Per-photo overrides from a sidecar next to the photo: `IMG_0001.dcwm.json`,
+//! or `dcwm:` fields in `IMG_0001.xmp` (or `IMG_0001.jpg.xmp`). They are
+//! merged over the run's settings and the photo's EXIF; the JSON sidecar
+//! wins over the XMP one.
+//!
+//! ```json
+//! { "camera": "Leica M6", "hideExposure": true, "caption": "Lisbon", "design": "vintage" }
+//! ```
+
+use serde::Deserialize;
+use std::borrow::Cow;
+use std::fs;
+use std::path::Path;
+
+use crate::designs;
+use crate::engine::Watermarker;
+use crate::watermark::ExifInfo;
+use crate::xmp::{self, Xmp, XmpInfo};
+
+/// Namespace of the `dcwm:` fields in XMP sidecars.
+pub const XMP_NAMESPACE: &str = "https://github.com/gvoze32/digicamwm/ns/1.0/";
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct Overrides {
+    /// Camera name printed instead of the one from EXIF
+    pub camera: Option<String>,
+    /// Camera make, which also picks the logo
+    pub make: Option<String>,
+    /// Printed instead of the capture date
+    pub date: Option<String>,
+    /// Leave the exposure info out
+    pub hide_exposure: Option<bool>,
+    /// Shown before the date
+    pub caption: Option<String>,
+    pub photographer: Option<String>,
+    /// Design to frame the photo in
+    pub design: Option<String>,
+}
+
+/// What the sidecars of a photo hold, read once per photo: its overrides
+/// and the XMP metadata laid over what the photo embeds.
+#[derive(Debug, Clone, Default)]
+pub struct Sidecars {
+    pub overrides: Overrides,
+    /// Fields of the XMP sidecar, when there is one that parses
+    pub xmp: Option<XmpInfo>,
+}
+
+impl Sidecars {
+    /// Reads the sidecars of `input_path`. An XMP sidecar that doesn't parse
+    /// is logged and skipped, like a missing one; only invalid `dcwm:`
+    /// fields or a bad JSON sidecar fail.
+    pub fn load(input_path: &Path) -> Result<Self, String> {
+        let xmp = xmp::read_sidecar(input_path).unwrap_or_else(|e| {
+            log::warn!("Ignoring XMP sidecar: {}", e);
+            None
+        });
+        let mut overrides = match &xmp {
+            Some(xmp) => Overrides::from_xmp_fields(xmp).map_err(|e| format!("{}: {}", input_path.display(), e))?,
+            None => Overrides::default(),
+        };
+        let path = input_path.with_extension("dcwm.json");
+        if path.exists() {
+            let json = fs::read_to_string(&path)
+                .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+            let from_json = Overrides::from_json(&json).map_err(|e| format!("{}: {}", path.display(), e))?;
+            overrides = overrides.merge(from_json);
+        }
+        Ok(Sidecars { overrides, xmp: xmp.map(|xmp| xmp.info()) })
+    }
+
+    /// Lays the sidecar's XMP over the embedded one in `exif`.
+    pub(crate) fn merge_xmp_into(&self, exif: &mut ExifInfo) {
+        if let Some(xmp) = &self.xmp {
+            exif.xmp.merge(xmp.clone());
+        }
+    }
+
+    /// The sidecar's XMP and the overridden make, camera and date put into
+    /// `exif`, so filters and rules see the photo as the frame prints it.
+    pub(crate) fn apply_to(&self, exif: &mut ExifInfo) {
+        self.merge_xmp_into(exif);
+        self.overrides.apply_to(exif);
+    }
+}
+
+impl Overrides {
+    /// Overrides from the sidecars of `input_path`; empty when it has none.
+    /// See [`Sidecars::load`].
+    pub fn load(input_path: &Path) -> Result<Self, String> {
+        Sidecars::load(input_path).map(|sidecars| sidecars.overrides)
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        let overrides: Overrides =
+            serde_json::from_str(json).map_err(|e| format!("Invalid overrides: {}", e))?;
+        overrides.check()
+    }
+
+    /// Reads the `dcwm:` fields; other XMP properties are ignored.
+    pub fn from_xmp(xml: &str) -> Result<Self, String> {
+        Self::from_xmp_fields(&Xmp::parse(xml)?)
+    }
+
+    fn from_xmp_fields(xmp: &Xmp) -> Result<Self, String> {
+        let text = |name: &str| xmp.get(XMP_NAMESPACE, name).map(str::to_string);
+        let hide_exposure = match xmp.get(XMP_NAMESPACE, "hideExposure") {
+            Some(value) if value.eq_ignore_ascii_case("true") => Some(true),
+            Some(value) if value.eq_ignore_ascii_case("false") => Some(false),
+            Some(value) => return Err(format!("Invalid dcwm:hideExposure '{}': expected True or False", value)),
+            None => None,
+        };
+        Overrides {
+            camera: text("camera"),
+            make: text("make"),
+            date: text("date"),
+            hide_exposure,
+            caption: text("caption"),
+            photographer: text("photographer"),
+            design: text("design"),
+        }
+        .check()
+    }
+
+    fn check(self) -> Result<Self, String> {
+        match &self.design {
+            Some(id) if !designs::get_design_list().iter().any(|d| &d.id == id) => {
+                Err(format!("Unknown design '{}' in overrides", id))
+            }
+            _ => Ok(self),
+        }
+    }
+
+    /// These overrides with the fields `other` sets replaced.
+    fn merge(self, other: Overrides) -> Overrides {
+        Overrides {
+            camera: other.camera.or(self.camera),
+            make: other.make.or(self.make),
+            date: other.date.or(self.date),
+            hide_exposure: other.hide_exposure.or(self.hide_exposure),
+            caption: other.caption.or(self.caption),
+            photographer: other.photographer.or(self.photographer),
+            design: other.design.or(self.design),
+        }
+    }
+
+    /// Names of the fields that are set, as in the JSON sidecar, for
+    /// reporting what a run changed.
+    pub fn applied(&self) -> Vec<&'static str> {
+        [
+            ("camera", self.camera.is_some()),
+            ("make", self.make.is_some()),
+            ("date", self.date.is_some()),
+            ("hideExposure", self.hide_exposure.is_some()),
+            ("caption", self.caption.is_some()),
+            ("photographer", self.photographer.is_some()),
+            ("design", self.design.is_some()),
+        ]
+        .into_iter()
+        .filter_map(|(name, set)| set.then_some(name))
+        .collect()
+    }
+
+    /// Puts the overridden make, camera and date into `exif`, so filters
+    /// and rules see the photo as the frame prints it.
+    pub(crate) fn apply_to(&self, exif: &mut ExifInfo) {
+        let fields = [
+            (&self.make, &mut exif.camera_make),
+            (&self.camera, &mut exif.camera_model),
+            (&self.date, &mut exif.date_time),
+        ];
+        for (value, field) in fields {
+            if let Some(value) = value {
+                field.clone_from(value);
+            }
+        }
+    }
+
+    /// `wm` with the design and photographer overridden. A new design keeps
+    /// the run's font choice.
+    pub(crate) fn watermarker<'a>(&self, wm: &'a Watermarker) -> Cow<'a, Watermarker> {
+        if self.design.is_none() && self.photographer.is_none() {
+            return Cow::Borrowed(wm);
+        }
+        let mut wm = match &self.design {
+            Some(id) => wm.with_design(id, wm.options.font_family.clone()),
+            None => wm.clone(),
+        };
+        if let Some(name) = &self.photographer {
+            wm.options.photographer_name = name.clone();
+        }
+        Cow::Owned(wm)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+
+    #[test]
+    fn json_sidecar_wins_over_xmp() {
+        let dir = TempDir::new("overrides");
+        let photo = dir.join("IMG_0001.jpg");
+        fs::write(
+            dir.join("IMG_0001.xmp"),
+            r#"<x:xmpmeta xmlns:x="adobe:ns:meta/">
+  <rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">
+    <rdf:Description rdf:about="" xmlns:dcwm="https://github.com/gvoze32/digicamwm/ns/1.0/"
+        dcwm:camera="Leica M6" dcwm:hideExposure="True">
+      <dcwm:caption>Lisbon &amp; the river</dcwm:caption>
+      <dcwm:design>dark</dcwm:design>
+    </rdf:Description>
+  </rdf:RDF>
+</x:xmpmeta>"#,
+        )
+        .unwrap();
+        fs::write(dir.join("IMG_0001.dcwm.json"), r#"{ "design": "vintage", "date": "Spring 1998" }"#).unwrap();
+
+        let overrides = Overrides::load(&photo).unwrap();
+        assert_eq!(overrides.camera.as_deref(), Some("Leica M6"));
+        assert_eq!(overrides.hide_exposure, Some(true));
+        assert_eq!(overrides.caption.as_deref(), Some("Lisbon & the river"));
+        assert_eq!(overrides.design.as_deref(), Some("vintage"));
+        assert_eq!(overrides.applied(), ["camera", "date", "hideExposure", "caption", "design"]);
+
+        assert_eq!(Overrides::load(&dir.join("IMG_0002.jpg")).unwrap(), Overrides::default());
+
+        // A broken XMP sidecar is skipped, but still not a bad dcwm: field
+        fs::write(dir.join("IMG_0003.xmp"), "<x:xmpmeta").unwrap();
+        let sidecars = Sidecars::load(&dir.join("IMG_0003.jpg")).unwrap();
+        assert_eq!(sidecars.overrides, Overrides::default());
+        assert_eq!(sidecars.xmp, None);
+        fs::write(
+            dir.join("IMG_0004.xmp"),
+            r#"<rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">
+  <rdf:Description xmlns:dcwm="https://github.com/gvoze32/digicamwm/ns/1.0/" dcwm:design="neon"/>
+</rdf:RDF>"#,
+        )
+        .unwrap();
+        assert!(Sidecars::load(&dir.join("IMG_0004.jpg")).is_err());
+    }
+
+    #[test]
+    fn rejects_bad_overrides() {
+        assert!(Overrides::from_json(r#"{ "design": "neon" }"#).is_err());
+        assert!(Overrides::from_json(r#"{ "lens": "50mm" }"#).is_err());
+        let xmp = r#"<rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">
+  <rdf:Description xmlns:dcwm="https://github.com/gvoze32/digicamwm/ns/1.0/" dcwm:hideExposure="maybe"/>
+</rdf:RDF>"#;
+        assert!(Overrides::from_xmp(xmp).is_err());
+    }
+}
@@ -0,0 +1,251 @@
+//! Adaptive frame colours sampled from the photo.
+
+use image::DynamicImage;
+use serde::Deserialize;
+
+use crate::color::{ColorPipeline, ColorSpace};
+use crate::designs::{self, FramePalette};
+
+/// Minimum WCAG contrast between frame text and background (AA for body text).
+pub const MIN_CONTRAST: f64 = 4.5;
+
+/// Most swatches a frame shows.
+pub const MAX_SWATCHES: usize = 8;
+
+/// Longest side of the thumbnail the photo's colours are sampled from.
+const SAMPLE_SIZE: u32 = 64;
+
+/// Where the frame colours come from.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FrameColor {
+    /// The design's own colours
+    #[default]
+    Design,
+    /// Background tinted with the photo's dominant colour, text picked for contrast
+    Adaptive,
+}
+
+impl FrameColor {
+    /// Parses the names used by the CLI and the frontend.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "design" => Some(FrameColor::Design),
+            "adaptive" => Some(FrameColor::Adaptive),
+            _ => None,
+        }
+    }
+}
+
+/// WCAG contrast ratio between two sRGB colours, from 1.0 to 21.0.
+pub fn contrast_ratio(a: [u8; 3], b: [u8; 3]) -> f64 {
+    let (la, lb) = (designs::relative_luminance(a), designs::relative_luminance(b));
+    (la.max(lb) + 0.05) / (la.min(lb) + 0.05)
+}
+
+/// Opaque pixels of the photo scaled to fit `size`, in sRGB like the frame
+/// colours. Smaller photos are used as they are.
+pub(crate) fn srgb_sample(
+    image: &DynamicImage,
+    icc_profile: Option<&[u8]>,
+    size: u32,
+) -> Result<Vec<[u8; 3]>, String> {
+    let mut thumb = if image.width() > size || image.height() > size {
+        image.thumbnail(size, size).into_rgba8()
+    } else {
+        image.to_rgba8()
+    };
+    let width = thumb.width();
+    ColorPipeline::new(ColorSpace::Srgb, icc_profile)?.convert_photo(thumb.as_mut(), width)?;
+    Ok(thumb
+        .pixels()
+        .filter(|px| px[3] >= 128)
+        .map(|px| [px[0], px[1], px[2]])
+        .collect())
+}
+
+/// Dominant sRGB colour of the photo: the mean of the fullest bucket of a
+/// coarse colour histogram over a thumbnail. Transparent pixels are skipped;
+/// fully transparent photos give white.
+pub fn dominant_color(image: &DynamicImage, icc_profile: Option<&[u8]>) -> Result<[u8; 3], String> {
+    // 4 bits per channel
+    let mut buckets = vec![(0u64, [0u64; 3]); 1 << 12];
+    for px in srgb_sample(image, icc_profile, SAMPLE_SIZE)? {
+        let [r, g, b] = px.map(|c| c as usize >> 4);
+        let (count, sum) = &mut buckets[r << 8 | g << 4 | b];
+        *count += 1;
+        for (total, c) in sum.iter_mut().zip(px) {
+            *total += c as u64;
+        }
+    }
+
+    let (count, sum) = buckets.iter().max_by_key(|(count, _)| *count).unwrap();
+    if *count == 0 {
+        return Ok([255; 3]);
+    }
+    Ok(sum.map(|total| (total / count) as u8))
+}
+
+/// Up to `count` representative sRGB colours of the photo by median cut,
+/// most common first. Photos with fewer distinct colours give fewer.
+pub fn palette_colors(
+    image: &DynamicImage,
+    icc_profile: Option<&[u8]>,
+    count: usize,
+) -> Result<Vec<[u8; 3]>, String> {
+    if count == 0 {
+        return Ok(Vec::new());
+    }
+    let pixels = srgb_sample(image, icc_profile, SAMPLE_SIZE)?;
+    if pixels.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    // Channel with the widest spread in a box, and that spread
+    let widest = |pixels: &[[u8; 3]]| {
+        (0..3)
+            .map(|c| {
+                let (min, max) = pixels.iter().fold((255, 0), |(min, max), px| {
+                    (px[c].min(min), px[c].max(max))
+                });
+                (c, max - min)
+            })
+            .max_by_key(|&(_, range)| range)
+            .unwrap()
+    };
+
+    // Keep halving the box with the widest spread at its median
+    let mut boxes = vec![pixels];
+    while boxes.len() < count {
+        let Some((index, channel)) = boxes
+            .iter()
+            .enumerate()
+            .map(|(i, b)| (i, widest(b)))
+            .filter(|(_, (_, range))| *range > 0)
+            .max_by_key(|(_, (_, range))| *range)
+            .map(|(i, (channel, _))| (i, channel))
+        else {
+            break;
+        };
+        let mut pixels = boxes.swap_remove(index);
+        pixels.sort_unstable_by_key(|px| px[channel]);
+        // Split where the value changes, so one colour never lands in two boxes
+        let median = pixels[pixels.len() / 2][channel];
+        let split = match pixels.partition_point(|px| px[channel] < median) {
+            0 => pixels.partition_point(|px| px[channel] <= median),
+            split => split,
+        };
+        let upper = pixels.split_off(split);
+        boxes.push(pixels);
+        boxes.push(upper);
+    }
+
+    boxes.sort_by_key(|b| std::cmp::Reverse(b.len()));
+    Ok(boxes
+        .iter()
+        .map(|b| {
+            let mut sum = [0u64; 3];
+            for px in b {
+                for (total, c) in sum.iter_mut().zip(px) {
+                    *total += *c as u64;
+                }
+            }
+            sum.map(|total| (total / b.len() as u64) as u8)
+        })
+        .collect())
+}
+
+/// Palette for a frame tinted with `background`. Text is black or white,
+/// whichever contrasts more; the quieter text roles are blended towards the
+/// background only as far as [`MIN_CONTRAST`] allows.
+pub fn adaptive_palette(background: [u8; 3]) -> FramePalette {
+    let text = if contrast_ratio([0; 3], background) >= contrast_ratio([255; 3], background) {
+        [0; 3]
+    } else {
+        [255; 3]
+    };
+    FramePalette {
+        background: hex(background),
+        background_end: hex(background),
+        text: hex(text),
+        secondary_text: hex(soften(text, background, 0.3)),
+        muted_text: hex(soften(text, background, 0.45)),
+        rule: hex(mix(background, text, 0.25)),
+    }
+}
+
+pub(crate) fn hex([r, g, b]: [u8; 3]) -> String {
+    format!("#{:02X}{:02X}{:02X}", r, g, b)
+}
+
+/// Parses `#RRGGBB`.
+pub(crate) fn parse_hex(hex: &str) -> Option<[u8; 3]> {
+    let digits = hex.strip_prefix('#').filter(|d| d.len() == 6 && d.is_ascii())?;
+    let mut rgb = [0; 3];
+    for (i, c) in rgb.iter_mut().enumerate() {
+        *c = u8::from_str_radix(&digits[i * 2..i * 2 + 2], 16).ok()?;
+    }
+    Some(rgb)
+}
+
+/// Moves `from` towards `to` by `t` (0.0–1.0).
+fn mix(from: [u8; 3], to: [u8; 3], t: f64) -> [u8; 3] {
+    std::array::from_fn(|i| (from[i] as f64 + (to[i] as f64 - from[i] as f64) * t).round() as u8)
+}
+
+/// Blends text towards the background by up to `amount`, backing off in 5%
+/// steps until it keeps enough contrast. Black or white text always does.
+fn soften(text: [u8; 3], background: [u8; 3], amount: f64) -> [u8; 3] {
+    let steps = (amount * 20.0).round() as u32;
+    (0..=steps)
+        .rev()
+        .map(|step| mix(text, background, step as f64 / 20.0))
+        .find(|&color| contrast_ratio(color, background) >= MIN_CONTRAST)
+        .unwrap_or(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgb, RgbImage};
+
+    #[test]
+    fn dominant_color_picks_the_largest_area() {
+        let img = RgbImage::from_fn(100, 100, |x, _| {
+            if x < 70 { Rgb([30, 90, 160]) } else { Rgb([240, 200, 20]) }
+        });
+        let color = dominant_color(&DynamicImage::ImageRgb8(img), None).unwrap();
+        assert!(color.iter().zip([30, 90, 160]).all(|(&a, b)| a.abs_diff(b) <= 2), "{:?}", color);
+    }
+
+    #[test]
+    fn adaptive_text_keeps_wcag_contrast() {
+        for background in [[0, 0, 0], [255, 255, 255], [119, 119, 119], [30, 90, 160], [240, 200, 20]] {
+            let palette = adaptive_palette(background);
+            assert_eq!(palette.background, hex(background));
+            for text in [&palette.text, &palette.secondary_text, &palette.muted_text] {
+                let ratio = contrast_ratio(parse_hex(text).unwrap(), background);
+                assert!(ratio >= MIN_CONTRAST, "{} on {:?}: {:.2}", text, background, ratio);
+            }
+        }
+        assert_eq!(adaptive_palette([20, 20, 40]).text, "#FFFFFF");
+        assert_eq!(adaptive_palette([240, 230, 200]).text, "#000000");
+    }
+
+    #[test]
+    fn palette_colors_finds_each_band() {
+        let bands = [[200, 40, 40], [40, 160, 60], [30, 60, 200], [240, 220, 120], [20, 20, 20]];
+        let img = RgbImage::from_fn(100, 100, |x, _| Rgb(bands[(x / 20) as usize]));
+        let colors = palette_colors(&DynamicImage::ImageRgb8(img), None, 8).unwrap();
+        // Resampling blends a few edge pixels; the bands still come first
+        assert!(colors.len() >= 5, "{:?}", colors);
+        for band in bands {
+            assert!(
+                colors[..5].iter().any(|c| c.iter().zip(band).all(|(&a, b)| a.abs_diff(b) <= 2)),
+                "{:?} missing from {:?}",
+                band,
+                colors
+            );
+        }
+    }
+}
@@ -0,0 +1,119 @@
+//! QR codes linking a framed photo back to the photographer.
+
+use qrcodegen::QrCodeEcc;
+
+/// Light modules around the code, as the spec asks.
+const QUIET_ZONE: usize = 4;
+
+/// Square matrix of dark and light modules.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QrCode {
+    /// Modules per side, without the quiet zone
+    pub size: usize,
+    /// Row by row, `true` for dark
+    pub modules: Vec<bool>,
+}
+
+impl QrCode {
+    /// Encodes `text` with medium error correction, which survives print
+    /// wear without making the code much denser.
+    pub fn encode(text: &str) -> Result<Self, String> {
+        let code = qrcodegen::QrCode::encode_text(text, QrCodeEcc::Medium)
+            .map_err(|_| format!("Link too long for a QR code: {}", text))?;
+        let size = code.size() as usize;
+        let modules = (0..size * size)
+            .map(|i| code.get_module((i % size) as i32, (i / size) as i32))
+            .collect();
+        Ok(Self { size, modules })
+    }
+
+    pub fn is_dark(&self, x: usize, y: usize) -> bool {
+        self.modules[y * self.size + x]
+    }
+
+    /// SVG rects drawing the code, quiet zone included, as a square of
+    /// `size` at (`x`, `y`). Runs of dark modules in a row share one rect.
+    pub fn svg_element(&self, x: f64, y: f64, size: f64, dark: &str, light: &str) -> String {
+        let module = size / (self.size + QUIET_ZONE * 2) as f64;
+        let mut svg = format!(
+            r##"<g shape-rendering="crispEdges"><rect x="{:.2}" y="{:.2}" width="{:.2}" height="{:.2}" fill="{}"/><g fill="{}">"##,
+            x, y, size, size, light, dark,
+        );
+        for row in 0..self.size {
+            let mut col = 0;
+            while col < self.size {
+                if !self.is_dark(col, row) {
+                    col += 1;
+                    continue;
+                }
+                let start = col;
+                while col < self.size && self.is_dark(col, row) {
+                    col += 1;
+                }
+                svg.push_str(&format!(
+                    r##"<rect x="{:.2}" y="{:.2}" width="{:.2}" height="{:.2}"/>"##,
+                    x + (start + QUIET_ZONE) as f64 * module,
+                    y + (row + QUIET_ZONE) as f64 * module,
+                    (col - start) as f64 * module,
+                    module,
+                ));
+            }
+        }
+        svg.push_str("</g></g>");
+        svg
+    }
+}
+
+/// Fills `{name}` in a link template with the photo's file name (without
+/// extension), percent-encoded. `None` when the template needs a name and
+/// the photo has none, e.g. when framing bytes rather than a file.
+pub fn resolve_link(template: &str, name: Option<&str>) -> Option<String> {
+    if !template.contains("{name}") {
+        return Some(template.to_string());
+    }
+    name.map(|name| template.replace("{name}", &percent_encode(name)))
+}
+
+fn percent_encode(text: &str) -> String {
+    text.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn links_fill_in_the_file_name() {
+        assert_eq!(
+            resolve_link("https://gallery.example/{name}", Some("IMG 0042")).as_deref(),
+            Some("https://gallery.example/IMG%200042")
+        );
+        assert_eq!(resolve_link("https://gallery.example/{name}", None), None);
+        assert_eq!(
+            resolve_link("https://instagram.com/jane", None).as_deref(),
+            Some("https://instagram.com/jane")
+        );
+    }
+
+    #[test]
+    fn code_keeps_finder_patterns() {
+        let code = QrCode::encode("https://gallery.example/IMG_0042").unwrap();
+        assert_eq!(code.size, 29);
+        // Finder pattern corners and the light ring inside them
+        for (x, y) in [(0, 0), (6, 6), (code.size - 1, 0), (0, code.size - 1)] {
+            assert!(code.is_dark(x, y), "({}, {})", x, y);
+        }
+        assert!(!code.is_dark(1, 1));
+
+        let svg = code.svg_element(0.0, 0.0, 74.0, "#111111", "#FFFFFF");
+        // 29 modules plus the quiet zone on both sides, 2px each
+        assert!(svg.starts_with(r##"<g shape-rendering="crispEdges"><rect x="0.00" y="0.00" width="74.00""##));
+        assert!(svg.contains(r##"<rect x="8.00" y="8.00" width="14.00" height="2.00"/>"##), "{}", svg);
+        assert!(QrCode::encode(&"x".repeat(4000)).is_err());
+    }
+}
@@ -0,0 +1,494 @@
+//! Creator and copyright metadata written into framed output, so DAMs and
+//! platforms that read metadata attribute the photo without looking at the
+//! frame. The photographer goes into EXIF Artist and XMP `dc:creator`; the
+//! notice into EXIF Copyright and `dc:rights`; usage terms, contact details
+//! and the credit line into their XMP (IPTC Core) properties.
+//!
+//! The fields are added as the file is encoded: as APP1 segments in JPEG,
+//! `eXIf` and `iTXt` chunks in PNG and `EXIF` and `XMP ` chunks in WebP.
+
+use serde::Deserialize;
+use std::io::{self, Seek, SeekFrom, Write};
+
+use crate::designs::escape;
+use crate::engine::OutputFormat;
+use crate::xmp;
+
+/// EXIF tags and the TIFF ASCII type.
+const TAG_ARTIST: u16 = 0x013B;
+const TAG_COPYRIGHT: u16 = 0x8298;
+const TYPE_ASCII: u16 = 2;
+
+const JPEG_APP1: u8 = 0xE1;
+const JPEG_EXIF_ID: &[u8] = b"Exif\0\0";
+/// Largest APP1 payload: the segment length field counts itself.
+const MAX_SEGMENT_PAYLOAD: usize = 65533;
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+/// VP8X feature flags.
+const WEBP_ALPHA: u8 = 0x10;
+const WEBP_EXIF: u8 = 0x08;
+const WEBP_XMP: u8 = 0x04;
+
+/// Rights stated along with the photographer, who is the creator. Empty
+/// fields are left out.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct Rights {
+    /// Copyright notice, e.g. `© 2024 Jane Doe`
+    pub copyright: Option<String>,
+    /// How the photo may be used, e.g. `Editorial use only`
+    pub usage_terms: Option<String>,
+    pub contact_email: Option<String>,
+    pub contact_url: Option<String>,
+    pub contact_phone: Option<String>,
+    /// Credit line to print with the photo
+    pub credit: Option<String>,
+}
+
+/// `field` unless it's missing or blank.
+fn text(field: &Option<String>) -> Option<&str> {
+    field.as_deref().map(str::trim).filter(|t| !t.is_empty())
+}
+
+/// The photo's creator and rights, ready to be written.
+pub(crate) struct Attribution<'a> {
+    creator: Option<&'a str>,
+    rights: &'a Rights,
+}
+
+impl<'a> Attribution<'a> {
+    pub(crate) fn new(photographer_name: &'a str, rights: &'a Rights) -> Self {
+        let creator = Some(photographer_name.trim()).filter(|c| !c.is_empty());
+        Self { creator, rights }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        let r = self.rights;
+        self.creator.is_none()
+            && [&r.copyright, &r.usage_terms, &r.contact_email, &r.contact_url, &r.contact_phone, &r.credit]
+                .into_iter()
+                .all(|field| text(field).is_none())
+    }
+
+    /// Whether the metadata goes into `format`; TIFF output is left as it is.
+    pub(crate) fn applies_to(&self, format: OutputFormat) -> bool {
+        if self.is_empty() {
+            return false;
+        }
+        if format == OutputFormat::Tiff {
+            log::warn!("Creator and copyright metadata aren't written to TIFF output");
+            return false;
+        }
+        true
+    }
+
+    /// `inner` wrapped to add the metadata to the JPEG, PNG or WebP file an
+    /// encoder writes through it.
+    pub(crate) fn writer<W: Write>(&'a self, format: OutputFormat, inner: W) -> MetadataWriter<'a, W> {
+        MetadataWriter { attribution: self, format, inner, head: Some(Vec::new()), written: 0 }
+    }
+
+    /// Big-endian TIFF structure with IFD0 holding Artist and Copyright, as
+    /// EXIF is stored in all three formats; `None` when neither is set.
+    fn exif(&self) -> Option<Vec<u8>> {
+        let mut entries: Vec<(u16, &str)> = Vec::new();
+        if let Some(creator) = self.creator {
+            entries.push((TAG_ARTIST, creator));
+        }
+        if let Some(copyright) = text(&self.rights.copyright) {
+            entries.push((TAG_COPYRIGHT, copyright));
+        }
+        if entries.is_empty() {
+            return None;
+        }
+
+        let mut tiff = b"MM\0\x2a\0\0\0\x08".to_vec();
+        let ifd_len = 2 + entries.len() * 12 + 4;
+        let mut values = Vec::new();
+        tiff.extend_from_slice(&(entries.len() as u16).to_be_bytes());
+        for (tag, value) in entries {
+            // NUL-terminated; values over four bytes go after the IFD
+            let mut bytes = value.as_bytes().to_vec();
+            bytes.push(0);
+            tiff.extend_from_slice(&tag.to_be_bytes());
+            tiff.extend_from_slice(&TYPE_ASCII.to_be_bytes());
+            tiff.extend_from_slice(&(bytes.len() as u32).to_be_bytes());
+            if bytes.len() <= 4 {
+                bytes.resize(4, 0);
+                tiff.extend_from_slice(&bytes);
+            } else {
+                let offset = 8 + ifd_len + values.len();
+                tiff.extend_from_slice(&(offset as u32).to_be_bytes());
+                values.extend_from_slice(&bytes);
+                // Values start on a word boundary
+                if values.len() % 2 == 1 {
+                    values.push(0);
+                }
+            }
+        }
+        tiff.extend_from_slice(&0u32.to_be_bytes());
+        tiff.extend_from_slice(&values);
+        Some(tiff)
+    }
+
+    /// XMP packet with the creator, rights and IPTC Core contact details.
+    fn xmp(&self) -> String {
+        let r = self.rights;
+        let alt = |value: &str| format!("<rdf:Alt><rdf:li xml:lang=\"x-default\">{}</rdf:li></rdf:Alt>", escape(value));
+        let mut properties = Vec::new();
+        if let Some(creator) = self.creator {
+            properties.push(format!("<dc:creator><rdf:Seq><rdf:li>{}</rdf:li></rdf:Seq></dc:creator>", escape(creator)));
+        }
+        if let Some(copyright) = text(&r.copyright) {
+            properties.push(format!("<dc:rights>{}</dc:rights>", alt(copyright)));
+            properties.push("<xmpRights:Marked>True</xmpRights:Marked>".into());
+        }
+        if let Some(terms) = text(&r.usage_terms) {
+            properties.push(format!("<xmpRights:UsageTerms>{}</xmpRights:UsageTerms>", alt(terms)));
+        }
+        if let Some(credit) = text(&r.credit) {
+            properties.push(format!("<photoshop:Credit>{}</photoshop:Credit>", escape(credit)));
+        }
+        let contact: String = [("CiEmailWork", &r.contact_email), ("CiUrlWork", &r.contact_url), ("CiTelWork", &r.contact_phone)]
+            .into_iter()
+            .filter_map(|(name, field)| {
+                text(field).map(|value| format!("<Iptc4xmpCore:{0}>{1}</Iptc4xmpCore:{0}>", name, escape(value)))
+            })
+            .collect();
+        if !contact.is_empty() {
+            properties.push(format!(
+                "<Iptc4xmpCore:CreatorContactInfo rdf:parseType=\"Resource\">{}</Iptc4xmpCore:CreatorContactInfo>",
+                contact
+            ));
+        }
+
+        format!(
+            concat!(
+                "<?xpacket begin=\"\u{feff}\" id=\"W5M0MpCehiHzreSzNTczkc9d\"?>\n",
+                "<x:xmpmeta xmlns:x=\"adobe:ns:meta/\">\n",
+                " <rdf:RDF xmlns:rdf=\"http://www.w3.org/1999/02/22-rdf-syntax-ns#\">\n",
+                "  <rdf:Description rdf:about=\"\"\n",
+                "    xmlns:dc=\"http://purl.org/dc/elements/1.1/\"\n",
+                "    xmlns:xmpRights=\"http://ns.adobe.com/xap/1.0/rights/\"\n",
+                "    xmlns:photoshop=\"http://ns.adobe.com/photoshop/1.0/\"\n",
+                "    xmlns:Iptc4xmpCore=\"http://iptc.org/std/Iptc4xmpCore/1.0/xmlns/\">\n",
+                "   {}\n",
+                "  </rdf:Description>\n",
+                " </rdf:RDF>\n",
+                "</x:xmpmeta>\n",
+                "<?xpacket end=\"w\"?>"
+            ),
+            properties.join("\n   ")
+        )
+    }
+
+    // ─── Containers ───────────────────────────────────────
+
+    /// The start of an encoded file with the metadata in it, once `head`
+    /// reaches the place it goes; `None` while more of the file is needed.
+    fn header(&self, format: OutputFormat, head: &[u8]) -> Result<Option<Vec<u8>>, String> {
+        match format {
+            OutputFormat::Jpeg => self.jpeg_header(head),
+            OutputFormat::Png => self.png_header(head),
+            OutputFormat::WebP => self.webp_header(head),
+            OutputFormat::Tiff => Ok(Some(head.to_vec())),
+        }
+    }
+
+    /// Adds EXIF and XMP APP1 segments after the SOI marker and JFIF header.
+    fn jpeg_header(&self, head: &[u8]) -> Result<Option<Vec<u8>>, String> {
+        match head {
+            [0xFF, 0xD8, ..] => {}
+            [] | [0xFF] => return Ok(None),
+            _ => return Err("Not a JPEG".into()),
+        }
+        let mut offset = 2;
+        loop {
+            match head.get(offset..) {
+                Some([0xFF, 0xE0, hi, lo, ..]) => offset += 2 + u16::from_be_bytes([*hi, *lo]) as usize,
+                Some([] | [0xFF] | [0xFF, 0xE0] | [0xFF, 0xE0, _]) | None => return Ok(None),
+                Some(_) => break,
+            }
+        }
+
+        let mut segments = Vec::new();
+        let mut push = |id: &[u8], data: &[u8]| {
+            if id.len() + data.len() > MAX_SEGMENT_PAYLOAD {
+                return Err("Creator and copyright metadata too long for JPEG".to_string());
+            }
+            segments.extend_from_slice(&[0xFF, JPEG_APP1]);
+            segments.extend_from_slice(&((2 + id.len() + data.len()) as u16).to_be_bytes());
+            segments.extend_from_slice(id);
+            segments.extend_from_slice(data);
+            Ok(())
+        };
+        if let Some(exif) = self.exif() {
+            push(JPEG_EXIF_ID, &exif)?;
+        }
+        push(xmp::JPEG_SIGNATURE, self.xmp().as_bytes())?;
+        Ok(Some([&head[..offset], &segments, &head[offset..]].concat()))
+    }
+
+    /// Adds `eXIf` and `iTXt` chunks before the image data.
+    fn png_header(&self, head: &[u8]) -> Result<Option<Vec<u8>>, String> {
+        if !head.starts_with(&PNG_SIGNATURE[..head.len().min(PNG_SIGNATURE.len())]) {
+            return Err("Not a PNG".into());
+        }
+        let mut offset = PNG_SIGNATURE.len();
+        loop {
+            let Some(header) = head.get(offset..offset + 8) else {
+                return Ok(None);
+            };
+            if &header[4..] == b"IDAT" {
+                break;
+            }
+            let length = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize;
+            offset += 12 + length;
+        }
+
+        let mut chunks = Vec::new();
+        let mut push = |kind: &[u8; 4], data: &[u8]| {
+            let mut crc = crc32fast::Hasher::new();
+            crc.update(kind);
+            crc.update(data);
+            chunks.extend_from_slice(&(data.len() as u32).to_be_bytes());
+            chunks.extend_from_slice(kind);
+            chunks.extend_from_slice(data);
+            chunks.extend_from_slice(&crc.finalize().to_be_bytes());
+        };
+        if let Some(exif) = self.exif() {
+            push(b"eXIf", &exif);
+        }
+        // Keyword, then uncompressed, with no language or translated keyword
+        let itxt = [xmp::PNG_KEYWORD, b"\0\0\0\0\0", self.xmp().as_bytes()].concat();
+        push(b"iTXt", &itxt);
+        Ok(Some([&head[..offset], &chunks, &head[offset..]].concat()))
+    }
+
+    /// Flags the `EXIF` and `XMP ` chunks that follow the image data,
+    /// turning a simple WebP into an extended one with a `VP8X` header when
+    /// needed, and counts them in the RIFF size.
+    fn webp_header(&self, head: &[u8]) -> Result<Option<Vec<u8>>, String> {
+        // RIFF header, then the first chunk's header and enough of it for the canvas
+        let Some(start) = head.get(..30) else {
+            return Ok(None);
+        };
+        if &start[..4] != b"RIFF" || &start[8..12] != b"WEBP" {
+            return Err("Not a WebP".into());
+        }
+        let (flags, trailer) = self.webp_chunks();
+        let mut header = head.to_vec();
+        let mut added = trailer.len();
+        if &start[12..16] == b"VP8X" {
+            header[20] |= flags;
+        } else {
+            let (width, height, alpha) = webp_canvas(&start[12..])?;
+            let mut vp8x = vec![flags | if alpha { WEBP_ALPHA } else { 0 }, 0, 0, 0];
+            vp8x.extend_from_slice(&(width - 1).to_le_bytes()[..3]);
+            vp8x.extend_from_slice(&(height - 1).to_le_bytes()[..3]);
+            let mut chunk = Vec::new();
+            push_riff_chunk(&mut chunk, b"VP8X", &vp8x);
+            added += chunk.len();
+            header.splice(12..12, chunk);
+        }
+        let size = u32::from_le_bytes([start[4], start[5], start[6], start[7]])
+            .checked_add(added as u32)
+            .ok_or("WebP too large")?;
+        header[4..8].copy_from_slice(&size.to_le_bytes());
+        Ok(Some(header))
+    }
+
+    /// `VP8X` flags and the `EXIF` and `XMP ` chunks of a WebP.
+    fn webp_chunks(&self) -> (u8, Vec<u8>) {
+        let mut flags = WEBP_XMP;
+        let mut chunks = Vec::new();
+        if let Some(exif) = self.exif() {
+            flags |= WEBP_EXIF;
+            push_riff_chunk(&mut chunks, b"EXIF", &exif);
+        }
+        push_riff_chunk(&mut chunks, b"XMP ", self.xmp().as_bytes());
+        (flags, chunks)
+    }
+}
+
+/// Adds an [`Attribution`] to a file as it's encoded. Only the start of the
+/// file is held back, until the place for the metadata is known.
+pub(crate) struct MetadataWriter<'a, W: Write> {
+    attribution: &'a Attribution<'a>,
+    format: OutputFormat,
+    inner: W,
+    /// Start of the file while the metadata isn't in yet
+    head: Option<Vec<u8>>,
+    /// Bytes taken from the encoder
+    written: u64,
+}
+
+impl<W: Write> MetadataWriter<'_, W> {
+    /// Ends the file; WebP's metadata chunks follow the image data.
+    pub(crate) fn finish(mut self) -> Result<(), String> {
+        if self.head.is_some() {
+            return Err("Encoded image ended before its image data".into());
+        }
+        if self.format == OutputFormat::WebP {
+            let (_, trailer) = self.attribution.webp_chunks();
+            self.inner.write_all(&trailer).map_err(|e| format!("Failed to write output: {}", e))?;
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for MetadataWriter<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.written += buf.len() as u64;
+        let Some(head) = &mut self.head else {
+            return self.inner.write_all(buf).map(|_| buf.len());
+        };
+        head.extend_from_slice(buf);
+        if let Some(header) = self.attribution.header(self.format, head).map_err(io::Error::other)? {
+            self.head = None;
+            self.inner.write_all(&header)?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// The encoders writing through it don't seek; they may only ask for the
+/// position.
+impl<W: Write> Seek for MetadataWriter<'_, W> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        match pos {
+            SeekFrom::Current(0) => Ok(self.written),
+            _ => Err(io::Error::new(io::ErrorKind::Unsupported, "Metadata is added to files written in order")),
+        }
+    }
+}
+
+/// RIFF chunks are padded to an even length.
+fn push_riff_chunk(out: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    out.extend_from_slice(kind);
+    out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    out.extend_from_slice(data);
+    if data.len() % 2 == 1 {
+        out.push(0);
+    }
+}
+
+/// Width, height and alpha of a simple WebP's `VP8L` or `VP8 ` chunk.
+fn webp_canvas(body: &[u8]) -> Result<(u32, u32, bool), String> {
+    let data = body.get(8..18).ok_or("Truncated WebP")?;
+    match &body[..4] {
+        b"VP8L" if data[0] == 0x2F => {
+            let bits = u32::from_le_bytes([data[1], data[2], data[3], data[4]]);
+            Ok(((bits & 0x3FFF) + 1, ((bits >> 14) & 0x3FFF) + 1, bits >> 28 & 1 == 1))
+        }
+        b"VP8 " => {
+            let width = u16::from_le_bytes([data[6], data[7]]) & 0x3FFF;
+            let height = u16::from_le_bytes([data[8], data[9]]) & 0x3FFF;
+            Ok((width.into(), height.into(), false))
+        }
+        _ => Err("Unsupported WebP image chunk".into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::Watermarker;
+    use crate::xmp::Xmp;
+    use image::{ImageFormat, Rgb, RgbImage};
+    use std::io::Cursor;
+
+    const DC: &str = "http://purl.org/dc/elements/1.1/";
+
+    fn rights() -> Rights {
+        Rights {
+            copyright: Some("© 2024 Jane Doe".into()),
+            usage_terms: Some("Editorial use only".into()),
+            contact_email: Some("jane@example.com".into()),
+            credit: Some("Jane Doe / Studio <JD>".into()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn every_format_carries_creator_and_rights() {
+        let mut input = Cursor::new(Vec::new());
+        RgbImage::from_pixel(300, 200, Rgb([90, 120, 150])).write_to(&mut input, ImageFormat::Png).unwrap();
+        let builder = || Watermarker::builder().system_fonts(false).photographer_name("Jane Doe").rights(rights());
+
+        // In memory, and streamed (WebP is always encoded in memory)
+        let outputs = [
+            (builder().build(), OutputFormat::Jpeg),
+            (builder().build(), OutputFormat::Png),
+            (builder().build(), OutputFormat::WebP),
+            (builder().memory_budget(800_000).build(), OutputFormat::Jpeg),
+            (builder().memory_budget(800_000).build(), OutputFormat::Png),
+        ];
+        for (wm, format) in outputs {
+            let framed = wm.process_bytes(input.get_ref(), format).unwrap();
+            assert!(image::load_from_memory(&framed).is_ok(), "{:?} still decodes", format);
+
+            let exif = exif::Reader::new().read_from_container(&mut Cursor::new(&framed)).unwrap();
+            let field = |tag| match &exif.get_field(tag, exif::In::PRIMARY).unwrap().value {
+                exif::Value::Ascii(values) => String::from_utf8(values[0].clone()).unwrap(),
+                other => panic!("{:?} isn't ASCII", other),
+            };
+            assert_eq!(field(exif::Tag::Artist), "Jane Doe", "{:?}", format);
+            assert_eq!(field(exif::Tag::Copyright), "© 2024 Jane Doe", "{:?}", format);
+
+            let packet = xmp::read_embedded(&mut Cursor::new(&framed)).unwrap();
+            let xmp = Xmp::parse(&packet).unwrap();
+            assert_eq!(xmp.get(DC, "creator"), Some("Jane Doe"));
+            assert_eq!(xmp.get(DC, "rights"), Some("© 2024 Jane Doe"));
+            assert_eq!(xmp.get("http://ns.adobe.com/photoshop/1.0/", "Credit"), Some("Jane Doe / Studio <JD>"));
+            assert_eq!(xmp.get("http://ns.adobe.com/xap/1.0/rights/", "UsageTerms"), Some("Editorial use only"));
+        }
+    }
+
+    #[test]
+    fn header_may_arrive_in_small_writes() {
+        let mut png = Cursor::new(Vec::new());
+        RgbImage::from_pixel(4, 4, Rgb([1, 2, 3])).write_to(&mut png, ImageFormat::Png).unwrap();
+        let none = Rights::default();
+        let attribution = Attribution::new("Jo", &none);
+
+        let mut out = Vec::new();
+        let mut writer = attribution.writer(OutputFormat::Png, &mut out);
+        for byte in png.get_ref() {
+            writer.write_all(&[*byte]).unwrap();
+        }
+        writer.finish().unwrap();
+        assert!(out.len() > png.get_ref().len());
+        assert!(image::load_from_memory(&out).is_ok());
+
+        // A file that ends before its image data is an error
+        let mut truncated = attribution.writer(OutputFormat::Png, Vec::new());
+        truncated.write_all(&png.get_ref()[..20]).unwrap();
+        assert!(truncated.finish().is_err());
+        assert!(attribution.writer(OutputFormat::Jpeg, Vec::new()).write_all(b"GIF89a").is_err());
+    }
+
+    #[test]
+    fn nothing_is_added_without_creator_or_rights() {
+        let blank = Rights { copyright: Some("  ".into()), ..Default::default() };
+        let attribution = Attribution::new("", &blank);
+        assert!(attribution.is_empty());
+        assert!(!attribution.applies_to(OutputFormat::Png));
+
+        // A creator alone still gets EXIF Artist, with no Copyright
+        let none = Rights::default();
+        let exif = Attribution::new("Jo", &none).exif().unwrap();
+        let parsed = exif::Reader::new().read_raw(exif).unwrap();
+        assert!(parsed.get_field(exif::Tag::Copyright, exif::In::PRIMARY).is_none());
+        assert_eq!(
+            parsed.get_field(exif::Tag::Artist, exif::In::PRIMARY).unwrap().display_value().to_string(),
+            "\"Jo\""
+        );
+    }
+}
@@ -0,0 +1,476 @@
+//! Rules that pick the design, and a few options, for each photo from its
+//! metadata. Rules are tried in order and the first whose conditions all
+//! hold wins; photos no rule matches get the default.
+//!
+//! ```json
+//! {
+//!   "default": { "design": "classic" },
+//!   "rules": [
+//!     { "when": { "brand": "fujifilm" }, "design": "vintage" },
+//!     { "when": { "orientation": "portrait" }, "design": "simple" },
+//!     { "when": { "model": "^iPhone" }, "design": "micro" },
+//!     { "when": { "iso": "> 6400" }, "design": "dark", "histogram": "line" }
+//!   ]
+//! }
+//! ```
+
+use regex::{Regex, RegexBuilder};
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+
+use crate::brands::BrandRegistry;
+use crate::designs;
+use crate::engine::Watermarker;
+use crate::histogram::HistogramStyle;
+use crate::palette::{self, FrameColor};
+use crate::watermark::{self, ExifInfo};
+
+/// File the desktop app keeps the rules in, next to its other settings.
+pub const RULES_FILE: &str = "design_rules.json";
+
+// ─── Rules file format ────────────────────────────────
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RulesFile {
+    default: Option<Choice>,
+    #[serde(default)]
+    rules: Vec<RuleEntry>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct RuleEntry {
+    #[serde(default)]
+    when: ConditionEntry,
+    design: Option<String>,
+    font_family: Option<String>,
+    frame_color: Option<FrameColor>,
+    swatches: Option<usize>,
+    histogram: Option<HistogramStyle>,
+}
+
+/// Conditions of one rule; all given ones must hold.
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct ConditionEntry {
+    /// Brand id from the brand registry, e.g. `fujifilm` or `apple`
+    brand: Option<String>,
+    /// Case-insensitive regex on the EXIF make
+    make: Option<String>,
+    /// Case-insensitive regex on the EXIF model
+    model: Option<String>,
+    orientation: Option<Orientation>,
+    /// Comparisons such as `"> 6400"` or `"<= 35"`
+    iso: Option<String>,
+    focal_length: Option<String>,
+    aperture: Option<String>,
+    /// XMP star rating comparison; -1 is rejected, 0 unrated
+    rating: Option<String>,
+    /// XMP colour label, e.g. `Red`
+    label: Option<String>,
+    /// One of the XMP keywords
+    keyword: Option<String>,
+}
+
+/// What a matching rule sets. Options left out keep the run's setting.
+#[derive(Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct Choice {
+    design: Option<String>,
+    font_family: Option<String>,
+    frame_color: Option<FrameColor>,
+    swatches: Option<usize>,
+    histogram: Option<HistogramStyle>,
+}
+
+/// Shape of the photo once the EXIF orientation is applied. Square photos
+/// are neither.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Orientation {
+    Portrait,
+    Landscape,
+}
+
+impl Orientation {
+    /// Parses the names used by the CLI and the frontend.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "portrait" => Some(Orientation::Portrait),
+            "landscape" => Some(Orientation::Landscape),
+            _ => None,
+        }
+    }
+}
+
+// ─── Conditions ───────────────────────────────────────
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    Less,
+    LessOrEqual,
+    Equal,
+    GreaterOrEqual,
+    Greater,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Comparison {
+    op: Op,
+    value: f64,
+}
+
+impl Comparison {
+    /// Parses `"> 6400"`, `">=1.8"`, `"= 35"` and plain numbers (equality).
+    fn parse(text: &str) -> Result<Self, String> {
+        let text = text.trim();
+        let (op, rest) = [
+            ("<=", Op::LessOrEqual),
+            (">=", Op::GreaterOrEqual),
+            ("<", Op::Less),
+            (">", Op::Greater),
+            ("=", Op::Equal),
+        ]
+        .iter()
+        .find_map(|(prefix, op)| text.strip_prefix(prefix).map(|rest| (*op, rest)))
+        .unwrap_or((Op::Equal, text));
+        let value = rest
+            .trim()
+            .parse()
+            .map_err(|_| format!("Invalid comparison '{}': expected e.g. \"> 6400\"", text))?;
+        Ok(Comparison { op, value })
+    }
+
+    fn holds(self, actual: f64) -> bool {
+        match self.op {
+            Op::Less => actual < self.value,
+            Op::LessOrEqual => actual <= self.value,
+            Op::Equal => (actual - self.value).abs() < 1e-6,
+            Op::GreaterOrEqual => actual >= self.value,
+            Op::Greater => actual > self.value,
+        }
+    }
+}
+
+struct Condition {
+    brand: Option<String>,
+    make: Option<Regex>,
+    model: Option<Regex>,
+    orientation: Option<Orientation>,
+    iso: Option<Comparison>,
+    focal_length: Option<Comparison>,
+    aperture: Option<Comparison>,
+    rating: Option<Comparison>,
+    label: Option<String>,
+    keyword: Option<String>,
+}
+
+/// The facts about one photo that rules look at.
+pub struct PhotoFacts<'a> {
+    pub exif: &'a ExifInfo,
+    /// Size with the EXIF orientation applied
+    pub width: u32,
+    pub height: u32,
+}
+
+impl PhotoFacts<'_> {
+    pub(crate) fn orientation(&self) -> Option<Orientation> {
+        match self.width.cmp(&self.height) {
+            std::cmp::Ordering::Less => Some(Orientation::Portrait),
+            std::cmp::Ordering::Greater => Some(Orientation::Landscape),
+            std::cmp::Ordering::Equal => None,
+        }
+    }
+}
+
+fn compile(pattern: &str) -> Result<Regex, String> {
+    RegexBuilder::new(pattern)
+        .case_insensitive(true)
+        .build()
+        .map_err(|e| format!("Invalid rule pattern '{}': {}", pattern, e))
+}
+
+/// First number in an EXIF string: `"f/2.8"` → 2.8, `"35mm"` → 35.
+fn leading_number(text: &str) -> Option<f64> {
+    let start = text.find(|c: char| c.is_ascii_digit())?;
+    let rest = &text[start..];
+    let end = rest.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(rest.len());
+    rest[..end].parse().ok()
+}
+
+fn compare(comparison: Option<Comparison>, exif_value: &str) -> bool {
+    comparison.is_none_or(|c| leading_number(exif_value).is_some_and(|v| c.holds(v)))
+}
+
+impl Condition {
+    fn compile(entry: &ConditionEntry) -> Result<Self, String> {
+        Ok(Condition {
+            brand: entry.brand.as_ref().map(|b| b.to_lowercase()),
+            make: entry.make.as_deref().map(compile).transpose()?,
+            model: entry.model.as_deref().map(compile).transpose()?,
+            orientation: entry.orientation,
+            iso: entry.iso.as_deref().map(Comparison::parse).transpose()?,
+            focal_length: entry.focal_length.as_deref().map(Comparison::parse).transpose()?,
+            aperture: entry.aperture.as_deref().map(Comparison::parse).transpose()?,
+            rating: entry.rating.as_deref().map(Comparison::parse).transpose()?,
+            label: entry.label.clone(),
+            keyword: entry.keyword.clone(),
+        })
+    }
+
+    fn matches(&self, photo: &PhotoFacts, brands: &BrandRegistry) -> bool {
+        let exif = photo.exif;
+        let model = watermark::known_model(exif);
+        self.brand.as_ref().is_none_or(|id| {
+            brands
+                .resolve(&exif.camera_make, model)
+                .is_some_and(|m| m.brand.id.eq_ignore_ascii_case(id))
+        }) && self.make.as_ref().is_none_or(|re| re.is_match(&exif.camera_make))
+            && self.model.as_ref().is_none_or(|re| re.is_match(model))
+            && self.orientation.is_none_or(|o| photo.orientation() == Some(o))
+            && compare(self.iso, &exif.iso)
+            && compare(self.focal_length, &exif.focal_length)
+            && compare(self.aperture, &exif.f_number)
+            && self.rating.is_none_or(|c| c.holds(exif.xmp.rating.unwrap_or(0) as f64))
+            && self.label.as_ref().is_none_or(|l| exif.xmp.label.eq_ignore_ascii_case(l))
+            && self.keyword.as_ref().is_none_or(|k| exif.xmp.keywords.iter().any(|w| w.eq_ignore_ascii_case(k)))
+    }
+}
+
+// ─── Rule set ─────────────────────────────────────────
+
+struct Rule {
+    when: Condition,
+    then: Choice,
+}
+
+/// Ordered design rules with a default.
+#[derive(Default)]
+pub struct DesignRules {
+    rules: Vec<Rule>,
+    default: Choice,
+}
+
+impl DesignRules {
+    /// Rules from `file`, or none if it's missing. A broken file is logged
+    /// and ignored so a typo doesn't stop the run.
+    pub fn load(file: Option<&Path>) -> Self {
+        match file.filter(|p| p.exists()).map(Self::from_file) {
+            Some(Ok(rules)) => rules,
+            Some(Err(e)) => {
+                log::warn!("Ignoring design rules: {}", e);
+                Self::default()
+            }
+            None => Self::default(),
+        }
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self, String> {
+        let data = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        Self::from_json(&data).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    /// Parses and checks rules: patterns must compile and designs must exist.
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        let file: RulesFile =
+            serde_json::from_str(json).map_err(|e| format!("Invalid design rules: {}", e))?;
+
+        let known = designs::get_design_list();
+        let check = |choice: &Choice| match &choice.design {
+            Some(id) if !known.iter().any(|d| &d.id == id) => Err(format!("Unknown design '{}' in rules", id)),
+            _ => Ok(()),
+        };
+
+        let default = file.default.unwrap_or_default();
+        check(&default)?;
+        let rules = file
+            .rules
+            .into_iter()
+            .map(|entry| {
+                let then = Choice {
+                    design: entry.design,
+                    font_family: entry.font_family,
+                    frame_color: entry.frame_color,
+                    swatches: entry.swatches,
+                    histogram: entry.histogram,
+                };
+                check(&then)?;
+                Ok(Rule { when: Condition::compile(&entry.when)?, then })
+            })
+            .collect::<Result<_, String>>()?;
+        Ok(DesignRules { rules, default })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty() && self.default.design.is_none()
+    }
+
+    /// One watermarker per rule, plus the default, built from the run's
+    /// settings once. `font_for` gives the font chosen for a design, used
+    /// when a rule switches design without naming a font.
+    pub fn watermarkers(&self, base: &Watermarker, font_for: impl Fn(&str) -> Option<String>) -> RuleWatermarkers<'_> {
+        let apply = |choice: &Choice| {
+            let mut wm = match &choice.design {
+                Some(id) if id != base.design_id() => base.with_design(id, font_for(id)),
+                _ => base.clone(),
+            };
+            let options = &mut wm.options;
+            if choice.font_family.is_some() {
+                options.font_family = choice.font_family.clone();
+            }
+            if let Some(frame_color) = choice.frame_color {
+                options.frame_color = frame_color;
+            }
+            if let Some(swatches) = choice.swatches {
+                options.swatches = swatches.min(palette::MAX_SWATCHES);
+            }
+            if choice.histogram.is_some() {
+                options.histogram = choice.histogram;
+            }
+            wm
+        };
+
+        RuleWatermarkers {
+            rules: self.rules.iter().map(|rule| (&rule.when, apply(&rule.then))).collect(),
+            default: apply(&self.default),
+        }
+    }
+}
+
+/// Rules resolved against the run's settings, ready to pick a watermarker
+/// per photo.
+pub struct RuleWatermarkers<'a> {
+    rules: Vec<(&'a Condition, Watermarker)>,
+    default: Watermarker,
+}
+
+impl RuleWatermarkers<'_> {
+    /// The watermarker of the first rule the photo matches.
+    pub fn select(&self, photo: &PhotoFacts) -> &Watermarker {
+        let brands = &self.default.brands;
+        self.rules
+            .iter()
+            .find(|(when, _)| when.matches(photo, brands))
+            .map_or(&self.default, |(_, wm)| wm)
+    }
+
+    /// Reads the photo's EXIF, with its sidecar overrides, and size (not its
+    /// pixels) and selects for it.
+    /// Unreadable photos get the default; framing them reports the error.
+    pub fn select_path(&self, input_path: &Path) -> &Watermarker {
+        if self.rules.is_empty() {
+            return &self.default;
+        }
+        match watermark::read_photo_facts(input_path) {
+            Ok((exif, width, height)) => self.select(&PhotoFacts { exif: &exif, width, height }),
+            Err(_) => &self.default,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn exif(make: &str, model: &str, iso: &str, f_number: &str) -> ExifInfo {
+        ExifInfo {
+            camera_model: model.into(),
+            camera_make: make.into(),
+            focal_length: "35mm".into(),
+            f_number: f_number.into(),
+            exposure_time: "1/250".into(),
+            iso: iso.into(),
+            lens_model: String::new(),
+            date_time: String::new(),
+            orientation: 1,
+            xmp: Default::default(),
+        }
+    }
+
+    const RULES: &str = r#"{
+        "default": { "design": "classic" },
+        "rules": [
+            { "when": { "brand": "fujifilm" }, "design": "vintage" },
+            { "when": { "orientation": "portrait" }, "design": "simple" },
+            { "when": { "model": "^iphone" }, "design": "micro" },
+            { "when": { "iso": "> 6400" }, "design": "dark", "histogram": "line" },
+            { "when": { "aperture": "<= 1.8", "focalLength": ">= 50" }, "swatches": 3 },
+            { "when": { "rating": ">= 4", "keyword": "portfolio" }, "design": "frosted" }
+        ]
+    }"#;
+
+    fn pick(rules: &RuleWatermarkers, exif: &ExifInfo, width: u32, height: u32) -> String {
+        rules.select(&PhotoFacts { exif, width, height }).design_id().to_string()
+    }
+
+    #[test]
+    fn first_matching_rule_wins() {
+        let rules = DesignRules::from_json(RULES).unwrap();
+        let base = Watermarker::builder().design("minimal").system_fonts(false).build();
+        let wms = rules.watermarkers(&base, |_| None);
+
+        let fuji = exif("FUJIFILM", "X-T5", "12800", "f/2.0");
+        assert_eq!(pick(&wms, &fuji, 600, 400), "vintage");
+        // Fujifilm comes first, even in portrait
+        assert_eq!(pick(&wms, &fuji, 400, 600), "vintage");
+        assert_eq!(pick(&wms, &exif("Sony", "ILCE-7M4", "100", "f/4.0"), 400, 600), "simple");
+        assert_eq!(pick(&wms, &exif("Apple", "iPhone 15 Pro", "100", "f/1.8"), 600, 400), "micro");
+
+        let night = exif("Canon", "Canon EOS R5", "25600", "f/2.8");
+        let dark = wms.select(&PhotoFacts { exif: &night, width: 600, height: 400 });
+        assert_eq!(dark.design_id(), "dark");
+        assert_eq!(dark.options().histogram, Some(HistogramStyle::Line));
+
+        // No rule: the default design; 6400 isn't above 6400
+        assert_eq!(pick(&wms, &exif("Canon", "Canon EOS R5", "6400", "f/2.8"), 600, 400), "classic");
+        assert_eq!(pick(&wms, &exif("", watermark::UNKNOWN_CAMERA, "", ""), 500, 500), "classic");
+
+        // XMP from the culling tool
+        let mut pick_of_the_day = exif("Canon", "Canon EOS R5", "100", "f/4.0");
+        pick_of_the_day.xmp.keywords = vec!["Portfolio".into()];
+        assert_eq!(pick(&wms, &pick_of_the_day, 600, 400), "classic");
+        pick_of_the_day.xmp.rating = Some(5);
+        assert_eq!(pick(&wms, &pick_of_the_day, 600, 400), "frosted");
+    }
+
+    #[test]
+    fn rules_can_keep_the_design_and_change_options() {
+        let rules = DesignRules::from_json(RULES).unwrap();
+        let base = Watermarker::builder().design("minimal").system_fonts(false).build();
+        let wms = rules.watermarkers(&base, |_| None);
+
+        let mut portrait_lens = exif("Nikon", "NIKON Z 6_2", "200", "f/1.8");
+        portrait_lens.focal_length = "85mm".into();
+        let wm = wms.select(&PhotoFacts { exif: &portrait_lens, width: 600, height: 400 });
+        assert_eq!(wm.design_id(), "minimal");
+        assert_eq!(wm.options().swatches, 3);
+
+        // Capped like the builder's setting
+        let rules = DesignRules::from_json(r#"{ "default": { "swatches": 20 } }"#).unwrap();
+        let wms = rules.watermarkers(&base, |_| None);
+        let wm = wms.select(&PhotoFacts { exif: &portrait_lens, width: 600, height: 400 });
+        assert_eq!(wm.options().swatches, palette::MAX_SWATCHES);
+    }
+
+    #[test]
+    fn without_a_default_the_runs_design_is_kept() {
+        let rules = DesignRules::from_json(r#"{ "rules": [] }"#).unwrap();
+        assert!(rules.is_empty());
+        let base = Watermarker::builder().design("dark").system_fonts(false).build();
+        let wms = rules.watermarkers(&base, |_| None);
+        assert_eq!(pick(&wms, &exif("Canon", "EOS R", "100", "f/4.0"), 600, 400), "dark");
+    }
+
+    #[test]
+    fn rejects_bad_rules() {
+        assert!(DesignRules::from_json(r#"{ "rules": [{ "design": "neon" }] }"#).is_err());
+        assert!(DesignRules::from_json(r#"{ "rules": [{ "when": { "iso": "lots" }, "design": "dark" }] }"#).is_err());
+        assert!(DesignRules::from_json(r#"{ "rules": [{ "when": { "model": "(" }, "design": "dark" }] }"#).is_err());
+        assert!(DesignRules::from_json(r#"{ "rules": [{ "when": { "lens": "50mm" }, "design": "dark" }] }"#).is_err());
+        assert!(Comparison::parse(">= 1.8").unwrap().holds(1.8));
+        assert!(Comparison::parse("35").unwrap().holds(35.0));
+    }
+}
@@ -0,0 +1,346 @@
+//! Detached Ed25519 signatures for delivered files, so a recipient (or the
+//! photographer, later) can confirm a file wasn't altered after export.
+//!
+//! Each output gets either a `<file>.sig` sidecar holding the base64
+//! signature of the file's bytes, or an entry in a `signatures.json`
+//! manifest written next to the batch. Files are signed as Ed25519ph, over
+//! their SHA-512, so they are hashed in chunks instead of read whole.
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use ed25519_dalek::pkcs8::spki::der::pem::LineEnding;
+use ed25519_dalek::pkcs8::{DecodePrivateKey, DecodePublicKey, EncodePrivateKey, EncodePublicKey};
+use ed25519_dalek::{Signature, SigningKey, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256, Sha512};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
+
+/// Batch manifest written next to the signed files.
+pub const MANIFEST_NAME: &str = "signatures.json";
+
+const ALGORITHM: &str = "Ed25519ph";
+
+/// Bytes read at a time when hashing a file.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Where the signatures go.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SignatureMode {
+    /// A `.sig` file next to every output
+    #[default]
+    Sidecar,
+    /// One `signatures.json` per output folder
+    Manifest,
+}
+
+impl SignatureMode {
+    /// Parses the names used by the CLI and the frontend.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "sidecar" => Some(SignatureMode::Sidecar),
+            "manifest" => Some(SignatureMode::Manifest),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Manifest {
+    algorithm: String,
+    /// Base64 of the raw 32-byte public key
+    public_key: String,
+    files: Vec<ManifestEntry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ManifestEntry {
+    name: String,
+    /// Hex SHA-256, to spot a changed file without the key
+    sha256: String,
+    /// Base64 Ed25519ph signature of the file's bytes
+    signature: String,
+}
+
+/// Signs output files as they are written. In manifest mode the entries
+/// are collected and written by [`SidecarSigner::finish`].
+pub struct SidecarSigner {
+    key: SigningKey,
+    mode: SignatureMode,
+    /// Manifest entries by output folder
+    pending: BTreeMap<PathBuf, Vec<ManifestEntry>>,
+}
+
+impl SidecarSigner {
+    pub fn new(key: SigningKey, mode: SignatureMode) -> Self {
+        Self { key, mode, pending: BTreeMap::new() }
+    }
+
+    /// Reads a PKCS#8 PEM Ed25519 key, or creates one (and its public half
+    /// next to it, as `<name>.pub.pem`) if the file doesn't exist yet.
+    pub fn load_or_create(key_path: &Path, mode: SignatureMode) -> Result<Self, String> {
+        let key = if key_path.exists() {
+            let pem = fs::read_to_string(key_path)
+                .map_err(|e| format!("Failed to read {}: {}", key_path.display(), e))?;
+            SigningKey::from_pkcs8_pem(&pem)
+                .map_err(|_| format!("{} isn't an Ed25519 private key in PEM format", key_path.display()))?
+        } else {
+            let key = SigningKey::generate(&mut rand_core::OsRng);
+            write_private_key(key_path, &key)?;
+            log::info!("Created signing key {}", key_path.display());
+            key
+        };
+
+        let public_path = public_key_path(key_path);
+        if !public_path.exists() {
+            let pem = key
+                .verifying_key()
+                .to_public_key_pem(LineEnding::LF)
+                .map_err(|e| format!("Failed to encode public key: {}", e))?;
+            fs::write(&public_path, pem)
+                .map_err(|e| format!("Failed to write {}: {}", public_path.display(), e))?;
+        }
+        Ok(Self::new(key, mode))
+    }
+
+    pub fn verifying_key(&self) -> VerifyingKey {
+        self.key.verifying_key()
+    }
+
+    /// Signs a finished output file.
+    pub fn sign(&mut self, path: &Path) -> Result<(), String> {
+        let (prehash, sha256) = digests(path)?;
+        let signature = self
+            .key
+            .sign_prehashed(prehash, None)
+            .map_err(|e| format!("Failed to sign {}: {}", path.display(), e))?;
+        let signature = BASE64.encode(signature.to_bytes());
+        match self.mode {
+            SignatureMode::Sidecar => {
+                let sig_path = sidecar_path(path);
+                fs::write(&sig_path, format!("{}\n", signature))
+                    .map_err(|e| format!("Failed to write {}: {}", sig_path.display(), e))
+            }
+            SignatureMode::Manifest => {
+                let (dir, name) = split(path)?;
+                self.pending.entry(dir).or_default().push(ManifestEntry {
+                    name,
+                    sha256: hex(&sha256),
+                    signature,
+                });
+                Ok(())
+            }
+        }
+    }
+
+    /// Writes the manifests collected in manifest mode, keeping entries of
+    /// an earlier manifest signed with the same key. Returns their paths.
+    pub fn finish(self) -> Result<Vec<PathBuf>, String> {
+        let public_key = BASE64.encode(self.key.verifying_key().as_bytes());
+        let mut written = Vec::new();
+        for (dir, entries) in self.pending {
+            let path = dir.join(MANIFEST_NAME);
+            let mut files: BTreeMap<String, ManifestEntry> = read_manifest(&path)
+                .ok()
+                .filter(|m| m.public_key == public_key)
+                .map(|m| m.files.into_iter().map(|e| (e.name.clone(), e)).collect())
+                .unwrap_or_default();
+            files.extend(entries.into_iter().map(|e| (e.name.clone(), e)));
+
+            let manifest = Manifest {
+                algorithm: ALGORITHM.into(),
+                public_key: public_key.clone(),
+                files: files.into_values().collect(),
+            };
+            let json = serde_json::to_string_pretty(&manifest)
+                .map_err(|e| format!("Failed to encode {}: {}", MANIFEST_NAME, e))?;
+            fs::write(&path, json).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+            written.push(path);
+        }
+        Ok(written)
+    }
+}
+
+/// Reads a public key for verification: a PEM public key, or the private
+/// key it belongs to.
+pub fn load_verifying_key(path: &Path) -> Result<VerifyingKey, String> {
+    let pem = fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    VerifyingKey::from_public_key_pem(&pem)
+        .or_else(|_| SigningKey::from_pkcs8_pem(&pem).map(|k| k.verifying_key()))
+        .map_err(|_| format!("{} isn't an Ed25519 key in PEM format", path.display()))
+}
+
+/// Checks a delivered file against its `.sig` sidecar, or failing that its
+/// entry in the folder's `signatures.json`.
+pub fn verify_file(path: &Path, key: &VerifyingKey) -> Result<(), String> {
+    let sig_path = sidecar_path(path);
+    let encoded = if sig_path.exists() {
+        fs::read_to_string(&sig_path).map_err(|e| format!("Failed to read {}: {}", sig_path.display(), e))?
+    } else {
+        let (dir, name) = split(path)?;
+        let manifest_path = dir.join(MANIFEST_NAME);
+        if !manifest_path.exists() {
+            return Err("No signature found".into());
+        }
+        let manifest = read_manifest(&manifest_path)?;
+        if manifest.public_key != BASE64.encode(key.as_bytes()) {
+            return Err(format!("{} was signed with a different key", MANIFEST_NAME));
+        }
+        let entry = manifest
+            .files
+            .into_iter()
+            .find(|e| e.name == name)
+            .ok_or_else(|| format!("Not listed in {}", MANIFEST_NAME))?;
+        entry.signature
+    };
+
+    let signature = BASE64
+        .decode(encoded.trim())
+        .ok()
+        .and_then(|bytes| Signature::from_slice(&bytes).ok())
+        .ok_or("Malformed signature")?;
+    let (prehash, _) = digests(path)?;
+    key.verify_prehashed(prehash, None, &signature)
+        .map_err(|_| "File was changed after it was signed, or signed with a different key".to_string())
+}
+
+/// SHA-512 state to sign and SHA-256 for the manifest, from one pass over
+/// the file.
+fn digests(path: &Path) -> Result<(Sha512, [u8; 32]), String> {
+    let read_error = |e: std::io::Error| format!("Failed to read {}: {}", path.display(), e);
+    let mut file = fs::File::open(path).map_err(read_error)?;
+    let mut prehash = Sha512::new();
+    let mut sha256 = Sha256::new();
+    let mut chunk = vec![0; CHUNK_SIZE];
+    loop {
+        let read = match file.read(&mut chunk) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) => return Err(read_error(e)),
+        };
+        prehash.update(&chunk[..read]);
+        sha256.update(&chunk[..read]);
+    }
+    Ok((prehash, sha256.finalize().into()))
+}
+
+fn read_manifest(path: &Path) -> Result<Manifest, String> {
+    let json = fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let manifest: Manifest =
+        serde_json::from_str(&json).map_err(|e| format!("Invalid {}: {}", path.display(), e))?;
+    if manifest.algorithm != ALGORITHM {
+        return Err(format!("Unsupported signature algorithm {}", manifest.algorithm));
+    }
+    Ok(manifest)
+}
+
+/// The private key is only readable by its owner where the OS allows.
+fn write_private_key(path: &Path, key: &SigningKey) -> Result<(), String> {
+    let pem = key
+        .to_pkcs8_pem(LineEnding::LF)
+        .map_err(|e| format!("Failed to encode signing key: {}", e))?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options
+        .open(path)
+        .and_then(|mut file| file.write_all(pem.as_bytes()))
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+fn public_key_path(key_path: &Path) -> PathBuf {
+    key_path.with_extension("pub.pem")
+}
+
+fn sidecar_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".sig");
+    PathBuf::from(name)
+}
+
+/// Folder and file name of an output, for manifest entries.
+fn split(path: &Path) -> Result<(PathBuf, String), String> {
+    let name = path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .ok_or_else(|| format!("{} has no file name", path.display()))?;
+    let dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+    Ok((dir, name.to_string()))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+
+    #[test]
+    fn sidecars_detect_changed_files() {
+        let dir = TempDir::new("sidecar");
+        let key_path = dir.join("config").join("signing-key.pem");
+        let mut signer = SidecarSigner::load_or_create(&key_path, SignatureMode::Sidecar).unwrap();
+        fs::write(dir.join("a.jpg"), b"framed photo").unwrap();
+        signer.sign(&dir.join("a.jpg")).unwrap();
+        assert!(signer.finish().unwrap().is_empty());
+
+        // The key is reused, and its public half verifies
+        let again = SidecarSigner::load_or_create(&key_path, SignatureMode::Sidecar).unwrap();
+        let public = load_verifying_key(&dir.join("config").join("signing-key.pub.pem")).unwrap();
+        assert_eq!(again.verifying_key(), public);
+        assert_eq!(verify_file(&dir.join("a.jpg"), &public), Ok(()));
+
+        fs::write(dir.join("a.jpg"), b"framed photo, edited").unwrap();
+        assert!(verify_file(&dir.join("a.jpg"), &public).unwrap_err().contains("changed"));
+        fs::write(dir.join("b.jpg"), b"unsigned").unwrap();
+        assert_eq!(verify_file(&dir.join("b.jpg"), &public), Err("No signature found".into()));
+
+        // Hashed over several chunks; a change in the last one is caught
+        let mut large = vec![1u8; CHUNK_SIZE * 2 + 10];
+        fs::write(dir.join("c.tif"), &large).unwrap();
+        let mut signer = SidecarSigner::load_or_create(&key_path, SignatureMode::Sidecar).unwrap();
+        signer.sign(&dir.join("c.tif")).unwrap();
+        assert_eq!(verify_file(&dir.join("c.tif"), &public), Ok(()));
+        *large.last_mut().unwrap() = 2;
+        fs::write(dir.join("c.tif"), &large).unwrap();
+        assert!(verify_file(&dir.join("c.tif"), &public).is_err());
+    }
+
+    #[test]
+    fn manifest_covers_the_batch() {
+        let dir = TempDir::new("manifest");
+        let key = SigningKey::from_bytes(&[7; 32]);
+        let public = key.verifying_key();
+        for name in ["a.jpg", "b.jpg"] {
+            fs::write(dir.join(name), name).unwrap();
+        }
+
+        let mut signer = SidecarSigner::new(key.clone(), SignatureMode::Manifest);
+        signer.sign(&dir.join("a.jpg")).unwrap();
+        assert_eq!(signer.finish().unwrap(), [dir.join(MANIFEST_NAME)]);
+        // A later run adds to the manifest rather than replacing it
+        let mut signer = SidecarSigner::new(key, SignatureMode::Manifest);
+        signer.sign(&dir.join("b.jpg")).unwrap();
+        signer.finish().unwrap();
+
+        assert_eq!(verify_file(&dir.join("a.jpg"), &public), Ok(()));
+        assert_eq!(verify_file(&dir.join("b.jpg"), &public), Ok(()));
+        fs::write(dir.join("b.jpg"), "B").unwrap();
+        assert!(verify_file(&dir.join("b.jpg"), &public).is_err());
+
+        let other = SigningKey::from_bytes(&[8; 32]).verifying_key();
+        assert!(verify_file(&dir.join("a.jpg"), &other).unwrap_err().contains("different key"));
+    }
+}
@@ -0,0 +1,435 @@
+//! Row-by-row output for images too large to composite in memory.
+//!
+//! The framed image is described as a [`RowSource`]; encoders pull it a strip
+//! at a time, so only the decoded photo, the (small) frame and a few strips
+//! are ever held at once.
+
+use image::codecs::jpeg::JpegEncoder;
+use image::{
+    imageops, ColorType, DynamicImage, GenericImageView, ImageBuffer, ImageEncoder, Pixel,
+    Primitive, Rgb, Rgba, RgbaImage,
+};
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::marker::PhantomData;
+use std::io::{Seek, Write};
+
+use crate::color::{self, ColorPipeline, Converter};
+use crate::designs;
+use crate::engine::OutputFormat;
+use crate::invisible::{self, MarkSample, Payload};
+use crate::watermark::Backdrop;
+
+/// Rows converted and written per step.
+pub(crate) const STRIP_ROWS: u32 = 64;
+
+const MIB: u64 = 1024 * 1024;
+
+// ─── Samples ──────────────────────────────────────────
+
+/// 8-bit or 16-bit canvas samples.
+pub(crate) trait CanvasSample: color::Sample + MarkSample + Primitive
+where
+    Rgba<Self>: Pixel<Subpixel = Self>,
+{
+    type TiffRgb: tiff::encoder::colortype::ColorType<Inner = Self>;
+    const PNG_DEPTH: png::BitDepth;
+
+    fn rgba(img: &DynamicImage) -> ImageBuffer<Rgba<Self>, Vec<Self>>;
+    /// Narrows to 8 bits for encoders that only take 8-bit input.
+    fn to_rgba8(buf: ImageBuffer<Rgba<Self>, Vec<Self>>) -> RgbaImage;
+    /// Appends samples in PNG (big-endian) byte order.
+    fn extend_be(out: &mut Vec<u8>, samples: &[Self]);
+}
+
+impl CanvasSample for u8 {
+    type TiffRgb = tiff::encoder::colortype::RGB8;
+    const PNG_DEPTH: png::BitDepth = png::BitDepth::Eight;
+
+    fn rgba(img: &DynamicImage) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
+        img.to_rgba8()
+    }
+
+    fn to_rgba8(buf: RgbaImage) -> RgbaImage {
+        buf
+    }
+
+    fn extend_be(out: &mut Vec<u8>, samples: &[u8]) {
+        out.extend_from_slice(samples);
+    }
+}
+
+impl CanvasSample for u16 {
+    type TiffRgb = tiff::encoder::colortype::RGB16;
+    const PNG_DEPTH: png::BitDepth = png::BitDepth::Sixteen;
+
+    fn rgba(img: &DynamicImage) -> ImageBuffer<Rgba<u16>, Vec<u16>> {
+        img.to_rgba16()
+    }
+
+    fn to_rgba8(buf: ImageBuffer<Rgba<u16>, Vec<u16>>) -> RgbaImage {
+        DynamicImage::ImageRgba16(buf).into_rgba8()
+    }
+
+    fn extend_be(out: &mut Vec<u8>, samples: &[u16]) {
+        out.extend(samples.iter().flat_map(|s| s.to_be_bytes()));
+    }
+}
+
+// ─── Row sources ──────────────────────────────────────
+
+/// An RGBA image that can be read a strip of rows at a time.
+pub(crate) trait RowSource<S: CanvasSample>
+where
+    Rgba<S>: Pixel<Subpixel = S>,
+{
+    fn dimensions(&self) -> (u32, u32);
+    fn rows(&self, y: u32, count: u32) -> Result<ImageBuffer<Rgba<S>, Vec<S>>, String>;
+}
+
+impl<S: CanvasSample> RowSource<S> for ImageBuffer<Rgba<S>, Vec<S>>
+where
+    Rgba<S>: Pixel<Subpixel = S>,
+{
+    fn dimensions(&self) -> (u32, u32) {
+        ImageBuffer::dimensions(self)
+    }
+
+    fn rows(&self, y: u32, count: u32) -> Result<ImageBuffer<Rgba<S>, Vec<S>>, String> {
+        Ok(self.view(0, y, self.width(), count).to_image())
+    }
+}
+
+/// Photo plus frame without a full canvas: photo rows are converted from the
+/// decoded image on demand, frame rows come from the pre-rendered frame.
+pub(crate) struct StreamedCanvas<'a, S: CanvasSample>
+where
+    Rgba<S>: Pixel<Subpixel = S>,
+{
+    photo: &'a DynamicImage,
+    photo_colors: Converter<S>,
+    frame: ImageBuffer<Rgba<S>, Vec<S>>,
+    backdrop: Backdrop,
+    mark: Option<Payload>,
+}
+
+impl<'a, S: CanvasSample> StreamedCanvas<'a, S>
+where
+    Rgba<S>: Pixel<Subpixel = S>,
+{
+    /// `frame` is the rendered frame SVG; it is laid on the backdrop and
+    /// converted to the output space up front, like the in-memory path does.
+    pub(crate) fn new(
+        photo: &'a DynamicImage,
+        frame: Option<ImageBuffer<Rgba<S>, Vec<S>>>,
+        frame_height: u32,
+        colors: &ColorPipeline,
+        backdrop: Backdrop,
+        mark: Option<Payload>,
+    ) -> Result<Self, String> {
+        let fill = Rgba([backdrop.frame(); 4]);
+        let mut canvas = ImageBuffer::from_pixel(photo.width(), frame_height, fill);
+        if let Some(frame) = frame {
+            imageops::overlay(&mut canvas, &frame, 0, 0);
+        }
+        colors.frame_converter()?.apply(&mut canvas, photo.width())?;
+
+        Ok(Self {
+            photo,
+            photo_colors: colors.photo_converter()?,
+            frame: canvas,
+            backdrop,
+            mark,
+        })
+    }
+}
+
+impl<S: CanvasSample> RowSource<S> for StreamedCanvas<'_, S>
+where
+    Rgba<S>: Pixel<Subpixel = S>,
+{
+    fn dimensions(&self) -> (u32, u32) {
+        (self.photo.width(), self.photo.height() + self.frame.height())
+    }
+
+    fn rows(&self, y: u32, count: u32) -> Result<ImageBuffer<Rgba<S>, Vec<S>>, String> {
+        let (width, photo_height) = self.photo.dimensions();
+        let mut strip = ImageBuffer::from_pixel(width, count, Rgba([self.backdrop.photo(); 4]));
+
+        // Part of the strip inside the photo, laid on the backdrop like the full canvas
+        if y < photo_height {
+            let rows = count.min(photo_height - y);
+            let mut part = S::rgba(&self.photo.crop_imm(0, y, width, rows));
+            self.photo_colors.apply(&mut part, width)?;
+            if let Some(mark) = &self.mark {
+                invisible::embed(&mut part, y, photo_height, mark);
+            }
+            imageops::overlay(&mut strip, &part, 0, 0);
+        }
+        // Part inside the frame
+        if y + count > photo_height {
+            let frame_y = y.saturating_sub(photo_height);
+            let skip = photo_height.saturating_sub(y);
+            let part = self.frame.view(0, frame_y, width, count - skip);
+            imageops::replace(&mut strip, &*part, 0, skip as i64);
+        }
+        Ok(strip)
+    }
+}
+
+// ─── Encoders ─────────────────────────────────────────
+
+/// Writes an RGB TIFF (16-bit for 16-bit sources) strip by strip, with the
+/// ICC profile. Strips are LZW-compressed after a horizontal predictor. The
+/// `tiff` encoder only compresses images written in one go, so the strips are
+/// compressed and the directory filled in here.
+pub(crate) fn write_tiff<S, W>(
+    source: &impl RowSource<S>,
+    icc_profile: Option<&[u8]>,
+    writer: &mut W,
+) -> Result<(), String>
+where
+    S: CanvasSample,
+    Rgba<S>: Pixel<Subpixel = S>,
+    [S]: tiff::encoder::TiffValue,
+    W: Write + Seek,
+{
+    use tiff::encoder::colortype::ColorType;
+    use tiff::encoder::compression::{CompressionAlgorithm, Lzw};
+    use tiff::encoder::{Rational, TiffValue};
+    use tiff::tags::{CompressionMethod, Predictor, ResolutionUnit, Tag};
+
+    let tiff_err = |e: tiff::TiffError| format!("Failed to save TIFF: {}", e);
+    let (width, height) = source.dimensions();
+    let row_samples = width as usize * S::TiffRgb::BITS_PER_SAMPLE.len();
+
+    let mut encoder = tiff::encoder::TiffEncoder::new(writer).map_err(tiff_err)?;
+    let mut directory = encoder.image_directory().map_err(tiff_err)?;
+    let mut strip_offsets = Vec::new();
+    let mut strip_byte_counts = Vec::new();
+    for y in (0..height).step_by(STRIP_ROWS as usize) {
+        let strip = source.rows(y, STRIP_ROWS.min(height - y))?;
+        let rgb: Vec<S> = strip.pixels().flat_map(|px| px.to_rgb().0).collect();
+        let mut predicted = Vec::with_capacity(rgb.len());
+        for row in rgb.chunks_exact(row_samples) {
+            S::TiffRgb::horizontal_predict(row, &mut predicted);
+        }
+        let mut compressed = Vec::new();
+        Lzw.write_to(&mut compressed, &predicted.data())
+            .map_err(|e| format!("Failed to save TIFF: {}", e))?;
+        let offset = directory.write_data(&compressed[..]).map_err(tiff_err)?;
+        strip_offsets.push(u32::try_from(offset).map_err(|_| "TIFF output is larger than 4 GiB".to_string())?);
+        strip_byte_counts.push(compressed.len() as u32);
+    }
+
+    let sample_format: Vec<u16> = S::TiffRgb::SAMPLE_FORMAT.iter().map(|f| f.to_u16()).collect();
+    let tags = || -> tiff::TiffResult<()> {
+        directory.write_tag(Tag::ImageWidth, width)?;
+        directory.write_tag(Tag::ImageLength, height)?;
+        directory.write_tag(Tag::Compression, CompressionMethod::LZW.to_u16())?;
+        directory.write_tag(Tag::Predictor, Predictor::Horizontal.to_u16())?;
+        directory.write_tag(Tag::BitsPerSample, S::TiffRgb::BITS_PER_SAMPLE)?;
+        directory.write_tag(Tag::SampleFormat, &sample_format[..])?;
+        directory.write_tag(Tag::PhotometricInterpretation, S::TiffRgb::TIFF_VALUE.to_u16())?;
+        directory.write_tag(Tag::SamplesPerPixel, S::TiffRgb::BITS_PER_SAMPLE.len() as u16)?;
+        directory.write_tag(Tag::RowsPerStrip, STRIP_ROWS)?;
+        directory.write_tag(Tag::StripOffsets, &strip_offsets[..])?;
+        directory.write_tag(Tag::StripByteCounts, &strip_byte_counts[..])?;
+        directory.write_tag(Tag::XResolution, Rational { n: 1, d: 1 })?;
+        directory.write_tag(Tag::YResolution, Rational { n: 1, d: 1 })?;
+        directory.write_tag(Tag::ResolutionUnit, ResolutionUnit::None.to_u16())?;
+        if let Some(icc) = icc_profile {
+            directory.write_tag(Tag::IccProfile, icc)?;
+        }
+        directory.finish()
+    };
+    tags().map_err(tiff_err)
+}
+
+/// Writes an RGBA PNG strip by strip.
+pub(crate) fn write_png<S, W>(
+    source: &impl RowSource<S>,
+    icc_profile: Option<&[u8]>,
+    writer: &mut W,
+) -> Result<(), String>
+where
+    S: CanvasSample,
+    Rgba<S>: Pixel<Subpixel = S>,
+    W: Write,
+{
+    let png_err = |e: png::EncodingError| format!("Failed to save PNG: {}", e);
+    let (width, height) = source.dimensions();
+
+    let mut info = png::Info::with_size(width, height);
+    info.icc_profile = icc_profile.map(Cow::Borrowed);
+    let mut encoder = png::Encoder::with_info(writer, info).map_err(png_err)?;
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(S::PNG_DEPTH);
+    encoder.set_filter(png::Filter::Adaptive);
+    let mut png_writer = encoder.write_header().map_err(png_err)?;
+    let mut stream = png_writer.stream_writer().map_err(png_err)?;
+
+    let mut bytes = Vec::new();
+    for y in (0..height).step_by(STRIP_ROWS as usize) {
+        let strip = source.rows(y, STRIP_ROWS.min(height - y))?;
+        bytes.clear();
+        S::extend_be(&mut bytes, &strip);
+        stream
+            .write_all(&bytes)
+            .map_err(|e| format!("Failed to save PNG: {}", e))?;
+    }
+    stream.finish().map_err(png_err)
+}
+
+/// Adapts a row source to the per-pixel view `JpegEncoder::encode_image`
+/// walks, keeping only the current strip. The encoder moves top to bottom,
+/// so each strip is built once.
+struct JpegView<'a, R, S> {
+    source: &'a R,
+    sample: PhantomData<S>,
+    /// First row of the cached strip, and the strip itself
+    strip: RefCell<(u32, RgbaImage)>,
+    error: RefCell<Option<String>>,
+}
+
+impl<S: CanvasSample, R: RowSource<S>> GenericImageView for JpegView<'_, R, S>
+where
+    Rgba<S>: Pixel<Subpixel = S>,
+{
+    type Pixel = Rgb<u8>;
+
+    fn dimensions(&self) -> (u32, u32) {
+        self.source.dimensions()
+    }
+
+    fn get_pixel(&self, x: u32, y: u32) -> Rgb<u8> {
+        let mut strip = self.strip.borrow_mut();
+        let (start, pixels) = &mut *strip;
+        if y < *start || y >= *start + pixels.height() {
+            let height = self.source.dimensions().1;
+            *start = y - y % STRIP_ROWS;
+            match self.source.rows(*start, STRIP_ROWS.min(height - *start)) {
+                Ok(rows) => *pixels = S::to_rgba8(rows),
+                Err(e) => {
+                    self.error.borrow_mut().get_or_insert(e);
+                    return Rgb([0, 0, 0]);
+                }
+            }
+        }
+        pixels.get_pixel(x, y - *start).to_rgb()
+    }
+}
+
+/// Writes a JPEG (quality 95) without materialising the RGB canvas.
+pub(crate) fn write_jpeg<S, W>(
+    source: &impl RowSource<S>,
+    icc_profile: Option<&[u8]>,
+    writer: &mut W,
+) -> Result<(), String>
+where
+    S: CanvasSample,
+    Rgba<S>: Pixel<Subpixel = S>,
+    W: Write,
+{
+    let view = JpegView {
+        source,
+        sample: PhantomData,
+        strip: RefCell::new((0, ImageBuffer::new(0, 0))),
+        error: RefCell::new(None),
+    };
+    let mut encoder = JpegEncoder::new_with_quality(writer, 95);
+    if let Some(icc) = icc_profile {
+        if let Err(e) = encoder.set_icc_profile(icc.to_vec()) {
+            log::warn!("ICC profile not embedded: {}", e);
+        }
+    }
+    encoder
+        .encode_image(&view)
+        .map_err(|e| format!("Failed to write JPEG: {}", e))?;
+    match view.error.into_inner() {
+        Some(e) => Err(e),
+        None => Ok(()),
+    }
+}
+
+// ─── Memory planning ──────────────────────────────────
+
+/// What an image is framed into, for planning its memory.
+#[derive(Debug, Clone)]
+pub(crate) struct PlannedOutput<'a> {
+    pub(crate) format: OutputFormat,
+    /// Designs the photo is framed in; the tallest frame is planned for
+    pub(crate) design_ids: Vec<&'a str>,
+    /// The decoded photo outlives the canvas, to frame it again in another
+    /// design
+    pub(crate) keep_decoded: bool,
+    /// The encoded file is held in memory to be signed
+    pub(crate) buffered: bool,
+}
+
+/// How an image is composited, chosen from its size and the memory budget.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum MemoryPlan {
+    /// Full canvas in memory
+    InMemory,
+    /// Strip by strip straight into the encoder
+    Streaming,
+}
+
+impl MemoryPlan {
+    /// Estimates peak memory for both paths and picks the first that fits.
+    ///
+    /// `width`/`height` are the photo size after EXIF rotation and `color`
+    /// the decoder's native colour type.
+    pub(crate) fn choose(
+        width: u32,
+        height: u32,
+        color: ColorType,
+        rotated: bool,
+        output: PlannedOutput,
+        budget: u64,
+    ) -> Result<Self, String> {
+        let PlannedOutput { format, design_ids, keep_decoded, buffered } = output;
+        let pixels = width as u64 * height as u64;
+        let frame_height = (height as f64 * 0.1).round();
+        let frame_rows = design_ids
+            .iter()
+            .map(|id| designs::get_frame_svg_height(id, frame_height, height > width))
+            .max()
+            .unwrap_or(0);
+        let frame_pixels = frame_rows as u64 * width as u64;
+        let depth = if color.bytes_per_pixel() > color.channel_count() { 2 } else { 1 };
+
+        let decoded = pixels * color.bytes_per_pixel() as u64;
+        // Rendered SVG plus the frame laid on white
+        let frame = frame_pixels * 4 * (1 + depth);
+        let canvas = (pixels + frame_pixels) * 4 * depth;
+
+        // A signed JPEG is held whole; quality 95 takes about 1.5 bytes a
+        // pixel for detailed photos
+        let encoded = if buffered { (pixels + frame_pixels) * 3 / 2 } else { 0 };
+
+        // Decoded photo, its RGBA copy, the canvas and the encoder's copy,
+        // plus a copy of the photo if it's kept for the next design
+        let kept = if keep_decoded { decoded } else { 0 };
+        let in_memory = decoded + kept + pixels * 4 * depth + 2 * canvas + frame + encoded;
+        // Decoded photo (twice while rotating), the frame and a few strips
+        let strips = width as u64 * STRIP_ROWS as u64 * 4 * depth * 4;
+        let streaming = decoded * if rotated { 2 } else { 1 } + frame + strips + encoded;
+
+        if in_memory <= budget {
+            Ok(MemoryPlan::InMemory)
+        } else if format != OutputFormat::WebP && streaming <= budget {
+            Ok(MemoryPlan::Streaming)
+        } else {
+            let needed = if format == OutputFormat::WebP { in_memory } else { streaming };
+            Err(format!(
+                "{}x{} image needs about {} MiB, more than the {} MiB memory budget",
+                width,
+                height,
+                needed.div_ceil(MIB),
+                budget / MIB
+            ))
+        }
+    }
+}
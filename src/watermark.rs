@@ -1,17 +1,36 @@
 use base64::Engine;
 use base64::engine::general_purpose::STANDARD as BASE64;
-use image::{DynamicImage, GenericImageView, ImageBuffer, Rgba, imageops};
+use image::{DynamicImage, GenericImageView, ImageBuffer, ImageDecoder, ImageEncoder, ImageReader, Limits, Pixel, Rgba, imageops};
 use image::codecs::jpeg::JpegEncoder;
+use image::codecs::png::PngEncoder;
+use image::codecs::webp::WebPEncoder;
+use resvg::usvg::fontdb;
 use std::fs;
-use std::io::Cursor;
-use std::path::Path;
+use std::io::{BufRead, BufReader, BufWriter, Cursor, Read, Seek, Write};
+use std::borrow::Cow;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
+use crate::brands::BrandMatch;
+use crate::c2pa;
+use crate::color::{self, ColorPipeline};
 use crate::designs::{
     self, LandscapeParams, PortraitParams,
 };
+use crate::engine::{FramedPhoto, FrostOptions, LogoSource, OutputFormat, Watermarker, DEFAULT_MEMORY_BUDGET};
+use crate::stream::{self, MemoryPlan, PlannedOutput, StreamedCanvas};
+use crate::fonts;
+use crate::histogram::Histogram;
+use crate::invisible;
+use crate::xmp::{self, Xmp, XmpInfo};
+use crate::overrides::{Overrides, Sidecars};
+use crate::qr::{self, QrCode};
+use crate::rights::Attribution;
+use crate::palette::{self, FrameColor};
 
 // ─── EXIF Parsing ─────────────────────────────────────
 
+#[derive(Clone)]
 pub struct ExifInfo {
     pub camera_model: String,
     pub camera_make: String,
@@ -19,24 +38,51 @@ pub struct ExifInfo {
     pub f_number: String,
     pub exposure_time: String,
     pub iso: String,
+    /// EXIF lens model, empty when not recorded
+    pub lens_model: String,
     pub date_time: String,
     pub orientation: u32,
+    /// Title, caption, rating and keywords from embedded XMP, overlaid by
+    /// an XMP sidecar for photos read from a path
+    pub xmp: XmpInfo,
 }
 
 pub fn parse_exif(image_data: &[u8]) -> ExifInfo {
+    read_exif(&mut Cursor::new(image_data))
+}
+
+/// Model shown when the file has no EXIF model.
+pub(crate) const UNKNOWN_CAMERA: &str = "Unknown Camera";
+
+/// The EXIF model, or `""` when the file has none.
+pub(crate) fn known_model(exif: &ExifInfo) -> &str {
+    match exif.camera_model.as_str() {
+        UNKNOWN_CAMERA => "",
+        model => model,
+    }
+}
+
+pub(crate) fn read_exif<R: BufRead + Seek>(reader: &mut R) -> ExifInfo {
     let mut info = ExifInfo {
-        camera_model: "Unknown Camera".into(),
+        camera_model: UNKNOWN_CAMERA.into(),
         camera_make: String::new(),
         focal_length: String::new(),
         f_number: String::new(),
         exposure_time: String::new(),
         iso: String::new(),
+        lens_model: String::new(),
         date_time: String::new(),
         orientation: 1,
+        xmp: xmp::read_embedded(reader)
+            .and_then(|packet| Xmp::parse(&packet).map_err(|e| log::warn!("Ignoring embedded XMP: {}", e)).ok())
+            .map(|xmp| xmp.info())
+            .unwrap_or_default(),
     };
 
-    let reader = exif::Reader::new();
-    let Ok(exif) = reader.read_from_container(&mut Cursor::new(image_data)) else {
+    if reader.rewind().is_err() {
+        return info;
+    }
+    let Ok(exif) = exif::Reader::new().read_from_container(reader) else {
         return info;
     };
 
@@ -103,9 +149,18 @@ pub fn parse_exif(image_data: &[u8]) -> ExifInfo {
             }
         }
     }
+    // LensModel
+    if let Some(f) = exif.get_field(exif::Tag::LensModel, exif::In::PRIMARY) {
+        info.lens_model = f.display_value().to_string().trim_matches('"').trim().to_string();
+    }
     // DateTimeOriginal
     if let Some(f) = exif.get_field(exif::Tag::DateTimeOriginal, exif::In::PRIMARY) {
-        let raw = f.display_value().to_string().trim_matches('"').to_string();
+        // Use the raw ASCII value; display_value() already reformats it as
+        // "2024-01-15 14:30:00", which the conversion below would mangle
+        let raw = match &f.value {
+            exif::Value::Ascii(v) if !v.is_empty() => String::from_utf8_lossy(&v[0]).to_string(),
+            _ => f.display_value().to_string().trim_matches('"').to_string(),
+        };
         // Convert "2024:01:15 14:30:00" → "2024.01.15 14:30:00"
         info.date_time = raw.replacen(':', ".", 1).replacen(':', ".", 1);
     }
@@ -123,7 +178,7 @@ pub fn parse_exif(image_data: &[u8]) -> ExifInfo {
 
 // ─── Image orientation ───────────────────────────────
 
-fn auto_orient(img: DynamicImage, orientation: u32) -> DynamicImage {
+pub(crate) fn auto_orient(img: DynamicImage, orientation: u32) -> DynamicImage {
     match orientation {
         2 => img.fliph(),
         3 => img.rotate180(),
@@ -138,52 +193,84 @@ fn auto_orient(img: DynamicImage, orientation: u32) -> DynamicImage {
 
 // ─── Logo lookup ──────────────────────────────────────
 
-fn find_brand_logo(camera_make: &str, models_dir: &Path) -> Option<String> {
-    let Ok(entries) = fs::read_dir(models_dir) else {
-        return None;
-    };
+/// Below this background luminance black text and logos lose contrast against
+/// white ones (the WCAG crossover point), so the light logo variant is used.
+const DARK_BACKGROUND_LUMINANCE: f64 = 0.179;
 
-    let available: Vec<String> = entries
-        .filter_map(|e| e.ok())
-        .filter_map(|e| {
-            let name = e.file_name().to_string_lossy().to_string();
-            if name.ends_with(".png") {
-                Some(name.to_lowercase())
-            } else {
-                None
-            }
-        })
-        .collect();
-
-    let make_words: Vec<String> = camera_make
-        .to_lowercase()
-        .split_whitespace()
-        .map(|s| s.to_string())
-        .collect();
+fn find_brand_logo(
+    brand: Option<&BrandMatch>,
+    models_dir: &Path,
+    background_luminance: f64,
+) -> Option<String> {
+    let brand = brand?;
+    let dark_background = background_luminance < DARK_BACKGROUND_LUMINANCE;
 
-    for word in &make_words {
-        if word.len() <= 2 {
-            continue;
+    // Prefer a dedicated light variant (e.g. `sony-light.png`) on dark frames
+    if dark_background {
+        for logo in &brand.logos {
+            if let Ok(data) = fs::read(models_dir.join(format!("{}-light.png", logo))) {
+                return Some(png_data_uri(&data));
+            }
         }
-        for logo in &available {
-            let logo_name = logo.trim_end_matches(".png");
-            if logo_name == word || word.contains(logo_name) || logo_name.contains(word.as_str()) {
-                let logo_path = models_dir.join(logo);
-                if let Ok(data) = fs::read(&logo_path) {
-                    let b64 = BASE64.encode(&data);
-                    return Some(format!("data:image/png;base64,{}", b64));
+    }
+
+    for logo in &brand.logos {
+        let logo_path = models_dir.join(format!("{}.png", logo));
+        if let Ok(data) = fs::read(&logo_path) {
+            if dark_background {
+                if let Some(light) = lighten_monochrome_logo(&data) {
+                    return Some(png_data_uri(&light));
                 }
             }
+            return Some(png_data_uri(&data));
         }
     }
     None
 }
 
+fn png_data_uri(data: &[u8]) -> String {
+    format!("data:image/png;base64,{}", BASE64.encode(data))
+}
+
+/// Inverts a dark monochrome logo so it stays visible on a dark frame.
+/// Returns `None` for coloured or already light logos, which are kept as-is.
+fn lighten_monochrome_logo(data: &[u8]) -> Option<Vec<u8>> {
+    let mut logo = image::load_from_memory(data).ok()?.to_rgba8();
+
+    let (mut count, mut chroma, mut luma) = (0u64, 0u64, 0u64);
+    for px in logo.pixels().filter(|px| px[3] > 128) {
+        let max = px[0].max(px[1]).max(px[2]) as u64;
+        let min = px[0].min(px[1]).min(px[2]) as u64;
+        count += 1;
+        chroma += max - min;
+        luma += (px[0] as u64 * 299 + px[1] as u64 * 587 + px[2] as u64 * 114) / 1000;
+    }
+    if count == 0 || chroma / count > 16 || luma / count >= 128 {
+        return None;
+    }
+
+    for px in logo.pixels_mut() {
+        px[0] = 255 - px[0];
+        px[1] = 255 - px[1];
+        px[2] = 255 - px[2];
+    }
+
+    let mut out = Cursor::new(Vec::new());
+    logo.write_to(&mut out, image::ImageFormat::Png).ok()?;
+    Some(out.into_inner())
+}
+
 // ─── SVG rendering ────────────────────────────────────
 
-fn render_svg_to_rgba(svg_string: &str) -> Result<(Vec<u8>, u32, u32), String> {
-    let mut opt = resvg::usvg::Options::default();
-    opt.fontdb_mut().load_system_fonts();
+pub(crate) fn render_svg_to_rgba(
+    svg_string: &str,
+    fontdb: &Arc<fontdb::Database>,
+) -> Result<(Vec<u8>, u32, u32), String> {
+    let opt = resvg::usvg::Options {
+        fontdb: fontdb.clone(),
+        font_resolver: fonts::resolver(),
+        ..Default::default()
+    };
 
     let tree = resvg::usvg::Tree::from_str(svg_string, &opt)
         .map_err(|e| format!("SVG parse error: {}", e))?;
@@ -197,40 +284,81 @@ fn render_svg_to_rgba(svg_string: &str) -> Result<(Vec<u8>, u32, u32), String> {
     let width = pixmap.width();
     let height = pixmap.height();
     // Convert from premultiplied RGBA to straight RGBA
-    let data = pixmap.take();
+    let data = pixmap
+        .pixels()
+        .iter()
+        .flat_map(|px| {
+            let c = px.demultiply();
+            [c.red(), c.green(), c.blue(), c.alpha()]
+        })
+        .collect();
 
     Ok((data, width, height))
 }
 
-// ─── Main processing ─────────────────────────────────
+// ─── Frame layout ────────────────────────────────────
 
-pub fn add_watermark_frame(
-    input_path: &Path,
-    output_path: &Path,
-    design_id: &str,
-    photographer_name: &str,
-    models_dir: &Path,
-) -> Result<(), String> {
-    // Read file
-    let image_data = fs::read(input_path)
-        .map_err(|e| format!("Failed to read {}: {}", input_path.display(), e))?;
-
-    // Parse EXIF
-    let exif_info = parse_exif(&image_data);
+/// Frame SVG for one photo and the height it adds below it.
+pub(crate) struct FrameSvg {
+    pub svg: String,
+    /// Height of the frame strip appended below the photo
+    pub height: u32,
+}
 
-    // Load image
-    let img = image::load_from_memory(&image_data)
-        .map_err(|e| format!("Failed to decode image: {}", e))?;
+/// What the frame takes from the photo's pixels.
+#[derive(Debug, Default)]
+pub(crate) struct PhotoSamples {
+    /// Background of adaptive frames, `None` for the design's own palette
+    pub background: Option<[u8; 3]>,
+    /// Swatches of the photo's palette, most common first
+    pub swatches: Vec<[u8; 3]>,
+    pub histogram: Option<Histogram>,
+}
 
-    // Auto-orient
-    let img = auto_orient(img, exif_info.orientation);
-    let (image_width, image_height) = img.dimensions();
+impl PhotoSamples {
+    /// Samples only what the options ask for; `frosted` frames get their
+    /// adaptive background dimmed like the photo behind them.
+    fn sample(
+        image: &DynamicImage,
+        icc_profile: Option<&[u8]>,
+        wm: &Watermarker,
+        frosted: bool,
+    ) -> Result<Self, String> {
+        let background = match wm.options.frame_color {
+            FrameColor::Design => None,
+            FrameColor::Adaptive => {
+                let color = palette::dominant_color(image, icc_profile)?;
+                Some(if frosted { dim(color, wm.options.frost.dim) } else { color })
+            }
+        };
+        let swatches = palette::palette_colors(image, icc_profile, wm.options.swatches)?;
+        let histogram = wm
+            .options
+            .histogram
+            .map(|style| Histogram::compute(image, icc_profile, style))
+            .transpose()?;
+        Ok(Self { background, swatches, histogram })
+    }
 
-    // Camera info
-    let mut camera_info = exif_info.camera_model.clone();
-    if camera_info.contains("iPhone") {
-        camera_info = format!("Apple {}", camera_info);
+    fn needed(wm: &Watermarker) -> bool {
+        wm.options.frame_color != FrameColor::Design
+            || wm.options.swatches > 0
+            || wm.options.histogram.is_some()
     }
+}
+
+pub(crate) fn build_frame_svg(
+    wm: &Watermarker,
+    exif_info: &ExifInfo,
+    image_width: u32,
+    image_height: u32,
+    sampled: &PhotoSamples,
+    per_image: &PerImage,
+) -> FrameSvg {
+    let background = sampled.background;
+    let design_id = wm.design_id.as_str();
+    let link = per_image.link.as_deref();
+    let overrides = &per_image.overrides;
 
     // Exposure info
     let mut parts: Vec<String> = Vec::new();
@@ -246,20 +374,65 @@ pub fn add_watermark_frame(
     if !exif_info.iso.is_empty() {
         parts.push(format!("ISO {}", exif_info.iso));
     }
+    if overrides.hide_exposure == Some(true) {
+        parts.clear();
+    }
     let exposure_info = parts.join(" | ");
 
+    // A caption leads the date line, so designs that show the date show it too
+    let xmp = &exif_info.xmp;
+    let xmp_title = if xmp.title.is_empty() { &xmp.caption } else { &xmp.title };
+    let caption = overrides
+        .caption
+        .as_deref()
+        .or_else(|| (wm.options.show_title && !xmp_title.is_empty()).then_some(xmp_title.as_str()));
+    let date = overrides.date.as_deref().map(designs::escape).unwrap_or_else(|| exif_info.date_time.clone());
+    let date_line = match caption.map(designs::escape) {
+        Some(caption) if !date.is_empty() => format!("{} · {}", caption, date),
+        Some(caption) => caption,
+        None => date,
+    };
+
     // Frame dimensions
     let frame_height = (image_height as f64 * 0.1).round();
     let font_size = f64::max(14.0, frame_height * 0.3);
     let small_font_size = f64::max(11.0, font_size * 0.75);
+    let font_family = fonts::family_for_design(design_id, wm.options.font_family.as_deref());
 
     // Find brand logo
-    let brand_logo = find_brand_logo(&exif_info.camera_make, models_dir);
+    let camera_make = overrides.make.as_deref().unwrap_or(&exif_info.camera_make);
+    let brand = wm.brands.resolve(camera_make, &exif_info.camera_model);
+    let camera_info = designs::escape(&match &overrides.camera {
+        Some(camera) => camera.clone(),
+        None => wm.camera_names.display_name(camera_make, &exif_info.camera_model, brand.as_ref(), wm.options.model_names),
+    });
+    let palette = background.map(palette::adaptive_palette);
+    let swatches: Vec<String> = sampled.swatches.iter().map(|&c| palette::hex(c)).collect();
+    let qr = link.and_then(|link| {
+        QrCode::encode(link)
+            .map_err(|e| log::warn!("{}; leaving the code out", e))
+            .ok()
+    });
+    let background_luminance = designs::relative_luminance(
+        background.unwrap_or_else(|| designs::get_background_color(design_id)),
+    );
+    let brand_logo = match &wm.logos {
+        LogoSource::Directory(models_dir) => {
+            find_brand_logo(brand.as_ref(), models_dir, background_luminance)
+        }
+        LogoSource::None => None,
+    };
+    let logo_text_fill = match &palette {
+        Some(palette) => palette.text.as_str(),
+        None if background_luminance < DARK_BACKGROUND_LUMINANCE => "#EEEEEE",
+        None => "#333333",
+    };
+    let brand_name = designs::escape(brand.as_ref().map_or(camera_make, |b| b.brand.name.as_str()));
 
     let is_portrait = image_height > image_width;
 
     // Generate SVG watermark
-    let svg_string = if is_portrait {
+    let svg = if is_portrait {
         let center_x = image_width as f64 / 2.0;
 
         // Build logo element
@@ -275,8 +448,8 @@ pub fn add_watermark_frame(
             )
         } else {
             format!(
-                r##"<text x="{}" y="{}" font-family="Arial, sans-serif" font-size="{}" font-weight="700" fill="#333333" text-anchor="middle" dominant-baseline="central">{}</text>"##,
-                center_x, frame_height * 0.8, font_size, exif_info.camera_make
+                r##"<text x="{}" y="{}" font-family="{}" font-size="{}" font-weight="700" fill="{}" text-anchor="middle" dominant-baseline="central">{}</text>"##,
+                center_x, frame_height * 0.8, font_family, font_size, logo_text_fill, brand_name
             )
         };
 
@@ -287,10 +460,17 @@ pub fn add_watermark_frame(
             logo_element,
             camera_info: camera_info.clone(),
             exposure_info: exposure_info.clone(),
-            date_time_string: exif_info.date_time.clone(),
+            date_time_string: date_line.clone(),
             font_size,
             small_font_size,
-            photographer_name: photographer_name.to_string(),
+            font_family: font_family.clone(),
+            photographer_name: designs::escape(&wm.options.photographer_name),
+            palette: palette.clone(),
+            swatches: swatches.clone(),
+            histogram: sampled.histogram.clone(),
+            qr: qr.clone(),
+            title: designs::escape(&xmp.title),
+            caption: designs::escape(&xmp.caption),
         };
 
         designs::render_portrait(design_id, &params)
@@ -313,7 +493,7 @@ pub fn add_watermark_frame(
         let left_text_x = left_padding;
         let right_text_x = divider_x - font_size;
 
-        let (exposure_y, date_y) = if !exposure_info.is_empty() && !exif_info.date_time.is_empty() {
+        let (exposure_y, date_y) = if !exposure_info.is_empty() && !date_line.is_empty() {
             let line_spacing = small_font_size * 1.5;
             (
                 center_y - line_spacing / 2.0 + text_adjustment,
@@ -333,8 +513,8 @@ pub fn add_watermark_frame(
             )
         } else {
             format!(
-                r##"<text x="{}" y="{}" font-family="Arial, sans-serif" font-size="{}" font-weight="700" fill="#333333" text-anchor="middle" dominant-baseline="central">{}</text>"##,
-                logo_x + logo_width / 2.0, center_y + text_adjustment, font_size, exif_info.camera_make
+                r##"<text x="{}" y="{}" font-family="{}" font-size="{}" font-weight="700" fill="{}" text-anchor="middle" dominant-baseline="central">{}</text>"##,
+                logo_x + logo_width / 2.0, center_y + text_adjustment, font_family, font_size, logo_text_fill, brand_name
             )
         };
 
@@ -347,7 +527,7 @@ pub fn add_watermark_frame(
             logo_element,
             camera_info: camera_info.clone(),
             exposure_info: exposure_info.clone(),
-            date_time_string: exif_info.date_time.clone(),
+            date_time_string: date_line.clone(),
             left_text_x,
             right_text_x,
             exposure_y,
@@ -357,76 +537,764 @@ pub fn add_watermark_frame(
             adjusted_divider_bottom,
             font_size,
             small_font_size,
-            photographer_name: photographer_name.to_string(),
+            font_family: font_family.clone(),
+            photographer_name: designs::escape(&wm.options.photographer_name),
+            palette: palette.clone(),
+            swatches: swatches.clone(),
+            histogram: sampled.histogram.clone(),
+            qr: qr.clone(),
+            title: designs::escape(&xmp.title),
+            caption: designs::escape(&xmp.caption),
         };
 
         designs::render_landscape(design_id, &params)
     };
 
-    // Render SVG to pixels
-    let (svg_pixels, svg_w, svg_h) = render_svg_to_rgba(&svg_string)?;
 
-    // Calculate actual frame height for the final image
-    let final_frame_height = designs::get_frame_svg_height(design_id, frame_height, is_portrait);
+    FrameSvg {
+        svg,
+        height: designs::get_frame_svg_height(design_id, frame_height, is_portrait),
+    }
+}
+
+// ─── Main processing ─────────────────────────────────
+
+#[derive(Clone)]
+pub(crate) struct DecodedImage {
+    pub exif: ExifInfo,
+    pub image: DynamicImage,
+    /// Embedded ICC profile, if the file has one
+    pub icc_profile: Option<Vec<u8>>,
+}
+
+pub(crate) struct FramedImage {
+    /// RGBA8, or RGBA16 when the source has more than 8 bits per channel
+    pub canvas: DynamicImage,
+    /// ICC profile describing the canvas pixels, embedded on encode
+    pub icc_profile: Option<Vec<u8>>,
+}
+
+/// `image`'s TIFF decoder looks the ICC tag up under the wrong key, so read
+/// it with the `tiff` crate directly.
+fn tiff_icc_profile<R: Read + Seek>(reader: R) -> Option<Vec<u8>> {
+    let mut decoder = tiff::decoder::Decoder::new(reader).ok()?;
+    decoder.get_tag_u8_vec(tiff::tags::Tag::IccProfile).ok()
+}
+
+/// Opens a decoder for `reader` and reads the embedded ICC profile.
+pub(crate) fn open_decoder<R: BufRead + Seek>(
+    reader: &mut R,
+    limits: Limits,
+) -> Result<(impl ImageDecoder + '_, Option<Vec<u8>>), String> {
+    let io_err = |e: std::io::Error| format!("Failed to read image: {}", e);
+    let decode_err = |e: image::ImageError| format!("Failed to decode image: {}", e);
+
+    reader.rewind().map_err(io_err)?;
+    let format = ImageReader::new(&mut *reader).with_guessed_format().map_err(io_err)?.format();
+    let tiff_icc = if format == Some(image::ImageFormat::Tiff) {
+        reader.rewind().map_err(io_err)?;
+        tiff_icc_profile(&mut *reader)
+    } else {
+        None
+    };
+
+    reader.rewind().map_err(io_err)?;
+    let mut image_reader = ImageReader::new(reader).with_guessed_format().map_err(io_err)?;
+    image_reader.limits(limits);
+    let mut decoder = image_reader.into_decoder().map_err(decode_err)?;
+    let icc_profile = decoder.icc_profile().ok().flatten().or(tiff_icc);
+    Ok((decoder, icc_profile))
+}
+
+/// Size of the photo once the EXIF orientation is applied.
+fn oriented_dimensions((width, height): (u32, u32), orientation: u32) -> (u32, u32) {
+    if (5..=8).contains(&orientation) {
+        (height, width)
+    } else {
+        (width, height)
+    }
+}
+
+/// Parses EXIF and decodes the photo, applying the EXIF orientation. Fails
+/// before decoding if the image can't be framed within the memory budget.
+fn decode<R: BufRead + Seek>(
+    reader: &mut R,
+    output: PlannedOutput,
+    budget: u64,
+) -> Result<(DecodedImage, MemoryPlan), String> {
+    reader.rewind().map_err(|e| format!("Failed to read image: {}", e))?;
+    let exif = read_exif(reader);
+
+    let mut limits = Limits::no_limits();
+    limits.max_alloc = Some(budget);
+    let (decoder, icc_profile) = open_decoder(reader, limits)?;
+
+    let (width, height) = oriented_dimensions(decoder.dimensions(), exif.orientation);
+    let rotated = exif.orientation != 1;
+    let plan = MemoryPlan::choose(width, height, decoder.color_type(), rotated, output, budget)?;
+
+    let img = DynamicImage::from_decoder(decoder)
+        .map_err(|e| format!("Failed to decode image: {}", e))?;
+    let image = auto_orient(img, exif.orientation);
+    Ok((DecodedImage { exif, image, icc_profile }, plan))
+}
+
+/// EXIF, with the sidecar overrides applied, and oriented size of the photo
+/// at `input_path`; only the header is decoded.
+pub(crate) fn read_photo_facts(input_path: &Path) -> Result<(ExifInfo, u32, u32), String> {
+    let mut reader = open_input(input_path)?;
+    let mut exif = read_exif(&mut reader);
+    Sidecars::load(input_path)?.apply_to(&mut exif);
+    let (decoder, _) = open_decoder(&mut reader, Limits::default())?;
+    let (width, height) = oriented_dimensions(decoder.dimensions(), exif.orientation);
+    Ok((exif, width, height))
+}
+
+/// Frame SVG for an encoded image; only the header is decoded.
+pub(crate) fn frame_svg_for(image_data: &[u8], wm: &Watermarker) -> Result<String, String> {
+    let mut reader = Cursor::new(image_data);
+    let exif = read_exif(&mut reader);
+    let (decoder, icc_profile) = open_decoder(&mut reader, Limits::default())?;
+    let (width, height) = oriented_dimensions(decoder.dimensions(), exif.orientation);
+    // Colours sampled from the photo need the pixels after all
+    let sampled = if PhotoSamples::needed(wm) {
+        let img = DynamicImage::from_decoder(decoder)
+            .map_err(|e| format!("Failed to decode image: {}", e))?;
+        let frosted = designs::uses_photo_backdrop(&wm.design_id);
+        PhotoSamples::sample(&img, icc_profile.as_deref(), wm, frosted)?
+    } else {
+        PhotoSamples::default()
+    };
+    let per_image = PerImage::new(wm, None, Overrides::default());
+    Ok(build_frame_svg(wm, &exif, width, height, &sampled, &per_image).svg)
+}
+
+/// Whether the photo carries more than 8 bits per channel (16-bit TIFF/PNG,
+/// float formats), in which case the canvas is composited at 16 bits.
+fn is_high_bit_depth(img: &DynamicImage) -> bool {
+    let color = img.color();
+    color.bytes_per_pixel() > color.channel_count()
+}
+
+/// What transparent parts of the photo and the frame are laid on: white, or
+/// nothing when the output keeps alpha.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Backdrop {
+    photo_white: bool,
+    frame_white: bool,
+}
 
-    // Create canvas with extended height
-    let new_height = image_height + final_frame_height;
-    let mut canvas: ImageBuffer<Rgba<u8>, Vec<u8>> =
-        ImageBuffer::from_pixel(image_width, new_height, Rgba([255, 255, 255, 255]));
+impl Backdrop {
+    pub(crate) fn new(format: OutputFormat, transparent_frame: bool) -> Self {
+        let alpha = format.supports_alpha();
+        Self { photo_white: !alpha, frame_white: !(alpha && transparent_frame) }
+    }
 
-    // Copy original image onto canvas
-    let img_rgba = img.to_rgba8();
-    imageops::overlay(&mut canvas, &img_rgba, 0, 0);
+    /// Sample value the photo area starts from (white or fully transparent).
+    pub(crate) fn photo<S: image::Primitive>(&self) -> S {
+        if self.photo_white { S::DEFAULT_MAX_VALUE } else { S::DEFAULT_MIN_VALUE }
+    }
+
+    /// Sample value the frame area starts from.
+    pub(crate) fn frame<S: image::Primitive>(&self) -> S {
+        if self.frame_white { S::DEFAULT_MAX_VALUE } else { S::DEFAULT_MIN_VALUE }
+    }
+}
+
+/// Stacks the photo and the rendered frame on the backdrop, then converts
+/// each part to the output colour space.
+fn composite<S>(
+    mut photo: ImageBuffer<Rgba<S>, Vec<S>>,
+    frame: Option<ImageBuffer<Rgba<S>, Vec<S>>>,
+    frame_height: u32,
+    colors: &ColorPipeline,
+    backdrop: Backdrop,
+) -> Result<ImageBuffer<Rgba<S>, Vec<S>>, String>
+where
+    S: color::Sample + image::Primitive,
+    Rgba<S>: Pixel<Subpixel = S>,
+{
+    let (image_width, image_height) = photo.dimensions();
+    let frame_start = image_height as usize * image_width as usize * 4;
+    let mut canvas = ImageBuffer::from_pixel(
+        image_width,
+        image_height + frame_height,
+        Rgba([backdrop.photo(); 4]),
+    );
+    canvas.as_mut()[frame_start..].fill(backdrop.frame());
+
+    colors.convert_photo(photo.as_mut(), image_width)?;
+    imageops::overlay(&mut canvas, &photo, 0, 0);
+    if let Some(frame) = frame {
+        imageops::overlay(&mut canvas, &frame, 0, image_height as i64);
+    }
 
-    // Copy SVG watermark below the image
-    if svg_w > 0 && svg_h > 0 {
+    // The frame was drawn in sRGB; bring it into the photo's output space
+    colors.convert_frame(&mut canvas.as_mut()[frame_start..], image_width)?;
+    Ok(canvas)
+}
+
+fn dim(rgb: [u8; 3], amount: f32) -> [u8; 3] {
+    rgb.map(|c| (c as f32 * (1.0 - amount)).round() as u8)
+}
+
+/// Background for designs drawn over the photo: its bottom rows mirrored
+/// into the frame, heavily blurred and dimmed. Built in sRGB like the frame
+/// SVG, since it is converted to the output space with it.
+fn frosted_backdrop(
+    decoded: &DecodedImage,
+    frame_height: u32,
+    frost: &FrostOptions,
+) -> Result<ImageBuffer<Rgba<u8>, Vec<u8>>, String> {
+    let (width, height) = decoded.image.dimensions();
+    let rows = frame_height.min(height);
+
+    // Blur a reduced copy; a heavy blur hides the lost detail anyway
+    let radius = (frost.blur * frame_height as f32).max(1.0);
+    let scale = (radius / 8.0).max(1.0);
+    let small_width = ((width as f32 / scale).round() as u32).max(1);
+    let small_height = ((rows as f32 / scale).round() as u32).max(1);
+    let mut small = decoded
+        .image
+        .crop_imm(0, height - rows, width, rows)
+        .resize_exact(small_width, small_height, imageops::FilterType::Triangle)
+        .into_rgba8();
+    ColorPipeline::new(color::ColorSpace::Srgb, decoded.icc_profile.as_deref())?
+        .convert_photo(small.as_mut(), small_width)?;
+
+    let mirrored = imageops::flip_vertical(&small);
+    let blurred = imageops::fast_blur(&mirrored, radius / scale);
+    let mut backdrop =
+        imageops::resize(&blurred, width, frame_height, imageops::FilterType::Triangle);
+    for px in backdrop.pixels_mut() {
+        let [r, g, b] = dim([px[0], px[1], px[2]], frost.dim);
+        *px = Rgba([r, g, b, 255]);
+    }
+    Ok(backdrop)
+}
+
+/// The rendered frame strip, before it is laid on the canvas.
+struct RenderedFrame {
+    height: u32,
+    pixels: Option<ImageBuffer<Rgba<u8>, Vec<u8>>>,
+}
+
+fn render_frame(
+    decoded: &DecodedImage,
+    wm: &Watermarker,
+    per_image: &PerImage,
+) -> Result<(RenderedFrame, ColorPipeline), String> {
+    let (image_width, image_height) = decoded.image.dimensions();
+    let frosted = designs::uses_photo_backdrop(&wm.design_id);
+    // Frosted text sits on the dimmed photo, not the photo itself
+    let sampled = PhotoSamples::sample(&decoded.image, decoded.icc_profile.as_deref(), wm, frosted)?;
+    let frame = build_frame_svg(wm, &decoded.exif, image_width, image_height, &sampled, per_image);
+    let colors = ColorPipeline::new(wm.options.color_space, decoded.icc_profile.as_deref())?;
+
+    // Render SVG to pixels
+    let (svg_pixels, svg_w, svg_h) = render_svg_to_rgba(&frame.svg, &wm.fontdb)?;
+    let pixels = if svg_w > 0 && svg_h > 0 {
         let watermark: ImageBuffer<Rgba<u8>, Vec<u8>> =
             ImageBuffer::from_raw(svg_w, svg_h, svg_pixels)
                 .ok_or("Failed to create watermark image buffer")?;
-        imageops::overlay(&mut canvas, &watermark, 0, image_height as i64);
+        Some(watermark)
+    } else {
+        None
+    };
+    let pixels = if frosted {
+        let mut backdrop = frosted_backdrop(decoded, frame.height, &wm.options.frost)?;
+        if let Some(svg) = &pixels {
+            imageops::overlay(&mut backdrop, svg, 0, 0);
+        }
+        Some(backdrop)
+    } else {
+        pixels
+    };
+
+    Ok((RenderedFrame { height: frame.height, pixels }, colors))
+}
+
+/// Returns the photo with the frame appended, converted to the output
+/// colour space.
+fn frame_image(
+    decoded: DecodedImage,
+    wm: &Watermarker,
+    format: OutputFormat,
+    per_image: &PerImage,
+) -> Result<FramedImage, String> {
+    let (frame, colors) = render_frame(&decoded, wm, per_image)?;
+    let backdrop = Backdrop::new(format, wm.options.transparent_frame);
+    let photo_height = decoded.image.height();
+
+    // Keep 16-bit masters at 16 bits; the 8-bit frame is upconverted
+    let canvas = if is_high_bit_depth(&decoded.image) {
+        let watermark = frame.pixels.map(|w| DynamicImage::ImageRgba8(w).into_rgba16());
+        let mut canvas = composite(decoded.image.into_rgba16(), watermark, frame.height, &colors, backdrop)?;
+        if let Some(mark) = &per_image.mark {
+            invisible::embed(&mut canvas, 0, photo_height, mark);
+        }
+        DynamicImage::ImageRgba16(canvas)
+    } else {
+        let mut canvas = composite(decoded.image.into_rgba8(), frame.pixels, frame.height, &colors, backdrop)?;
+        if let Some(mark) = &per_image.mark {
+            invisible::embed(&mut canvas, 0, photo_height, mark);
+        }
+        DynamicImage::ImageRgba8(canvas)
+    };
+
+    Ok(FramedImage { canvas, icc_profile: colors.icc_profile })
+}
+
+/// Writes the framed photo strip by strip, without a full canvas.
+fn stream_image<W: Write + Seek>(
+    decoded: &DecodedImage,
+    wm: &Watermarker,
+    format: OutputFormat,
+    per_image: &PerImage,
+    writer: &mut W,
+) -> Result<(), String> {
+    let (frame, colors) = render_frame(decoded, wm, per_image)?;
+    let icc = colors.icc_profile.as_deref();
+    let backdrop = Backdrop::new(format, wm.options.transparent_frame);
+
+    if is_high_bit_depth(&decoded.image) {
+        let pixels = frame.pixels.map(|w| DynamicImage::ImageRgba8(w).into_rgba16());
+        let canvas = StreamedCanvas::<u16>::new(&decoded.image, pixels, frame.height, &colors, backdrop, per_image.mark)?;
+        write_streamed(&canvas, format, icc, writer)
+    } else {
+        let canvas = StreamedCanvas::<u8>::new(&decoded.image, frame.pixels, frame.height, &colors, backdrop, per_image.mark)?;
+        write_streamed(&canvas, format, icc, writer)
     }
+}
 
-    // Save output
-    let ext = output_path
-        .extension()
-        .and_then(|e| e.to_str())
-        .unwrap_or("jpg")
-        .to_lowercase();
+fn write_streamed<S, W>(
+    canvas: &StreamedCanvas<S>,
+    format: OutputFormat,
+    icc_profile: Option<&[u8]>,
+    writer: &mut W,
+) -> Result<(), String>
+where
+    S: stream::CanvasSample,
+    Rgba<S>: Pixel<Subpixel = S>,
+    [S]: tiff::encoder::TiffValue,
+    W: Write + Seek,
+{
+    match format {
+        OutputFormat::Jpeg => stream::write_jpeg(canvas, icc_profile, writer),
+        OutputFormat::Png => stream::write_png(canvas, icc_profile, writer),
+        OutputFormat::Tiff => stream::write_tiff(canvas, icc_profile, writer),
+        OutputFormat::WebP => Err("WebP output needs the whole image in memory".into()),
+    }
+}
 
-    // Ensure output directory exists
-    if let Some(parent) = output_path.parent() {
-        fs::create_dir_all(parent)
-            .map_err(|e| format!("Failed to create output directory: {}", e))?;
+/// What a frame carries that depends on the photo's file name and sidecar.
+pub(crate) struct PerImage {
+    /// Link for the QR code, if the options ask for one
+    link: Option<String>,
+    /// Invisible watermark, if the options ask for one
+    mark: Option<invisible::Payload>,
+    /// Frame text from the photo's sidecar
+    overrides: Overrides,
+}
+
+impl PerImage {
+    /// `name` is the file name without extension, `None` for bytes.
+    fn new(wm: &Watermarker, name: Option<&str>, overrides: Overrides) -> Self {
+        let link = wm.options.qr_link.as_deref().and_then(|template| {
+            let link = qr::resolve_link(template, name);
+            if link.is_none() {
+                log::warn!("QR link {} needs a file name; leaving the code out", template);
+            }
+            link
+        });
+        let mark = wm.options.invisible_mark.map(|photographer_id| invisible::Payload {
+            photographer_id,
+            image_id: name.map(invisible::image_id).unwrap_or(0),
+        });
+        Self { link, mark, overrides }
+    }
+}
+
+/// Decodes an image from `reader`, frames it and writes it to `writer`.
+/// Images whose full canvas would exceed the memory budget are streamed.
+/// `name` is the photo's file name without extension, for QR links and
+/// the invisible watermark's image ID.
+pub(crate) fn process<R: BufRead + Seek, W: Write + Seek>(
+    reader: &mut R,
+    wm: &Watermarker,
+    format: OutputFormat,
+    name: Option<&str>,
+    writer: &mut W,
+) -> Result<(), String> {
+    let budget = wm.options.memory_budget.unwrap_or(DEFAULT_MEMORY_BUDGET);
+    let (decoded, plan) = decode(reader, planned_output(wm, format, vec![wm.design_id()]), budget)?;
+    write_decoded(Cow::Owned(decoded), plan, wm, format, name, &Overrides::default(), writer)
+}
+
+/// What `wm` frames a photo into, in each of `design_ids`, for planning its
+/// memory. Content credentials hash the whole JPEG, so it's encoded in
+/// memory first.
+fn planned_output<'a>(wm: &Watermarker, format: OutputFormat, design_ids: Vec<&'a str>) -> PlannedOutput<'a> {
+    let buffered = format == OutputFormat::Jpeg && wm.options.content_credentials.is_some();
+    PlannedOutput { format, keep_decoded: design_ids.len() > 1, design_ids, buffered }
+}
+
+/// Frames a decoded photo with `wm`, adjusted by the photo's `overrides`,
+/// and writes it to `writer`. A borrowed photo is copied for the in-memory
+/// path and read as-is when streaming.
+fn write_decoded<W: Write + Seek>(
+    decoded: Cow<'_, DecodedImage>,
+    plan: MemoryPlan,
+    wm: &Watermarker,
+    format: OutputFormat,
+    name: Option<&str>,
+    overrides: &Overrides,
+    writer: &mut W,
+) -> Result<(), String> {
+    let wm = &*overrides.watermarker(wm);
+    let per_image = PerImage::new(wm, name, overrides.clone());
+    let attribution = Attribution::new(&wm.options.photographer_name, &wm.options.rights);
+    let signer = match &wm.options.content_credentials {
+        Some(signer) if format == OutputFormat::Jpeg => Some(signer),
+        Some(_) => {
+            log::warn!("Content credentials are only added to JPEG output");
+            None
+        }
+        None => None,
+    };
+    let tagged = attribution.applies_to(format);
+    let Some(signer) = signer else {
+        if !tagged {
+            return write_framed(decoded, plan, wm, format, &per_image, writer);
+        }
+        let mut tagging = attribution.writer(format, writer);
+        write_framed(decoded, plan, wm, format, &per_image, &mut tagging)?;
+        return tagging.finish();
+    };
+
+    // The manifest hashes the finished file, metadata included, so encode it first
+    let provenance = c2pa::Provenance {
+        creator: wm.options.photographer_name.clone(),
+        camera_make: decoded.exif.camera_make.clone(),
+        // The manifest only states what the EXIF records
+        camera_model: known_model(&decoded.exif).to_string(),
+    };
+    let mut encoded = Cursor::new(Vec::new());
+    if tagged {
+        let mut tagging = attribution.writer(format, &mut encoded);
+        write_framed(decoded, plan, wm, format, &per_image, &mut tagging)?;
+        tagging.finish()?;
+    } else {
+        write_framed(decoded, plan, wm, format, &per_image, &mut encoded)?;
     }
+    c2pa::sign_jpeg(encoded.get_ref(), &provenance, signer, writer)
+}
 
-    match ext.as_str() {
-        "jpg" | "jpeg" => {
-            let file = fs::File::create(output_path)
-                .map_err(|e| format!("Failed to create output file: {}", e))?;
-            let mut buf = std::io::BufWriter::new(file);
-            let encoder = JpegEncoder::new_with_quality(&mut buf, 95);
-            canvas.write_with_encoder(encoder)
-                .map_err(|e| format!("Failed to write JPEG: {}", e))?;
+fn write_framed<W: Write + Seek>(
+    decoded: Cow<'_, DecodedImage>,
+    plan: MemoryPlan,
+    wm: &Watermarker,
+    format: OutputFormat,
+    per_image: &PerImage,
+    writer: &mut W,
+) -> Result<(), String> {
+    match plan {
+        MemoryPlan::InMemory => {
+            encode_image(&frame_image(decoded.into_owned(), wm, format, per_image)?, format, writer)
         }
-        "png" => {
-            canvas.save(output_path)
-                .map_err(|e| format!("Failed to save PNG: {}", e))?;
+        MemoryPlan::Streaming => {
+            log::info!(
+                "Streaming {}x{} image to stay within the memory budget",
+                decoded.image.width(),
+                decoded.image.height()
+            );
+            stream_image(&decoded, wm, format, per_image, writer)
         }
-        "webp" => {
-            canvas.save(output_path)
-                .map_err(|e| format!("Failed to save WebP: {}", e))?;
+    }
+}
+
+fn with_icc<E: ImageEncoder>(mut encoder: E, icc_profile: Option<&[u8]>) -> E {
+    if let Some(icc) = icc_profile {
+        if let Err(e) = encoder.set_icc_profile(icc.to_vec()) {
+            log::warn!("ICC profile not embedded: {}", e);
         }
-        _ => {
-            // Default: save as JPEG
-            let file = fs::File::create(output_path)
-                .map_err(|e| format!("Failed to create output file: {}", e))?;
-            let mut buf = std::io::BufWriter::new(file);
-            let encoder = JpegEncoder::new_with_quality(&mut buf, 95);
-            canvas.write_with_encoder(encoder)
-                .map_err(|e| format!("Failed to write output: {}", e))?;
+    }
+    encoder
+}
+
+/// Writes an RGB TIFF (16-bit for high-bit-depth canvases) with the ICC
+/// profile, which `image`'s TIFF encoder can't embed. Shares the streamed
+/// path's writer, so both are compressed alike.
+fn encode_tiff<W: Write + Seek>(
+    canvas: &DynamicImage,
+    icc_profile: Option<&[u8]>,
+    writer: &mut W,
+) -> Result<(), String> {
+    match canvas {
+        DynamicImage::ImageRgba16(rgba) => stream::write_tiff(rgba, icc_profile, writer),
+        DynamicImage::ImageRgba8(rgba) => stream::write_tiff(rgba, icc_profile, writer),
+        _ => stream::write_tiff(&canvas.to_rgba8(), icc_profile, writer),
+    }
+}
+
+fn encode_image<W: Write + Seek>(
+    framed: &FramedImage,
+    format: OutputFormat,
+    writer: &mut W,
+) -> Result<(), String> {
+    let canvas = &framed.canvas;
+    let icc = framed.icc_profile.as_deref();
+    match format {
+        OutputFormat::Jpeg => {
+            // JPEG has no alpha channel and only 8 bits per channel
+            let rgb = canvas.to_rgb8();
+            let encoder = with_icc(JpegEncoder::new_with_quality(writer, 95), icc);
+            rgb.write_with_encoder(encoder)
+                .map_err(|e| format!("Failed to write JPEG: {}", e))
         }
+        OutputFormat::Png => canvas
+            .write_with_encoder(with_icc(PngEncoder::new(writer), icc))
+            .map_err(|e| format!("Failed to save PNG: {}", e)),
+        OutputFormat::WebP => canvas
+            .to_rgba8()
+            .write_with_encoder(with_icc(WebPEncoder::new_lossless(writer), icc))
+            .map_err(|e| format!("Failed to save WebP: {}", e)),
+        OutputFormat::Tiff => encode_tiff(canvas, icc, writer),
+    }
+}
+
+/// Frames the photo at `input_path`, applying the overrides in its sidecar
+/// (see [`Sidecars::load`]).
+pub fn add_watermark_frame(
+    input_path: &Path,
+    output_path: &Path,
+    wm: &Watermarker,
+) -> Result<(), String> {
+    let sidecars = Sidecars::load(input_path)?;
+    let overrides = &sidecars.overrides;
+    let mut reader = open_input(input_path)?;
+    // Overrides change the design and photographer, never the budget
+    let budget = wm.options.memory_budget.unwrap_or(DEFAULT_MEMORY_BUDGET);
+    let format = OutputFormat::from_path(output_path);
+    let name = input_path.file_stem().and_then(|n| n.to_str());
+    write_output(output_path, |writer| {
+        let design_id = overrides.design.as_deref().unwrap_or(wm.design_id());
+        let (mut decoded, plan) = decode(&mut reader, planned_output(wm, format, vec![design_id]), budget)?;
+        sidecars.merge_xmp_into(&mut decoded.exif);
+        write_decoded(Cow::Owned(decoded), plan, wm, format, name, overrides, writer)
+    })
+}
+
+/// Frames `input_path` once per watermarker, decoding it only once. The
+/// memory plan follows the first output's format and budget. Fails as a
+/// whole if the photo can't be read, otherwise returns each output's result.
+pub(crate) fn add_watermark_frames(
+    input_path: &Path,
+    outputs: &[(&Watermarker, PathBuf)],
+) -> Result<FramedPhoto, String> {
+    let Some((first, first_path)) = outputs.first() else {
+        return Ok(FramedPhoto::default());
+    };
+    let mut sidecars = Sidecars::load(input_path)?;
+    if outputs.len() > 1 && sidecars.overrides.design.take().is_some() {
+        log::warn!("{}: framing in several designs, so the sidecar's design is ignored", input_path.display());
+    }
+    let mut reader = open_input(input_path)?;
+    let budget = first.options.memory_budget.unwrap_or(DEFAULT_MEMORY_BUDGET);
+    let overrides = &sidecars.overrides;
+    let design_ids = outputs
+        .iter()
+        .map(|(wm, _)| overrides.design.as_deref().unwrap_or(wm.design_id()))
+        .collect();
+    let output = planned_output(first, OutputFormat::from_path(first_path), design_ids);
+    let (mut decoded, plan) = decode(&mut reader, output, budget)?;
+    sidecars.merge_xmp_into(&mut decoded.exif);
+    let name = input_path.file_stem().and_then(|n| n.to_str());
+
+    // Every design but the last borrows the photo; the last one takes it
+    let mut decoded = Some(decoded);
+    let mut results = Vec::with_capacity(outputs.len());
+    for (i, (wm, output_path)) in outputs.iter().enumerate() {
+        let photo = if i + 1 == outputs.len() {
+            Cow::Owned(decoded.take().expect("photo is only taken by the last design"))
+        } else {
+            Cow::Borrowed(decoded.as_ref().expect("photo is only taken by the last design"))
+        };
+        let format = OutputFormat::from_path(output_path);
+        results.push(write_output(output_path, |writer| {
+            write_decoded(photo, plan, wm, format, name, overrides, writer)
+        }));
+    }
+    Ok(FramedPhoto { overrides: overrides.applied(), results })
+}
+
+/// Opens an input through a buffer rather than loading the whole file.
+fn open_input(input_path: &Path) -> Result<BufReader<fs::File>, String> {
+    let input = fs::File::open(input_path)
+        .map_err(|e| format!("Failed to read {}: {}", input_path.display(), e))?;
+    Ok(BufReader::new(input))
+}
+
+/// Creates `output_path` and its folder and fills it with `write`,
+/// removing the file again if that fails.
+fn write_output<F>(output_path: &Path, write: F) -> Result<(), String>
+where
+    F: FnOnce(&mut BufWriter<fs::File>) -> Result<(), String>,
+{
+    // Ensure output directory exists
+    if let Some(parent) = output_path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create output directory: {}", e))?;
+    }
+
+    let file = fs::File::create(output_path)
+        .map_err(|e| format!("Failed to create output file: {}", e))?;
+    let mut buf = BufWriter::new(file);
+    let result = write(&mut buf).and_then(|_| buf.flush().map_err(|e| format!("Failed to write output: {}", e)));
+    if let Err(e) = result {
+        // Don't leave a half-written file behind
+        drop(buf);
+        let _ = fs::remove_file(output_path);
+        return Err(e);
     }
 
     log::info!("Watermarked image saved: {}", output_path.display());
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode_png(img: &ImageBuffer<Rgba<u8>, Vec<u8>>) -> Vec<u8> {
+        let mut out = Cursor::new(Vec::new());
+        img.write_to(&mut out, image::ImageFormat::Png).unwrap();
+        out.into_inner()
+    }
+
+    #[test]
+    fn capture_date_is_read_from_the_raw_value() {
+        let date = exif::Field {
+            tag: exif::Tag::DateTimeOriginal,
+            ifd_num: exif::In::PRIMARY,
+            value: exif::Value::Ascii(vec![b"2024:01:15 14:30:00".to_vec()]),
+        };
+        let mut writer = exif::experimental::Writer::new();
+        writer.push_field(&date);
+        let mut tiff = Cursor::new(Vec::new());
+        writer.write(&mut tiff, false).unwrap();
+        let tiff = tiff.into_inner();
+
+        let mut jpeg = vec![0xFF, 0xD8, 0xFF, 0xE1];
+        jpeg.extend_from_slice(&((tiff.len() + 8) as u16).to_be_bytes());
+        jpeg.extend_from_slice(b"Exif\0\0");
+        jpeg.extend_from_slice(&tiff);
+        jpeg.extend_from_slice(&[0xFF, 0xD9]);
+
+        // The displayed value, "2024-01-15 14:30:00", came out as "2024-01-15 14.30.00"
+        assert_eq!(parse_exif(&jpeg).date_time, "2024.01.15 14:30:00");
+    }
+
+    #[test]
+    fn lightens_black_monochrome_logo() {
+        let logo = ImageBuffer::from_fn(4, 4, |x, _| {
+            if x < 2 { Rgba([0, 0, 0, 255]) } else { Rgba([0, 0, 0, 0]) }
+        });
+        let light = lighten_monochrome_logo(&encode_png(&logo)).unwrap();
+        let light = image::load_from_memory(&light).unwrap().to_rgba8();
+        assert_eq!(light.get_pixel(0, 0), &Rgba([255, 255, 255, 255]));
+        assert_eq!(light.get_pixel(3, 0)[3], 0);
+    }
+
+    #[test]
+    fn keeps_coloured_logo() {
+        let logo = ImageBuffer::from_pixel(4, 4, Rgba([200, 20, 30, 255]));
+        assert!(lighten_monochrome_logo(&encode_png(&logo)).is_none());
+    }
+
+    #[test]
+    fn frame_pixels_are_demultiplied() {
+        let svg = r##"<svg width="4" height="4" xmlns="http://www.w3.org/2000/svg">
+  <rect width="4" height="4" fill="#FF8000" fill-opacity="0.5"/>
+</svg>"##;
+        let fontdb = Arc::new(fontdb::Database::new());
+        let (pixels, _, _) = render_svg_to_rgba(svg, &fontdb).unwrap();
+        assert_eq!(&pixels[..4], &[255, 128, 0, 128]);
+    }
+
+    #[test]
+    fn sidecar_overrides_change_the_frame_text() {
+        let exif = ExifInfo {
+            camera_model: "X-T5".into(),
+            camera_make: "FUJIFILM".into(),
+            focal_length: "35mm".into(),
+            f_number: "f/2.0".into(),
+            exposure_time: "1/250".into(),
+            iso: "400".into(),
+            lens_model: String::new(),
+            date_time: "2024:05:03 14:22".into(),
+            orientation: 1,
+            xmp: XmpInfo::default(),
+        };
+        let wm = Watermarker::builder().system_fonts(false).build();
+        let svg = |overrides: Overrides| {
+            let per_image = PerImage::new(&wm, None, overrides);
+            build_frame_svg(&wm, &exif, 600, 400, &PhotoSamples::default(), &per_image).svg
+        };
+
+        let plain = svg(Overrides::default());
+        assert!(plain.contains("Fujifilm X-T5") && plain.contains("ISO 400"));
+        let svg = svg(Overrides {
+            camera: Some("Leica M6".into()),
+            hide_exposure: Some(true),
+            caption: Some("Lisbon & the river".into()),
+            ..Default::default()
+        });
+        assert!(svg.contains("Leica M6") && !svg.contains("X-T5"));
+        assert!(!svg.contains("ISO 400"));
+        assert!(svg.contains("Lisbon &amp; the river · 2024:05:03 14:22"));
+    }
+
+    #[test]
+    fn photographer_names_are_escaped() {
+        let exif = parse_exif(&[]);
+        let wm = Watermarker::builder().system_fonts(false).photographer_name("Smith & Wesson").build();
+        let overrides = Overrides { photographer: Some("A & <B>".into()), ..Default::default() };
+        for design in designs::get_design_list() {
+            for (base, name) in [(Overrides::default(), "Smith &amp; Wesson"), (overrides.clone(), "A &amp; &lt;B&gt;")] {
+                let styled = wm.with_design(&design.id, None);
+                let wm = &*base.watermarker(&styled);
+                let per_image = PerImage::new(wm, None, base.clone());
+                for (width, height) in [(600, 400), (400, 600)] {
+                    let svg = build_frame_svg(wm, &exif, width, height, &PhotoSamples::default(), &per_image).svg;
+                    assert!(svg.contains(name), "{} {}x{}", design.id, width, height);
+                    assert!(resvg::usvg::Tree::from_str(&svg, &Default::default()).is_ok(), "{}", design.id);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn backdrop_follows_output_alpha() {
+        let photo = ImageBuffer::from_pixel(2, 2, Rgba([10u8, 20, 30, 0]));
+        let frame = ImageBuffer::from_pixel(2, 1, Rgba([200u8, 0, 0, 128]));
+        let colors = ColorPipeline::new(color::ColorSpace::Srgb, None).unwrap();
+        let run = |format, transparent_frame| {
+            let backdrop = Backdrop::new(format, transparent_frame);
+            composite(photo.clone(), Some(frame.clone()), 1, &colors, backdrop).unwrap()
+        };
+
+        let jpeg = run(OutputFormat::Jpeg, true);
+        assert_eq!(jpeg.get_pixel(0, 0), &Rgba([255, 255, 255, 255]));
+        // Half-transparent red frame over white
+        let [r, g, _, _] = jpeg.get_pixel(0, 2).0;
+        assert!(r > 220 && (120..=135).contains(&g), "{:?}", jpeg.get_pixel(0, 2));
+
+        let png = run(OutputFormat::Png, false);
+        assert_eq!(png.get_pixel(0, 0)[3], 0);
+        assert_eq!(png.get_pixel(0, 2), jpeg.get_pixel(0, 2));
+
+        let transparent = run(OutputFormat::Png, true);
+        assert_eq!(transparent.get_pixel(0, 2), &Rgba([200, 0, 0, 128]));
+    }
+}
@@ -0,0 +1,393 @@
+//! XMP packets, the RDF/XML metadata Lightroom, Capture One and others
+//! write into images and `.xmp` sidecars: title, caption, rating, colour
+//! label and keywords.
+
+use roxmltree::{Document, Node};
+use std::fs;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+const RDF: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#";
+const XML: &str = "http://www.w3.org/XML/1998/namespace";
+const DC: &str = "http://purl.org/dc/elements/1.1/";
+const XMP_BASIC: &str = "http://ns.adobe.com/xap/1.0/";
+
+/// Signature of the APP1 segment holding a JPEG's XMP.
+pub(crate) const JPEG_SIGNATURE: &[u8] = b"http://ns.adobe.com/xap/1.0/\0";
+/// Keyword of the `iTXt` chunk holding a PNG's XMP.
+pub(crate) const PNG_KEYWORD: &[u8] = b"XML:com.adobe.xmp";
+/// TIFF tag holding the XMP packet.
+const TIFF_TAG_XMP: u16 = 700;
+const PACKET_START: &[u8] = b"<x:xmpmeta";
+const PACKET_END: &[u8] = b"</x:xmpmeta>";
+/// Larger packets aren't culling metadata; stop looking.
+const MAX_PACKET: usize = 4 * 1024 * 1024;
+
+struct Property {
+    namespace: String,
+    name: String,
+    /// One value, or the items of a list
+    values: Vec<String>,
+}
+
+/// The simple and list properties of an XMP packet, by namespace URI and
+/// name.
+pub struct Xmp {
+    properties: Vec<Property>,
+}
+
+/// What culling tools record about a photo.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct XmpInfo {
+    /// `dc:title`
+    pub title: String,
+    /// `dc:description`
+    pub caption: String,
+    /// Stars from 1 to 5, 0 for unrated and -1 for rejected; `None` when
+    /// not recorded
+    pub rating: Option<i32>,
+    /// Colour label as the tool names it, e.g. `Red`
+    pub label: String,
+    /// `dc:subject`
+    pub keywords: Vec<String>,
+}
+
+impl Xmp {
+    pub fn parse(xml: &str) -> Result<Self, String> {
+        let doc = Document::parse(xml).map_err(|e| format!("Invalid XMP: {}", e))?;
+        let mut properties = Vec::new();
+        for description in doc.descendants().filter(|n| n.has_tag_name((RDF, "Description"))) {
+            // Properties are written both as attributes and as child elements
+            for attr in description.attributes() {
+                match attr.namespace() {
+                    Some(namespace) if namespace != RDF => properties.push(Property {
+                        namespace: namespace.to_string(),
+                        name: attr.name().to_string(),
+                        values: vec![attr.value().to_string()],
+                    }),
+                    _ => {}
+                }
+            }
+            for child in description.children().filter(Node::is_element) {
+                let tag = child.tag_name();
+                let Some(namespace) = tag.namespace().filter(|ns| *ns != RDF) else {
+                    continue;
+                };
+                if let Some(values) = property_values(child) {
+                    properties.push(Property {
+                        namespace: namespace.to_string(),
+                        name: tag.name().to_string(),
+                        values,
+                    });
+                }
+            }
+        }
+        Ok(Xmp { properties })
+    }
+
+    fn property(&self, namespace: &str, name: &str) -> Option<&Property> {
+        self.properties.iter().find(|p| p.namespace == namespace && p.name == name)
+    }
+
+    /// A simple property, or the first item of a list.
+    pub fn get(&self, namespace: &str, name: &str) -> Option<&str> {
+        self.property(namespace, name)
+            .and_then(|p| p.values.first())
+            .map(String::as_str)
+    }
+
+    /// The items of a list property such as `dc:subject`.
+    pub fn get_list(&self, namespace: &str, name: &str) -> Vec<&str> {
+        self.property(namespace, name)
+            .map(|p| p.values.iter().map(String::as_str).collect())
+            .unwrap_or_default()
+    }
+
+    pub fn info(&self) -> XmpInfo {
+        let text = |namespace, name| self.get(namespace, name).unwrap_or_default().to_string();
+        XmpInfo {
+            title: text(DC, "title"),
+            caption: text(DC, "description"),
+            // Lightroom writes whole stars, other tools sometimes "4.0"
+            rating: self
+                .get(XMP_BASIC, "Rating")
+                .and_then(|r| r.trim().parse::<f64>().ok())
+                .map(|r| r.round().clamp(-1.0, 5.0) as i32),
+            label: text(XMP_BASIC, "Label"),
+            keywords: self.get_list(DC, "subject").into_iter().map(String::from).collect(),
+        }
+    }
+}
+
+/// Text of a simple property, the default of a language alternative such
+/// as `dc:title`, or the items of a bag or sequence such as `dc:subject`.
+fn property_values(node: Node) -> Option<Vec<String>> {
+    let text = |n: Node| n.text().unwrap_or_default().trim().to_string();
+    let Some(container) = node.children().find(Node::is_element) else {
+        return Some(vec![text(node)]);
+    };
+    let items: Vec<Node> = container.children().filter(|n| n.has_tag_name((RDF, "li"))).collect();
+    if container.has_tag_name((RDF, "Alt")) {
+        let default = items.iter().find(|li| li.attribute((XML, "lang")) == Some("x-default"));
+        default.or(items.first()).map(|&li| vec![text(li)])
+    } else if container.has_tag_name((RDF, "Bag")) || container.has_tag_name((RDF, "Seq")) {
+        Some(items.into_iter().map(text).collect())
+    } else {
+        None
+    }
+}
+
+impl XmpInfo {
+    /// These values with the ones `other` sets replaced. A rating of 0
+    /// counts as set, so a sidecar can clear an embedded rating.
+    pub(crate) fn merge(&mut self, other: XmpInfo) {
+        if !other.title.is_empty() {
+            self.title = other.title;
+        }
+        if !other.caption.is_empty() {
+            self.caption = other.caption;
+        }
+        self.rating = other.rating.or(self.rating);
+        if !other.label.is_empty() {
+            self.label = other.label;
+        }
+        if !other.keywords.is_empty() {
+            self.keywords = other.keywords;
+        }
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+/// The XMP packet embedded in an image: only the segment, chunk or tag
+/// that holds it is read, the rest of the file is skipped.
+pub(crate) fn read_embedded<R: Read + Seek>(reader: &mut R) -> Option<String> {
+    reader.rewind().ok()?;
+    let mut magic = [0u8; 12];
+    let read = reader.read(&mut magic).ok()?;
+    let magic = &magic[..read];
+    reader.rewind().ok()?;
+    let packet = if magic.starts_with(&[0xFF, 0xD8]) {
+        reader.seek(SeekFrom::Start(2)).ok()?;
+        jpeg_packet(reader)?
+    } else if magic.starts_with(b"\x89PNG\r\n\x1a\n") {
+        reader.seek(SeekFrom::Start(8)).ok()?;
+        png_packet(reader)?
+    } else if magic.starts_with(b"II*\0") || magic.starts_with(b"MM\0*") {
+        tiff_packet(reader)?
+    } else if magic.len() == 12 && magic.starts_with(b"RIFF") && &magic[8..] == b"WEBP" {
+        reader.seek(SeekFrom::Start(12)).ok()?;
+        webp_packet(reader)?
+    } else {
+        return None;
+    };
+    let start = find(&packet, PACKET_START)?;
+    let end = find(&packet, PACKET_END)? + PACKET_END.len();
+    String::from_utf8(packet.get(start..end)?.to_vec()).ok()
+}
+
+fn jpeg_packet<R: Read>(reader: &mut R) -> Option<Vec<u8>> {
+    loop {
+        let mut header = [0u8; 4];
+        reader.read_exact(&mut header).ok()?;
+        // Start of scan or end of image: no XMP before the image data
+        if header[0] != 0xFF || header[1] == 0xDA || header[1] == 0xD9 {
+            return None;
+        }
+        let length = usize::from(u16::from_be_bytes([header[2], header[3]])).checked_sub(2)?;
+        let mut segment = vec![0; length];
+        reader.read_exact(&mut segment).ok()?;
+        if header[1] == 0xE1 && segment.starts_with(JPEG_SIGNATURE) {
+            return Some(segment.split_off(JPEG_SIGNATURE.len()));
+        }
+    }
+}
+
+/// `length` bytes from the reader, unless that is more than a packet.
+fn read_bytes<R: Read>(reader: &mut R, length: u64) -> Option<Vec<u8>> {
+    let length = usize::try_from(length).ok().filter(|&l| l <= MAX_PACKET)?;
+    let mut data = vec![0; length];
+    reader.read_exact(&mut data).ok()?;
+    Some(data)
+}
+
+/// The uncompressed `iTXt` chunk keyed `XML:com.adobe.xmp`, wherever it is
+/// among the chunks.
+fn png_packet<R: Read + Seek>(reader: &mut R) -> Option<Vec<u8>> {
+    loop {
+        let mut header = [0u8; 8];
+        reader.read_exact(&mut header).ok()?;
+        let length = u32::from_be_bytes([header[0], header[1], header[2], header[3]]);
+        match &header[4..] {
+            b"IEND" => return None,
+            b"iTXt" => {
+                let data = read_bytes(reader, length.into())?;
+                // Keyword, NUL, then the compression flag
+                if data.starts_with(PNG_KEYWORD) && data.get(PNG_KEYWORD.len()..PNG_KEYWORD.len() + 2) == Some(b"\0\0") {
+                    return Some(data);
+                }
+                reader.seek(SeekFrom::Current(4)).ok()?;
+            }
+            _ => {
+                reader.seek(SeekFrom::Current(i64::from(length) + 4)).ok()?;
+            }
+        }
+    }
+}
+
+/// The XMP tag of the first IFD.
+fn tiff_packet<R: Read + Seek>(reader: &mut R) -> Option<Vec<u8>> {
+    let mut header = [0u8; 8];
+    reader.read_exact(&mut header).ok()?;
+    let big_endian = header[0] == b'M';
+    let u16_at = |b: &[u8]| if big_endian { u16::from_be_bytes([b[0], b[1]]) } else { u16::from_le_bytes([b[0], b[1]]) };
+    let u32_at = |b: &[u8]| {
+        let b = [b[0], b[1], b[2], b[3]];
+        if big_endian { u32::from_be_bytes(b) } else { u32::from_le_bytes(b) }
+    };
+    reader.seek(SeekFrom::Start(u32_at(&header[4..]).into())).ok()?;
+    let mut count = [0u8; 2];
+    reader.read_exact(&mut count).ok()?;
+    let entries = read_bytes(reader, u64::from(u16_at(&count)) * 12)?;
+    let entry = entries.chunks_exact(12).find(|e| u16_at(e) == TIFF_TAG_XMP)?;
+    // BYTE or UNDEFINED values; short packets would sit in the entry itself
+    let length = u32_at(&entry[4..]);
+    if length <= 4 {
+        return None;
+    }
+    reader.seek(SeekFrom::Start(u32_at(&entry[8..]).into())).ok()?;
+    read_bytes(reader, length.into())
+}
+
+/// The `XMP ` chunk of a WebP's RIFF container.
+fn webp_packet<R: Read + Seek>(reader: &mut R) -> Option<Vec<u8>> {
+    loop {
+        let mut header = [0u8; 8];
+        reader.read_exact(&mut header).ok()?;
+        let length = u32::from_le_bytes([header[4], header[5], header[6], header[7]]);
+        if &header[..4] == b"XMP " {
+            return read_bytes(reader, length.into());
+        }
+        // Chunks are padded to an even size
+        reader.seek(SeekFrom::Current(i64::from(length) + i64::from(length & 1))).ok()?;
+    }
+}
+
+/// XMP sidecars as Lightroom (`IMG_0001.xmp`) and Capture One or
+/// darktable (`IMG_0001.jpg.xmp`) name them.
+pub(crate) fn sidecar_paths(input_path: &Path) -> [PathBuf; 2] {
+    let mut full_name = input_path.as_os_str().to_owned();
+    full_name.push(".xmp");
+    [input_path.with_extension("xmp"), PathBuf::from(full_name)]
+}
+
+/// The XMP sidecar of `input_path`, if it has one.
+pub fn read_sidecar(input_path: &Path) -> Result<Option<Xmp>, String> {
+    let Some(path) = sidecar_paths(input_path).into_iter().find(|p| p.exists()) else {
+        return Ok(None);
+    };
+    let data = fs::read_to_string(&path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    Xmp::parse(&data).map(Some).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    const LIGHTROOM: &str = r#"<x:xmpmeta xmlns:x="adobe:ns:meta/" x:xmptk="Adobe XMP Core 7.0">
+ <rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">
+  <rdf:Description rdf:about=""
+    xmlns:xmp="http://ns.adobe.com/xap/1.0/"
+    xmlns:dc="http://purl.org/dc/elements/1.1/"
+   xmp:Rating="4" xmp:Label="Red">
+   <dc:title><rdf:Alt><rdf:li xml:lang="de">Abend</rdf:li><rdf:li xml:lang="x-default">Evening</rdf:li></rdf:Alt></dc:title>
+   <dc:description><rdf:Alt><rdf:li xml:lang="x-default">Tram 28 &amp; the river</rdf:li></rdf:Alt></dc:description>
+   <dc:subject><rdf:Bag><rdf:li>Lisbon</rdf:li><rdf:li>tram</rdf:li></rdf:Bag></dc:subject>
+  </rdf:Description>
+ </rdf:RDF>
+</x:xmpmeta>"#;
+
+    #[test]
+    fn reads_lightroom_fields() {
+        let info = Xmp::parse(LIGHTROOM).unwrap().info();
+        assert_eq!(
+            info,
+            XmpInfo {
+                title: "Evening".into(),
+                caption: "Tram 28 & the river".into(),
+                rating: Some(4),
+                label: "Red".into(),
+                keywords: vec!["Lisbon".into(), "tram".into()],
+            }
+        );
+        let rejected = LIGHTROOM.replace(r#"xmp:Rating="4""#, r#"xmp:Rating="-1""#);
+        assert_eq!(Xmp::parse(&rejected).unwrap().info().rating, Some(-1));
+    }
+
+    #[test]
+    fn sidecar_rating_replaces_embedded_one() {
+        let mut embedded = Xmp::parse(LIGHTROOM).unwrap().info();
+        embedded.merge(XmpInfo { title: "Dusk".into(), ..Default::default() });
+        assert_eq!((embedded.title.as_str(), embedded.rating), ("Dusk", Some(4)));
+        let cleared = LIGHTROOM.replace(r#"xmp:Rating="4""#, r#"xmp:Rating="0""#);
+        embedded.merge(Xmp::parse(&cleared).unwrap().info());
+        assert_eq!(embedded.rating, Some(0));
+    }
+
+    #[test]
+    fn finds_embedded_packets() {
+        // JPEG: SOI, APP0, APP1 with the XMP, then start of scan
+        let mut jpeg = vec![0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x04, 0x00, 0x00];
+        let segment = [JPEG_SIGNATURE, LIGHTROOM.as_bytes()].concat();
+        jpeg.extend_from_slice(&[0xFF, 0xE1]);
+        jpeg.extend_from_slice(&(segment.len() as u16 + 2).to_be_bytes());
+        jpeg.extend_from_slice(&segment);
+        jpeg.extend_from_slice(&[0xFF, 0xDA, 0x00, 0x02]);
+        let packet = read_embedded(&mut Cursor::new(&jpeg)).unwrap();
+        assert_eq!(Xmp::parse(&packet).unwrap().info().rating, Some(4));
+
+        // PNG: the iTXt chunk may follow the image data
+        let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+        let chunk = |png: &mut Vec<u8>, kind: &[u8], data: &[u8]| {
+            png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+            png.extend_from_slice(kind);
+            png.extend_from_slice(data);
+            png.extend_from_slice(&[0; 4]);
+        };
+        chunk(&mut png, b"IDAT", &[0; 100]);
+        chunk(&mut png, b"iTXt", &[b"Comment\0\0\0\0\0", LIGHTROOM.as_bytes()].concat());
+        chunk(&mut png, b"iTXt", &[PNG_KEYWORD, b"\0\0\0\0\0", LIGHTROOM.as_bytes()].concat());
+        chunk(&mut png, b"IEND", &[]);
+        let packet = read_embedded(&mut Cursor::new(&png)).unwrap();
+        assert_eq!(Xmp::parse(&packet).unwrap().info().label, "Red");
+
+        // Little-endian TIFF: one IFD entry pointing past the image data
+        let mut tiff = b"II*\0\x08\0\0\0\x01\0".to_vec();
+        tiff.extend_from_slice(&TIFF_TAG_XMP.to_le_bytes());
+        tiff.extend_from_slice(&7u16.to_le_bytes());
+        tiff.extend_from_slice(&(LIGHTROOM.len() as u32).to_le_bytes());
+        tiff.extend_from_slice(&1000u32.to_le_bytes());
+        tiff.resize(1000, 0);
+        tiff.extend_from_slice(LIGHTROOM.as_bytes());
+        let packet = read_embedded(&mut Cursor::new(&tiff)).unwrap();
+        assert_eq!(Xmp::parse(&packet).unwrap().info().keywords, ["Lisbon", "tram"]);
+
+        // WebP: an odd-sized chunk before the XMP one is padded
+        let mut webp = b"RIFF\0\0\0\0WEBPVP8L\x03\0\0\0\x2f\0\0\0".to_vec();
+        webp.extend_from_slice(b"XMP ");
+        webp.extend_from_slice(&(LIGHTROOM.len() as u32).to_le_bytes());
+        webp.extend_from_slice(LIGHTROOM.as_bytes());
+        let packet = read_embedded(&mut Cursor::new(&webp)).unwrap();
+        assert_eq!(Xmp::parse(&packet).unwrap().info().title, "Evening");
+
+        // A packet outside its container's XMP slot isn't looked for
+        let mut stray = png[..8].to_vec();
+        chunk(&mut stray, b"tEXt", LIGHTROOM.as_bytes());
+        chunk(&mut stray, b"IEND", &[]);
+        assert_eq!(read_embedded(&mut Cursor::new(&stray)), None);
+        assert_eq!(read_embedded(&mut Cursor::new(vec![0xFF, 0xD8, 0xFF, 0xD9])), None);
+    }
+}
@@ -0,0 +1,254 @@
+//! Golden-image and SVG snapshot tests for every design.
+//!
+//! Synthetic photos with crafted EXIF are framed with `Watermarker`
+//! and compared against `tests/golden/*.png` within a small tolerance; the
+//! raw SVG templates are compared byte-for-byte against `tests/snapshots`.
+//! Run with `UPDATE_GOLDEN=1` to regenerate both after an intended change.
+
+use app_lib::designs::{self, LandscapeParams, PortraitParams};
+use app_lib::{fonts, watermark, LogoSource, Watermarker};
+use exif::{Field, In, Rational, Tag, Value};
+use image::codecs::jpeg::JpegEncoder;
+use image::{Rgb, RgbImage, RgbaImage};
+use std::fs;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+
+/// Channel difference above which a pixel counts as changed.
+const PIXEL_TOLERANCE: u8 = 24;
+/// Share of changed pixels allowed before a golden comparison fails.
+const MAX_CHANGED_RATIO: f64 = 0.002;
+
+fn manifest_dir() -> &'static Path {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+}
+
+fn update_requested() -> bool {
+    std::env::var_os("UPDATE_GOLDEN").is_some()
+}
+
+fn scratch_dir() -> PathBuf {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("golden");
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+// ─── Synthetic input ──────────────────────────────────
+
+fn exif_fields() -> Vec<Field> {
+    let ascii = |tag, s: &str| Field {
+        tag,
+        ifd_num: In::PRIMARY,
+        value: Value::Ascii(vec![s.as_bytes().to_vec()]),
+    };
+    let rational = |tag, num, denom| Field {
+        tag,
+        ifd_num: In::PRIMARY,
+        value: Value::Rational(vec![Rational { num, denom }]),
+    };
+    vec![
+        ascii(Tag::Make, "SONY"),
+        ascii(Tag::Model, "ILCE-7M4"),
+        rational(Tag::FocalLength, 35, 1),
+        rational(Tag::FNumber, 28, 10),
+        rational(Tag::ExposureTime, 1, 250),
+        Field {
+            tag: Tag::PhotographicSensitivity,
+            ifd_num: In::PRIMARY,
+            value: Value::Short(vec![400]),
+        },
+        ascii(Tag::DateTimeOriginal, "2024:01:15 14:30:00"),
+    ]
+}
+
+/// JPEG of flat, block-aligned colour bands (so decoding is exact enough to
+/// compare) with an APP1 EXIF segment spliced in after SOI.
+fn synthetic_jpeg(width: u32, height: u32) -> Vec<u8> {
+    let palette = [Rgb([200, 80, 40]), Rgb([40, 120, 200]), Rgb([60, 160, 90]), Rgb([230, 210, 120])];
+    let img = RgbImage::from_fn(width, height, |x, y| {
+        palette[((x / 64 + y / 64) % palette.len() as u32) as usize]
+    });
+
+    let mut jpeg = Vec::new();
+    JpegEncoder::new_with_quality(&mut jpeg, 95).encode_image(&img).unwrap();
+
+    let fields = exif_fields();
+    let mut writer = exif::experimental::Writer::new();
+    for field in &fields {
+        writer.push_field(field);
+    }
+    let mut tiff = Cursor::new(Vec::new());
+    writer.write(&mut tiff, false).unwrap();
+    let tiff = tiff.into_inner();
+
+    let mut app1 = vec![0xFF, 0xE1];
+    app1.extend_from_slice(&((tiff.len() + 8) as u16).to_be_bytes());
+    app1.extend_from_slice(b"Exif\0\0");
+    app1.extend_from_slice(&tiff);
+
+    let mut out = jpeg[..2].to_vec();
+    out.extend_from_slice(&app1);
+    out.extend_from_slice(&jpeg[2..]);
+    out
+}
+
+// ─── Comparison ───────────────────────────────────────
+
+fn compare_with_golden(name: &str, actual: &RgbaImage) {
+    let golden_path = manifest_dir().join("tests").join("golden").join(format!("{}.png", name));
+
+    if update_requested() {
+        fs::create_dir_all(golden_path.parent().unwrap()).unwrap();
+        actual.save(&golden_path).unwrap();
+        return;
+    }
+    assert!(golden_path.exists(), "{}: no golden image, run with UPDATE_GOLDEN=1", name);
+
+    let golden = image::open(&golden_path).unwrap().to_rgba8();
+    assert_eq!(golden.dimensions(), actual.dimensions(), "{}: size changed", name);
+
+    let changed = golden
+        .pixels()
+        .zip(actual.pixels())
+        .filter(|(g, a)| g.0.iter().zip(a.0.iter()).any(|(g, a)| g.abs_diff(*a) > PIXEL_TOLERANCE))
+        .count();
+    let ratio = changed as f64 / (golden.width() * golden.height()) as f64;
+
+    if ratio > MAX_CHANGED_RATIO {
+        let failure = scratch_dir().join(format!("{}.actual.png", name));
+        actual.save(&failure).unwrap();
+        panic!(
+            "{}: {:.3}% of pixels differ from {} (actual written to {})",
+            name,
+            ratio * 100.0,
+            golden_path.display(),
+            failure.display()
+        );
+    }
+}
+
+fn compare_with_snapshot(name: &str, actual: &str) {
+    let snapshot_path = manifest_dir().join("tests").join("snapshots").join(format!("{}.svg", name));
+
+    if update_requested() {
+        fs::create_dir_all(snapshot_path.parent().unwrap()).unwrap();
+        fs::write(&snapshot_path, actual).unwrap();
+        return;
+    }
+    assert!(snapshot_path.exists(), "{}: no snapshot, run with UPDATE_GOLDEN=1", name);
+
+    let expected = fs::read_to_string(&snapshot_path).unwrap();
+    assert_eq!(expected, actual, "{}: SVG template changed", name);
+}
+
+// ─── Golden images ────────────────────────────────────
+
+fn render_design(design_id: &str, width: u32, height: u32) -> RgbaImage {
+    // Bundled fonts only, so results don't depend on the host's font set
+    let wm = Watermarker::builder()
+        .design(design_id)
+        .photographer_name("Jane Doe")
+        .logos(LogoSource::Directory(manifest_dir().join("assets").join("models")))
+        .system_fonts(false)
+        .build();
+
+    let dir = scratch_dir();
+    let input = dir.join(format!("{}-{}x{}-input.jpg", design_id, width, height));
+    let output = dir.join(format!("{}-{}x{}-output.png", design_id, width, height));
+    fs::write(&input, synthetic_jpeg(width, height)).unwrap();
+
+    wm.process_path(&input, &output).unwrap();
+
+    image::open(&output).unwrap().to_rgba8()
+}
+
+#[test]
+fn golden_images_for_every_design() {
+    for design in designs::get_design_list() {
+        for (orientation, width, height) in [("landscape", 640, 448), ("portrait", 448, 640)] {
+            let actual = render_design(&design.id, width, height);
+            compare_with_golden(&format!("{}-{}", design.id, orientation), &actual);
+        }
+    }
+}
+
+#[test]
+fn exif_is_read_from_synthetic_input() {
+    let info = watermark::parse_exif(&synthetic_jpeg(64, 64));
+    assert_eq!(info.camera_make, "SONY");
+    assert_eq!(info.camera_model, "ILCE-7M4");
+    assert_eq!(info.focal_length, "35mm");
+    assert_eq!(info.f_number, "f/2.8");
+    assert_eq!(info.exposure_time, "1/250");
+    assert_eq!(info.iso, "400");
+    assert_eq!(info.date_time, "2024.01.15 14:30:00");
+}
+
+// ─── SVG snapshots ────────────────────────────────────
+
+const LOGO_ELEMENT: &str =
+    r##"<image x="1000" y="20" width="90" height="60" href="data:image/png;base64,AAAA" preserveAspectRatio="xMidYMid meet" />"##;
+
+fn portrait_params() -> PortraitParams {
+    PortraitParams {
+        image_width: 800,
+        frame_height: 120.0,
+        center_x: 400.0,
+        logo_element: LOGO_ELEMENT.into(),
+        camera_info: "ILCE-7M4".into(),
+        exposure_info: "35mm | f/2.8 | 1/250 | ISO 400".into(),
+        date_time_string: "2024.01.15 14:30:00".into(),
+        font_size: 36.0,
+        small_font_size: 27.0,
+        font_family: fonts::family_for_design("classic", None),
+        photographer_name: "Jane Doe".into(),
+        palette: None,
+        swatches: Vec::new(),
+        histogram: None,
+        qr: None,
+        title: String::new(),
+        caption: String::new(),
+    }
+}
+
+fn landscape_params() -> LandscapeParams {
+    LandscapeParams {
+        image_width: 1200,
+        frame_height: 80.0,
+        center_y: 40.0,
+        text_adjustment: 6.0,
+        logo_adjustment: 2.0,
+        logo_element: LOGO_ELEMENT.into(),
+        camera_info: "ILCE-7M4".into(),
+        exposure_info: "35mm | f/2.8 | 1/250 | ISO 400".into(),
+        date_time_string: "2024.01.15 14:30:00".into(),
+        left_text_x: 30.0,
+        right_text_x: 900.0,
+        exposure_y: 26.0,
+        date_y: 58.0,
+        divider_x: 940.0,
+        adjusted_divider_top: 18.0,
+        adjusted_divider_bottom: 66.0,
+        font_size: 24.0,
+        small_font_size: 18.0,
+        font_family: fonts::family_for_design("classic", None),
+        photographer_name: "Jane Doe".into(),
+        palette: None,
+        swatches: Vec::new(),
+        histogram: None,
+        qr: None,
+        title: String::new(),
+        caption: String::new(),
+    }
+}
+
+#[test]
+fn svg_snapshots_for_every_design() {
+    for design in designs::get_design_list() {
+        let portrait = designs::render_portrait(&design.id, &portrait_params());
+        compare_with_snapshot(&format!("{}-portrait", design.id), &portrait);
+
+        let landscape = designs::render_landscape(&design.id, &landscape_params());
+        compare_with_snapshot(&format!("{}-landscape", design.id), &landscape);
+    }
+}